@@ -0,0 +1,196 @@
+mod xsk;
+
+use std::ffi::CString;
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::Context;
+
+use aya::maps::XskMap;
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+
+use clap::Parser;
+use log::{debug, info};
+use tokio::{signal, task};
+
+use xsk::{BindMode, XskSocket};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Receive queue of the interface the AF_XDP socket is bound to.
+    #[clap(short, long, default_value_t = 0)]
+    queue: u32,
+
+    /// Bind the socket in the zero copy mode, instead of the copy mode (requires driver support).
+    #[clap(long)]
+    zero_copy: bool,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+const ETH_HLEN: usize = 14;
+const IPV6_HLEN: usize = 40;
+const ETH_P_IPV6: u16 = 0x86DD;
+const IPPROTO_ICMPV6: u8 = 58;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+
+// Updates the Internet checksum `check` when a 16 bit word is changed from `old` to `new` (See
+// RFC 1624). Same as `csum_replace2` in the 'packet02-rewriting' tutorial.
+fn csum_replace2(check: u16, old: u16, new: u16) -> u16 {
+    let mut sum = (!check as u32) + (!old as u32) + (new as u32);
+    sum = (sum & 0xFFFF) + (sum >> 16);
+    sum = (sum & 0xFFFF) + (sum >> 16);
+
+    !(sum as u16)
+}
+
+// Turns an ICMPv6 echo request into an echo reply in place, returns `false` if the packet is not
+// an ICMPv6 echo request.
+//
+// This is the Userspace version of the ICMP echo responder of the 'packet03-redirecting'
+// tutorial, the MAC and the IPv6 addresses are swapped and the ICMPv6 type is changed.
+fn icmpv6_echo_reply(pkt: &mut [u8]) -> bool {
+    if pkt.len() < ETH_HLEN + IPV6_HLEN + 8 {
+        return false;
+    }
+    if u16::from_be_bytes([pkt[12], pkt[13]]) != ETH_P_IPV6 {
+        return false;
+    }
+    let ip6 = ETH_HLEN;
+    if pkt[ip6 + 6] != IPPROTO_ICMPV6 {
+        return false;
+    }
+    let icmp6 = ip6 + IPV6_HLEN;
+    if pkt[icmp6] != ICMPV6_ECHO_REQUEST {
+        return false;
+    }
+
+    // Ethernet: destination (0..6) and source (6..12) MAC addresses.
+    let (dest, source) = pkt[..12].split_at_mut(6);
+    dest.swap_with_slice(source);
+
+    // IPv6: source (8..24) and destination (24..40) addresses.
+    let (saddr, daddr) = pkt[ip6 + 8..ip6 + 40].split_at_mut(16);
+    saddr.swap_with_slice(daddr);
+
+    let code = pkt[icmp6 + 1];
+    let old = u16::from_be_bytes([ICMPV6_ECHO_REQUEST, code]);
+    let new = u16::from_be_bytes([ICMPV6_ECHO_REPLY, code]);
+    let check = u16::from_be_bytes([pkt[icmp6 + 2], pkt[icmp6 + 3]]);
+    pkt[icmp6] = ICMPV6_ECHO_REPLY;
+    pkt[icmp6 + 2..icmp6 + 4].copy_from_slice(&csum_replace2(check, old, new).to_be_bytes());
+
+    true
+}
+
+// Processes the packets received on the socket until `stop` is set. The echo requests are sent
+// back as echo replies using the same frame. The XDP program only redirects the echo requests, any
+// other packet is dropped (its frame is given back to the kernel).
+fn process_packets(mut xsk: XskSocket, stop: Arc<AtomicBool>) -> anyhow::Result<(u64, u64)> {
+    let (mut received, mut replied) = (0u64, 0u64);
+
+    while !stop.load(Ordering::Relaxed) {
+        if !xsk.poll(1000)? {
+            continue;
+        }
+
+        let mut sent = false;
+        while let Some(desc) = xsk.recv() {
+            received += 1;
+            if icmpv6_echo_reply(xsk.frame_mut(&desc)) && xsk.send(desc) {
+                replied += 1;
+                sent = true;
+                debug!("Sent an ICMPv6 echo reply ({} bytes)", desc.len);
+            } else {
+                xsk.release(&desc);
+            }
+        }
+
+        if sent {
+            xsk.kick_tx();
+        }
+        xsk.reclaim_completed();
+        xsk.refill();
+    }
+
+    Ok((received, replied))
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The runner also creates an AF_XDP socket bound to a queue of the interface and adds the socket
+// to the `XSKS_MAP`, the XDP program redirects the ICMPv6 echo requests on the queue to the
+// socket. The packets are processed in a blocking task, until Ctrl-C is pressed.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let iface = CString::new(opts.iface.as_str())?;
+    let ifindex = unsafe { libc::if_nametoindex(iface.as_ptr()) };
+    if ifindex == 0 {
+        return Err(anyhow::Error::msg(format!(
+            "Interface '{}' not found",
+            opts.iface
+        )));
+    }
+
+    let mode = if opts.zero_copy {
+        BindMode::ZeroCopy
+    } else {
+        BindMode::Copy
+    };
+    let xsk =
+        XskSocket::new(ifindex, opts.queue, mode).context("Failed to create the AF_XDP socket")?;
+    let mut xsks_map = XskMap::try_from(bpf.map_mut("XSKS_MAP").unwrap())?;
+    xsks_map.set(opts.queue, xsk.as_raw_fd(), 0)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    info!(
+        "XDP Program '{}' attached to '{}', AF_XDP socket bound to queue {}! Now waiting for Ctrl-C",
+        program_name, &opts.iface, opts.queue
+    );
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let processor = task::spawn_blocking({
+        let stop = stop.clone();
+        move || process_packets(xsk, stop)
+    });
+
+    signal::ctrl_c().await?;
+    info!("Exiting...");
+    stop.store(true, Ordering::Relaxed);
+
+    let (received, replied) = processor.await??;
+    info!(
+        "Received {} packets, sent {} echo replies",
+        received, replied
+    );
+
+    Ok(())
+}
@@ -0,0 +1,381 @@
+// An AF_XDP socket with its UMEM and rings, using only the system calls (through `libc`).
+//
+// The UMEM is a memory area registered with the kernel, divided into frames of `FRAME_SIZE`
+// bytes. The frames are passed between the kernel and the Userspace using four rings -
+//
+// 1. Fill Ring - Userspace gives the free frames to the kernel for receiving the packets.
+// 2. RX Ring - Kernel gives the frames with the received packets to the Userspace.
+// 3. TX Ring - Userspace gives the frames with the packets to be sent to the kernel.
+// 4. Completion Ring - Kernel gives back the frames of the sent packets to the Userspace.
+//
+// Every ring is a single producer, single consumer ring, shared with the kernel using `mmap`.
+
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::ptr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use libc::{sockaddr_xdp, xdp_desc, xdp_mmap_offsets, xdp_ring_offset, xdp_umem_reg};
+
+/// Size of every frame in the UMEM.
+pub const FRAME_SIZE: u32 = 4096;
+
+/// Number of frames in the UMEM.
+pub const NUM_FRAMES: u32 = 4096;
+
+/// Number of descriptors in every ring (should be a power of 2).
+pub const RING_SIZE: u32 = 2048;
+
+/// How the packets are passed between the driver and the UMEM, set when the socket is bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindMode {
+    /// `XDP_COPY` - the packets are copied between the buffers of the driver and the UMEM, which
+    /// works on all the interfaces (including `veth`).
+    Copy,
+    /// `XDP_ZEROCOPY` - the driver receives the packets directly into the UMEM, the bind fails if
+    /// the driver does not support it.
+    ZeroCopy,
+}
+
+impl BindMode {
+    fn flags(self) -> u16 {
+        match self {
+            BindMode::Copy => libc::XDP_COPY,
+            BindMode::ZeroCopy => libc::XDP_ZEROCOPY,
+        }
+    }
+}
+
+// Returns the address of the frame of a descriptor. In the aligned mode, the address may point
+// into the frame (after the headroom).
+fn frame_addr(addr: u64) -> u64 {
+    addr - addr % FRAME_SIZE as u64
+}
+
+fn last_os_error(what: &str) -> io::Error {
+    let e = io::Error::last_os_error();
+    io::Error::new(e.kind(), format!("{what}: {e}"))
+}
+
+// A ring shared with the kernel, the descriptors are `T` (`u64` frame addresses for the Fill and
+// the Completion rings and `xdp_desc` for the RX and the TX rings).
+struct Ring<T> {
+    map: *mut libc::c_void,
+    map_len: usize,
+    producer: *const AtomicU32,
+    consumer: *const AtomicU32,
+    descs: *mut T,
+}
+
+impl<T: Copy> Ring<T> {
+    // Maps the ring (whose size is set using the socket option `opt`) at the page offset `pgoff`,
+    // the offsets of the fields in the mapping are from `XDP_MMAP_OFFSETS`.
+    fn new(
+        fd: RawFd,
+        opt: libc::c_int,
+        pgoff: libc::off_t,
+        off: &xdp_ring_offset,
+    ) -> io::Result<Self> {
+        let map_len = off.desc as usize + RING_SIZE as usize * mem::size_of::<T>();
+        let map = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED | libc::MAP_POPULATE,
+                fd,
+                pgoff,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(last_os_error(&format!(
+                "Failed to mmap the ring (option: {opt})"
+            )));
+        }
+
+        let base = map as usize;
+        Ok(Self {
+            map,
+            map_len,
+            producer: (base + off.producer as usize) as *const AtomicU32,
+            consumer: (base + off.consumer as usize) as *const AtomicU32,
+            descs: (base + off.desc as usize) as *mut T,
+        })
+    }
+
+    // Adds a descriptor to a ring produced by the Userspace (Fill and TX), returns `false` if the
+    // ring is full.
+    fn push(&mut self, desc: T) -> bool {
+        let (producer, consumer) = unsafe { (&*self.producer, &*self.consumer) };
+        let prod = producer.load(Ordering::Relaxed);
+        if prod.wrapping_sub(consumer.load(Ordering::Acquire)) >= RING_SIZE {
+            return false;
+        }
+
+        unsafe {
+            self.descs
+                .add((prod & (RING_SIZE - 1)) as usize)
+                .write(desc)
+        };
+        // The descriptor has to be visible to the kernel before the producer index.
+        producer.store(prod.wrapping_add(1), Ordering::Release);
+
+        true
+    }
+
+    // Removes a descriptor from a ring produced by the kernel (RX and Completion).
+    fn pop(&mut self) -> Option<T> {
+        let (producer, consumer) = unsafe { (&*self.producer, &*self.consumer) };
+        let cons = consumer.load(Ordering::Relaxed);
+        if cons == producer.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let desc = unsafe { self.descs.add((cons & (RING_SIZE - 1)) as usize).read() };
+        consumer.store(cons.wrapping_add(1), Ordering::Release);
+
+        Some(desc)
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.map, self.map_len) };
+    }
+}
+
+// The memory area of the UMEM, `NUM_FRAMES * FRAME_SIZE` bytes.
+struct Umem {
+    area: *mut u8,
+}
+
+impl Drop for Umem {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(
+                self.area as *mut libc::c_void,
+                (NUM_FRAMES * FRAME_SIZE) as usize,
+            )
+        };
+    }
+}
+
+pub struct XskSocket {
+    // The fields are dropped in the order of declaration, the rings are unmapped before the
+    // socket is closed and the UMEM is freed last.
+    fill: Ring<u64>,
+    comp: Ring<u64>,
+    rx: Ring<xdp_desc>,
+    tx: Ring<xdp_desc>,
+    fd: OwnedFd,
+    umem: Umem,
+    free_frames: Vec<u64>,
+}
+
+// The raw pointers are to the memory owned by the socket, the socket is only used from one thread
+// at a time.
+unsafe impl Send for XskSocket {}
+
+impl XskSocket {
+    /// Creates an AF_XDP socket bound to the queue `queue_id` of the interface `ifindex` in the
+    /// `mode`.
+    pub fn new(ifindex: u32, queue_id: u32, mode: BindMode) -> io::Result<Self> {
+        let fd = unsafe { libc::socket(libc::AF_XDP, libc::SOCK_RAW, 0) };
+        if fd < 0 {
+            return Err(last_os_error("Failed to create the AF_XDP socket"));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let raw_fd = fd.as_raw_fd();
+
+        let umem_len = (NUM_FRAMES * FRAME_SIZE) as usize;
+        let umem = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                umem_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if umem == libc::MAP_FAILED {
+            return Err(last_os_error("Failed to allocate the UMEM"));
+        }
+        let umem = Umem {
+            area: umem as *mut u8,
+        };
+
+        let mut reg: xdp_umem_reg = unsafe { mem::zeroed() };
+        reg.addr = umem.area as u64;
+        reg.len = umem_len as u64;
+        reg.chunk_size = FRAME_SIZE;
+        reg.headroom = 0;
+        setsockopt(raw_fd, libc::XDP_UMEM_REG, &reg)
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to register the UMEM: {e}")))?;
+
+        for opt in [
+            libc::XDP_UMEM_FILL_RING,
+            libc::XDP_UMEM_COMPLETION_RING,
+            libc::XDP_RX_RING,
+            libc::XDP_TX_RING,
+        ] {
+            setsockopt(raw_fd, opt, &(RING_SIZE as libc::c_int))?;
+        }
+
+        let mut off: xdp_mmap_offsets = unsafe { mem::zeroed() };
+        let mut optlen = mem::size_of::<xdp_mmap_offsets>() as libc::socklen_t;
+        let ret = unsafe {
+            libc::getsockopt(
+                raw_fd,
+                libc::SOL_XDP,
+                libc::XDP_MMAP_OFFSETS,
+                &mut off as *mut _ as *mut libc::c_void,
+                &mut optlen,
+            )
+        };
+        if ret != 0 {
+            return Err(last_os_error("Failed to get the offsets of the rings"));
+        }
+
+        let fill = Ring::new(
+            raw_fd,
+            libc::XDP_UMEM_FILL_RING,
+            libc::XDP_UMEM_PGOFF_FILL_RING as libc::off_t,
+            &off.fr,
+        )?;
+        let comp = Ring::new(
+            raw_fd,
+            libc::XDP_UMEM_COMPLETION_RING,
+            libc::XDP_UMEM_PGOFF_COMPLETION_RING as libc::off_t,
+            &off.cr,
+        )?;
+        let rx = Ring::new(raw_fd, libc::XDP_RX_RING, libc::XDP_PGOFF_RX_RING, &off.rx)?;
+        let tx = Ring::new(raw_fd, libc::XDP_TX_RING, libc::XDP_PGOFF_TX_RING, &off.tx)?;
+
+        let mut addr: sockaddr_xdp = unsafe { mem::zeroed() };
+        addr.sxdp_family = libc::AF_XDP as u16;
+        addr.sxdp_ifindex = ifindex;
+        addr.sxdp_queue_id = queue_id;
+        addr.sxdp_flags = mode.flags();
+        let ret = unsafe {
+            libc::bind(
+                raw_fd,
+                &addr as *const _ as *const libc::sockaddr,
+                mem::size_of::<sockaddr_xdp>() as libc::socklen_t,
+            )
+        };
+        if ret != 0 {
+            return Err(last_os_error("Failed to bind the AF_XDP socket"));
+        }
+
+        let mut xsk = Self {
+            fill,
+            comp,
+            rx,
+            tx,
+            fd,
+            umem,
+            free_frames: (0..NUM_FRAMES).map(|i| (i * FRAME_SIZE) as u64).collect(),
+        };
+        xsk.refill();
+
+        Ok(xsk)
+    }
+
+    /// Gives the free frames to the kernel for receiving the packets.
+    pub fn refill(&mut self) {
+        while let Some(addr) = self.free_frames.pop() {
+            if !self.fill.push(addr) {
+                self.free_frames.push(addr);
+                break;
+            }
+        }
+    }
+
+    /// Takes back the frames of the packets that are sent by the kernel.
+    pub fn reclaim_completed(&mut self) {
+        while let Some(addr) = self.comp.pop() {
+            self.free_frames.push(frame_addr(addr));
+        }
+    }
+
+    /// Returns the descriptor of the next received packet.
+    pub fn recv(&mut self) -> Option<xdp_desc> {
+        self.rx.pop()
+    }
+
+    /// Returns the frame of the packet as a mutable slice.
+    pub fn frame_mut(&mut self, desc: &xdp_desc) -> &mut [u8] {
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.umem.area.add(desc.addr as usize),
+                desc.len as usize,
+            )
+        }
+    }
+
+    /// Returns the frame of a packet that is not sent, to the free frames.
+    pub fn release(&mut self, desc: &xdp_desc) {
+        self.free_frames.push(frame_addr(desc.addr));
+    }
+
+    /// Sends the packet in the frame of the descriptor, returns `false` if the TX ring is full.
+    pub fn send(&mut self, desc: xdp_desc) -> bool {
+        self.tx.push(desc)
+    }
+
+    /// Tells the kernel to send the packets in the TX ring. This is required in the copy mode.
+    pub fn kick_tx(&self) {
+        unsafe {
+            libc::sendto(
+                self.fd.as_raw_fd(),
+                ptr::null(),
+                0,
+                libc::MSG_DONTWAIT,
+                ptr::null(),
+                0,
+            )
+        };
+    }
+
+    /// Waits for the packets to be received, for at most `timeout_ms` milliseconds.
+    pub fn poll(&self, timeout_ms: i32) -> io::Result<bool> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let ret = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+        if ret < 0 {
+            return Err(last_os_error("Failed to poll the AF_XDP socket"));
+        }
+
+        Ok(ret > 0)
+    }
+}
+
+impl AsRawFd for XskSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+fn setsockopt<T>(fd: RawFd, opt: libc::c_int, value: &T) -> io::Result<()> {
+    let ret = unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_XDP,
+            opt,
+            value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if ret != 0 {
+        return Err(last_os_error(&format!(
+            "Failed to set the socket option {opt}"
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    macros::{map, xdp},
+    maps::{PerCpuArray, XskMap},
+    programs::XdpContext,
+};
+
+// The AF_XDP sockets, indexed by the receive queue of the interface. The Userspace program adds
+// its socket at the index of the queue the socket is bound to.
+#[map]
+static XSKS_MAP: XskMap = XskMap::with_max_entries(64, 0);
+
+// Number of packets redirected on every receive queue (index `0` to `63`).
+#[map]
+static XDP_STATS_MAP: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(64, 0);
+
+const ETH_HLEN: usize = 14;
+const IPV6_HLEN: usize = 40;
+const ETH_P_IPV6: u16 = 0x86DD;
+const IPPROTO_ICMPV6: u8 = 58;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+
+// Returns true if the packet is an ICMPv6 echo request (without VLAN tags or IPv6 extension
+// headers), the only packets the Userspace program answers. These are the same checks as in the
+// `icmpv6_echo_reply` of the runner.
+#[inline(always)]
+fn is_icmpv6_echo_request(ctx: &XdpContext) -> bool {
+    let start = ctx.data();
+    if start + ETH_HLEN + IPV6_HLEN + 1 > ctx.data_end() {
+        return false;
+    }
+
+    let byte_at = |offset: usize| unsafe { *((start + offset) as *const u8) };
+    u16::from_be_bytes([byte_at(12), byte_at(13)]) == ETH_P_IPV6
+        && byte_at(ETH_HLEN + 6) == IPPROTO_ICMPV6
+        && byte_at(ETH_HLEN + IPV6_HLEN) == ICMPV6_ECHO_REQUEST
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Redirects the ICMPv6 echo requests received on a queue to the AF_XDP socket bound to that queue.
+// All the other packets (like the neighbour discovery packets, which the kernel needs to resolve
+// the addresses for the echo replies) are passed to the kernel network stack.
+//
+// If no socket is bound to the queue, the echo requests are passed to the kernel network stack as
+// well. The lower bits of the `flags` of the redirect are the action returned when the index is
+// not found in the map.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    if !is_icmpv6_echo_request(ctx) {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    let queue_id = unsafe { (*ctx.ctx).rx_queue_index };
+
+    if let Some(count) = XDP_STATS_MAP.get_ptr_mut(queue_id) {
+        unsafe { *count += 1 };
+    }
+
+    Ok(XSKS_MAP
+        .redirect(queue_id, xdp_action::XDP_PASS as u64)
+        .unwrap_or(xdp_action::XDP_PASS))
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,43 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the ports, the size of the `PORTS`, the `PORT_MACS` and the `PORT_STATS`
+/// maps.
+pub const MAX_PORTS: u32 = 32;
+
+/// Flood the ARP packets (`Config::flood`).
+pub const FLOOD_ARP: u32 = 1 << 0;
+/// Flood the packets with a multicast (or broadcast) destination MAC address (`Config::flood`).
+pub const FLOOD_MULTICAST: u32 = 1 << 1;
+
+/// Configuration of the program, the value at the index 0 of the `CONFIG` map.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct Config {
+    /// The packets that are flooded to the other ports, the `FLOOD_*` flags.
+    pub flood: u32,
+}
+
+/// Counters of a port, the value in the `PORT_STATS` map for the interface index of the port.
+///
+/// The `rx_*` are the packets received on the port and flooded to the other ports (counted by the
+/// XDP program), the `tx_*` are the flooded packets sent out on the port (counted by the DEVMAP
+/// program).
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct PortStats {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for Config {}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for PortStats {}
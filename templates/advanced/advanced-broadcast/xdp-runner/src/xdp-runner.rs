@@ -0,0 +1,266 @@
+use std::ffi::CString;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use aya::maps::{Array, DevMapHash, HashMap, MapData, PerCpuHashMap, PerCpuValues};
+use aya::programs::{Xdp, XdpFlags};
+use aya::util::nr_cpus;
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    Config, PortStats, FLOOD_ARP, FLOOD_MULTICAST, MAX_PORTS,
+};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// The interfaces between which the packets are flooded, eg. the interfaces of the test
+    /// environments '--ports left,right,third'. The program is attached to all of them.
+    #[clap(short, long, value_delimiter = ',', required = true)]
+    ports: Vec<String>,
+
+    /// The packets that are flooded to the other ports.
+    #[clap(long, value_delimiter = ',', default_value = "arp")]
+    flood: Vec<FloodKind>,
+
+    /// Interval (in seconds) between two successive displays of the counters.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+enum FloodKind {
+    /// The ARP packets
+    Arp,
+
+    /// The packets with a multicast (or broadcast) destination MAC address
+    Multicast,
+}
+
+/// A port, an interface to and from which the packets are flooded.
+struct Port {
+    name: String,
+    ifindex: u32,
+    mac: [u8; 6],
+}
+
+// Parses a MAC address in the usual `aa:bb:cc:dd:ee:ff` format.
+fn parse_mac(mac: &str) -> Result<[u8; 6], anyhow::Error> {
+    let mut addr = [0u8; 6];
+    let mut octets = mac.split(':');
+    for octet in addr.iter_mut() {
+        let value = octets
+            .next()
+            .ok_or_else(|| anyhow::Error::msg(format!("Invalid MAC address: '{mac}'")))?;
+        *octet = u8::from_str_radix(value, 16)
+            .with_context(|| format!("Invalid MAC address: '{mac}'"))?;
+    }
+    if octets.next().is_some() {
+        return Err(anyhow::Error::msg(format!("Invalid MAC address: '{mac}'")));
+    }
+
+    Ok(addr)
+}
+
+// Returns the interface index of the interface with the given name.
+fn ifindex_from_name(iface: &str) -> Result<u32, anyhow::Error> {
+    let name = CString::new(iface)?;
+    let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if ifindex == 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Interface '{iface}' not found"));
+    }
+
+    Ok(ifindex)
+}
+
+// Builds the ports from the interface names, the MAC address of an interface is read from the
+// `sysfs`.
+fn ports_from_names(names: &[String]) -> anyhow::Result<Vec<Port>> {
+    let mut ports: Vec<Port> = vec![];
+    for name in names {
+        if ports.iter().any(|port| port.name == *name) {
+            return Err(anyhow::Error::msg(format!(
+                "Port '{name}' given more than once."
+            )));
+        }
+
+        let ifindex = ifindex_from_name(name)?;
+        let address = std::fs::read_to_string(format!("/sys/class/net/{name}/address"))
+            .with_context(|| format!("Failed to read the MAC address of '{name}'"))?;
+        let mac = parse_mac(address.trim())?;
+        ports.push(Port {
+            name: name.clone(),
+            ifindex,
+            mac,
+        });
+    }
+
+    Ok(ports)
+}
+
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|octet| format!("{octet:02x}"))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+// Returns the counters of the port, summed for all the CPUs.
+fn port_stats(
+    stats: &PerCpuHashMap<MapData, u32, PortStats>,
+    port: &Port,
+) -> anyhow::Result<PortStats> {
+    let mut total = PortStats::default();
+    for cpu_stats in stats.get(&port.ifindex, 0)?.iter() {
+        total.rx_packets += cpu_stats.rx_packets;
+        total.rx_bytes += cpu_stats.rx_bytes;
+        total.tx_packets += cpu_stats.tx_packets;
+        total.tx_bytes += cpu_stats.tx_bytes;
+    }
+
+    Ok(total)
+}
+
+fn print_stats(
+    stats: &PerCpuHashMap<MapData, u32, PortStats>,
+    ports: &[Port],
+) -> anyhow::Result<()> {
+    for port in ports {
+        let total = port_stats(stats, port)?;
+        info!(
+            "{:<16} flooded from: {:>10} packets {:>12} bytes, flooded to: {:>10} packets {:>12} bytes",
+            port.name, total.rx_packets, total.rx_bytes, total.tx_packets, total.tx_bytes
+        );
+    }
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The DEVMAP program is loaded first, as its file descriptor is set along with the interface index
+// for every port in the `PORTS` map. Then the XDP program, which floods the selected packets to the
+// other ports, is loaded and attached to all the ports. The counters of the ports are displayed
+// every `--interval` seconds.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+    if opts.ports.len() < 2 || opts.ports.len() > MAX_PORTS as usize {
+        return Err(anyhow::Error::msg(format!(
+            "Number of the ports should be between 2 and {MAX_PORTS}."
+        )));
+    }
+    let ports = ports_from_names(&opts.ports)?;
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let egress_program_name = "{{to_snake_case tutorial_name}}_egress";
+    let egress_xdp: &mut Xdp = bpf
+        .program_mut(egress_program_name)
+        .with_context(|| format!("Unable to find the program '{egress_program_name}'"))?
+        .try_into()?;
+    egress_xdp.load()?;
+    let egress_fd = egress_xdp.fd()?.try_clone()?;
+
+    let mut port_macs: HashMap<_, u32, [u8; 6]> =
+        HashMap::try_from(bpf.map_mut("PORT_MACS").unwrap())?;
+    for port in &ports {
+        port_macs.insert(port.ifindex, port.mac, 0)?;
+    }
+
+    // The entries of the ports are created here, the programs only update the existing entries.
+    let mut stats = PerCpuHashMap::try_from(bpf.take_map("PORT_STATS").unwrap())?;
+    let cpus = nr_cpus().map_err(|(_, e)| e)?;
+    for port in &ports {
+        let zeros = PerCpuValues::try_from(vec![PortStats::default(); cpus])?;
+        stats.insert(port.ifindex, zeros, 0)?;
+    }
+
+    // The ports are keyed by the interface index, any key would do, as the program only redirects
+    // with `BPF_F_BROADCAST` (ignoring the key).
+    let mut devmap = DevMapHash::try_from(bpf.map_mut("PORTS").unwrap())?;
+    for port in &ports {
+        devmap
+            .insert(port.ifindex, port.ifindex, Some(&egress_fd), 0)
+            .with_context(|| format!("Failed to add the port '{}' to the `PORTS`", port.name))?;
+    }
+
+    let mut flood = 0;
+    if opts.flood.contains(&FloodKind::Arp) {
+        flood |= FLOOD_ARP;
+    }
+    if opts.flood.contains(&FloodKind::Multicast) {
+        flood |= FLOOD_MULTICAST;
+    }
+    let mut config = Array::try_from(bpf.map_mut("CONFIG").unwrap())?;
+    config.set(0, Config { flood }, 0)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    for port in &ports {
+        let _linkid = xdp
+            .attach(&port.name, XdpFlags::default())
+            .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+    }
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'! Now waiting for Ctrl-C",
+        program_name,
+        opts.ports.join("', '")
+    );
+    for port in &ports {
+        info!(
+            "Port '{}' (ifindex: {}) with the source MAC: {}",
+            port.name,
+            port.ifindex,
+            format_mac(&port.mac)
+        );
+    }
+    info!("Flooding the packets: {:?}", opts.flood);
+
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                print_stats(&stats, &ports)?;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::{xdp_action, BPF_F_BROADCAST, BPF_F_EXCLUDE_INGRESS},
+    macros::{map, xdp},
+    maps::{Array, DevMapHash, HashMap, PerCpuHashMap},
+    programs::XdpContext,
+};
+
+use parsing_helpers::{parse_ethhdr, HdrCursor, ETH_P_ARP};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    Config, PortStats, FLOOD_ARP, FLOOD_MULTICAST, MAX_PORTS,
+};
+
+#[map]
+static CONFIG: Array<Config> = Array::<Config>::with_max_entries(1, 0);
+
+// The ports to which the packets are flooded, keyed by the interface index of the port. The
+// value of an entry (set by the runner) has the interface index and the DEVMAP program run on the
+// packets sent out on the port.
+#[map]
+static PORTS: DevMapHash = DevMapHash::with_max_entries(MAX_PORTS, 0);
+
+// The MAC address of every port, keyed by the interface index, used as the source MAC address of
+// the packets sent out on the port.
+#[map]
+static PORT_MACS: HashMap<u32, [u8; 6]> = HashMap::<u32, [u8; 6]>::with_max_entries(MAX_PORTS, 0);
+
+// Counters of every port, keyed by the interface index. The entries are created by the runner.
+#[map]
+static PORT_STATS: PerCpuHashMap<u32, PortStats> =
+    PerCpuHashMap::<u32, PortStats>::with_max_entries(MAX_PORTS, 0);
+
+// Returns true if the packet is to be flooded to the other ports, depending on the `flood` flags
+// of the `CONFIG`.
+#[inline(always)]
+fn should_flood(ctx: &XdpContext, flood: u32) -> bool {
+    let mut cursor = HdrCursor::new(ctx);
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return false;
+    };
+
+    if flood & FLOOD_ARP != 0 && u16::from_be(unsafe { (*eth).h_proto }) == ETH_P_ARP {
+        return true;
+    }
+
+    // The lowest bit of the first octet is set for the multicast addresses, including the
+    // broadcast address.
+    flood & FLOOD_MULTICAST != 0 && unsafe { (*eth).h_dest[0] } & 0x01 != 0
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Floods the selected packets to all the ports in the `PORTS` map, except for the port on which
+// the packet is received. The other packets are passed to the network stack.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let Some(config) = CONFIG.get(0) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if !should_flood(ctx, config.flood) {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    let ifindex = unsafe { (*ctx.ctx).ingress_ifindex };
+    if let Some(stats) = PORT_STATS.get_ptr_mut(&ifindex) {
+        unsafe {
+            (*stats).rx_packets += 1;
+            (*stats).rx_bytes += (ctx.data_end() - ctx.data()) as u64;
+        }
+    }
+
+    // With `BPF_F_BROADCAST` the key is ignored and the packet is sent out on every port in the
+    // map (cloning the packet for every port), `BPF_F_EXCLUDE_INGRESS` leaves out the port on
+    // which the packet is received.
+    let flags = (BPF_F_BROADCAST | BPF_F_EXCLUDE_INGRESS) as u64;
+    Ok(PORTS.redirect(0, flags).unwrap_or(xdp_action::XDP_ABORTED))
+}
+
+// The program run by the DEVMAP on every packet sent out on a port, `egress_ifindex` is the
+// interface index of the port. Every port gets its own copy of a flooded packet, so the source MAC
+// address can be different for every port.
+#[xdp(map = "devmap")]
+pub fn {{to_snake_case tutorial_name}}_egress(ctx: XdpContext) -> u32 {
+    let ifindex = unsafe { (*ctx.ctx).egress_ifindex };
+
+    let mut cursor = HdrCursor::new(&ctx);
+    let Some(eth) = parse_ethhdr(&ctx, &mut cursor) else {
+        return xdp_action::XDP_DROP;
+    };
+    if let Some(mac) = unsafe { PORT_MACS.get(&ifindex) } {
+        unsafe { (*eth).h_source = *mac };
+    }
+
+    if let Some(stats) = PORT_STATS.get_ptr_mut(&ifindex) {
+        unsafe {
+            (*stats).tx_packets += 1;
+            (*stats).tx_bytes += (ctx.data_end() - ctx.data()) as u64;
+        }
+    }
+
+    // `XDP_PASS` sends the packet out on the port, `XDP_DROP` drops the packet (only for this
+    // port).
+    xdp_action::XDP_PASS
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,53 @@
+// Helpers for parsing the packet headers.
+//
+// These are the helpers from the 'packet03-redirecting' tutorial, only the Ethernet header is
+// needed for flooding the packets.
+
+use core::mem;
+
+use aya_ebpf::programs::XdpContext;
+
+pub const ETH_P_ARP: u16 = 0x0806;
+
+/// Ethernet Header.
+#[repr(C)]
+pub struct EthHdr {
+    pub h_dest: [u8; 6],
+    pub h_source: [u8; 6],
+    /// Protocol of the next header (in network byte order).
+    pub h_proto: u16,
+}
+
+/// Keeps track of the current parsing position in the packet.
+///
+/// Every successful `parse_*` call advances the cursor to the start of the next header.
+pub struct HdrCursor {
+    /// Address of the current parsing position in the packet.
+    pub pos: usize,
+}
+
+impl HdrCursor {
+    pub fn new(ctx: &XdpContext) -> Self {
+        Self { pos: ctx.data() }
+    }
+}
+
+/// Returns the pointer to a `T` at the cursor, only if the whole `T` lies within the packet.
+#[inline(always)]
+pub fn ptr_at<T>(ctx: &XdpContext, cursor: &HdrCursor) -> Option<*mut T> {
+    let len = mem::size_of::<T>();
+    if cursor.pos + len > ctx.data_end() {
+        return None;
+    }
+
+    Some(cursor.pos as *mut T)
+}
+
+/// Parses the Ethernet header and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_ethhdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*mut EthHdr> {
+    let eth: *mut EthHdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<EthHdr>();
+
+    Some(eth)
+}
@@ -0,0 +1,33 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the CPUs, the size of the `CPU_MAP` and the `CPUS_AVAILABLE` maps.
+pub const MAX_CPUS: u32 = 128;
+
+/// The CPUs to which the packets are redirected, the value at the index 0 of the `CPU_CONFIG` map.
+///
+/// A packet is redirected to the CPU `CPUS_AVAILABLE[flow_hash % num_cpus]`, the `CPUS_AVAILABLE`
+/// map has the CPUs at the indexes `0..num_cpus`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct CpuConfig {
+    pub num_cpus: u32,
+}
+
+/// Structure that maintains the Packet Statistics, same as in the 'basic-04' tutorial.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct StatsRecord {
+    pub pkt_count: u64,
+    pub bytes_count: u64,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for CpuConfig {}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for StatsRecord {}
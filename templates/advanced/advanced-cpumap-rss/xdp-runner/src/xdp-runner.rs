@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+
+use aya::maps::{Array, CpuMap, MapData, PerCpuArray};
+use aya::programs::{Xdp, XdpFlags};
+use aya::util::online_cpus;
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{info, warn};
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{CpuConfig, StatsRecord, MAX_CPUS};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// The CPUs to which the packets are redirected, eg. '--cpus 1,2,3'. All the online CPUs, if
+    /// not given.
+    #[clap(long, value_delimiter = ',')]
+    cpus: Vec<u32>,
+
+    /// Size of the queue (in packets) of every CPU in the `CPU_MAP`.
+    #[clap(long, default_value_t = 2048)]
+    queue_size: u32,
+
+    /// Interval (in seconds) between two successive displays of the counters.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Returns the counters of every CPU, indexed by the CPU.
+fn per_cpu_stats(stats: &PerCpuArray<MapData, StatsRecord>) -> anyhow::Result<Vec<StatsRecord>> {
+    Ok(stats.get(&0, 0)?.iter().copied().collect())
+}
+
+// Returns the CPUs given with `--cpus` (or all the online CPUs), after checking that they are
+// online and can be set in the `CPU_MAP`.
+fn target_cpus(cpus: &[u32]) -> anyhow::Result<Vec<u32>> {
+    let online = online_cpus().map_err(|(msg, e)| anyhow::Error::new(e).context(msg))?;
+    if cpus.is_empty() {
+        return Ok(online.into_iter().filter(|cpu| *cpu < MAX_CPUS).collect());
+    }
+
+    for cpu in cpus {
+        if *cpu >= MAX_CPUS {
+            return Err(anyhow::Error::msg(format!(
+                "CPU {cpu} is not supported, the CPUs should be less than {MAX_CPUS}."
+            )));
+        }
+        if !online.contains(cpu) {
+            return Err(anyhow::Error::msg(format!("CPU {cpu} is not online.")));
+        }
+    }
+
+    let mut cpus = cpus.to_vec();
+    cpus.sort_unstable();
+    cpus.dedup();
+
+    Ok(cpus)
+}
+
+// Displays the rates of the packets received on every CPU (by the XDP program) and of the packets
+// processed on every CPU (by the CPUMAP program), along with the share of every CPU of the
+// processed packets. The CPUs without any packets in the interval are not displayed.
+fn print_stats(
+    prev_rx: &[StatsRecord],
+    rx: &[StatsRecord],
+    prev_cpu: &[StatsRecord],
+    cpu: &[StatsRecord],
+    elapsed: f64,
+) {
+    let delta = |prev: &[StatsRecord], curr: &[StatsRecord], i: usize| {
+        curr[i].pkt_count.saturating_sub(prev[i].pkt_count)
+    };
+    let total: u64 = (0..cpu.len()).map(|i| delta(prev_cpu, cpu, i)).sum();
+
+    info!(
+        "{:>5} {:>14} {:>15} {:>8}",
+        "CPU", "RX (pps)", "Processed (pps)", "Share"
+    );
+    for i in 0..cpu.len() {
+        let received = delta(prev_rx, rx, i);
+        let processed = delta(prev_cpu, cpu, i);
+        if received == 0 && processed == 0 {
+            continue;
+        }
+
+        let share = if total > 0 {
+            processed as f64 * 100.0 / total as f64
+        } else {
+            0.0
+        };
+        info!(
+            "{:>5} {:>14.0} {:>15.0} {:>7.1}%",
+            i,
+            received as f64 / elapsed,
+            processed as f64 / elapsed,
+            share
+        );
+    }
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The CPUMAP program is loaded first, as its file descriptor is set along with the queue size for
+// every CPU in the `CPU_MAP`. Then the XDP program, which redirects the packets to the CPUs using
+// the flow hash, is loaded and attached. The per-CPU counters are displayed every `--interval`
+// seconds.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+    if opts.queue_size == 0 {
+        return Err(anyhow::Error::msg("Queue size should not be 0."));
+    }
+    let cpus = target_cpus(&opts.cpus)?;
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let cpumap_program_name = "{{to_snake_case tutorial_name}}_cpumap";
+    let cpumap_xdp: &mut Xdp = bpf
+        .program_mut(cpumap_program_name)
+        .with_context(|| format!("Unable to find the program '{cpumap_program_name}'"))?
+        .try_into()?;
+    cpumap_xdp.load()?;
+    let cpumap_fd = cpumap_xdp.fd()?.try_clone()?;
+
+    // The entry of a CPU allocates the queue of the CPU and starts the kernel thread that
+    // processes the packets redirected to the CPU.
+    let mut cpu_map = CpuMap::try_from(bpf.map_mut("CPU_MAP").unwrap())?;
+    for cpu in &cpus {
+        cpu_map
+            .set(*cpu, opts.queue_size, Some(&cpumap_fd), 0)
+            .with_context(|| format!("Failed to set the CPU {cpu} in the `CPU_MAP`"))?;
+    }
+
+    let mut cpus_available = Array::try_from(bpf.map_mut("CPUS_AVAILABLE").unwrap())?;
+    for (i, cpu) in cpus.iter().enumerate() {
+        cpus_available.set(i as u32, *cpu, 0)?;
+    }
+
+    // The `num_cpus` is set last, so that the program only uses the CPUs that are set.
+    let mut cpu_config = Array::try_from(bpf.map_mut("CPU_CONFIG").unwrap())?;
+    let config = CpuConfig {
+        num_cpus: cpus.len() as u32,
+    };
+    cpu_config.set(0, config, 0)?;
+
+    let rx_stats = PerCpuArray::try_from(bpf.take_map("RX_STATS").unwrap())?;
+    let cpu_stats = PerCpuArray::try_from(bpf.take_map("CPU_STATS").unwrap())?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'! Now waiting for Ctrl-C",
+        program_name, &opts.iface
+    );
+    info!(
+        "Redirecting the packets to the CPUs: {:?} (queue size: {})",
+        cpus, opts.queue_size
+    );
+
+    let mut prev_rx = per_cpu_stats(&rx_stats)?;
+    let mut prev_cpu = per_cpu_stats(&cpu_stats)?;
+    let mut prev_time = Instant::now();
+
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                let rx = per_cpu_stats(&rx_stats)?;
+                let cpu = per_cpu_stats(&cpu_stats)?;
+                let now = Instant::now();
+
+                let elapsed = now.duration_since(prev_time).as_secs_f64();
+                print_stats(&prev_rx, &rx, &prev_cpu, &cpu, elapsed);
+
+                prev_rx = rx;
+                prev_cpu = cpu;
+                prev_time = now;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
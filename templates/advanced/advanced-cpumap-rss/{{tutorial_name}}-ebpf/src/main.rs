@@ -0,0 +1,183 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    macros::{map, xdp},
+    maps::{Array, CpuMap, PerCpuArray},
+    programs::XdpContext,
+};
+
+use parsing_helpers::{
+    parse_ethhdr, parse_ip6hdr, parse_iphdr, parse_tcphdr, parse_udphdr, HdrCursor, ETH_P_IP,
+    ETH_P_IPV6, IPPROTO_TCP, IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{CpuConfig, StatsRecord, MAX_CPUS};
+
+// The CPUs to which the packets can be redirected, indexed by the CPU. The value of an entry
+// (set by the runner) has the size of the queue of the CPU and the program run on the CPU.
+#[map]
+static CPU_MAP: CpuMap = CpuMap::with_max_entries(MAX_CPUS, 0);
+
+// The CPUs selected by the runner, at the indexes `0..num_cpus` (see `CpuConfig`).
+#[map]
+static CPUS_AVAILABLE: Array<u32> = Array::<u32>::with_max_entries(MAX_CPUS, 0);
+
+#[map]
+static CPU_CONFIG: Array<CpuConfig> = Array::<CpuConfig>::with_max_entries(1, 0);
+
+// Packets received by the XDP program, on the CPUs that receive the packets from the interface.
+#[map]
+static RX_STATS: PerCpuArray<StatsRecord> = PerCpuArray::<StatsRecord>::with_max_entries(1, 0);
+
+// Packets processed by the CPUMAP program, on the CPUs to which the packets are redirected.
+#[map]
+static CPU_STATS: PerCpuArray<StatsRecord> = PerCpuArray::<StatsRecord>::with_max_entries(1, 0);
+
+#[inline(always)]
+fn count(stats: &PerCpuArray<StatsRecord>, ctx: &XdpContext) {
+    if let Some(record) = stats.get_ptr_mut(0) {
+        unsafe {
+            (*record).pkt_count += 1;
+            (*record).bytes_count += (ctx.data_end() - ctx.data()) as u64;
+        }
+    }
+}
+
+// Adds a word to the hash, the mixing step of the MurmurHash3.
+#[inline(always)]
+fn hash_add(hash: u32, word: u32) -> u32 {
+    let word = word
+        .wrapping_mul(0xcc9e2d51)
+        .rotate_left(15)
+        .wrapping_mul(0x1b873593);
+
+    (hash ^ word)
+        .rotate_left(13)
+        .wrapping_mul(5)
+        .wrapping_add(0xe6546b64)
+}
+
+// The finalization step of the MurmurHash3, so that all the bits of the words affect the lower
+// bits of the hash (used for selecting the CPU).
+#[inline(always)]
+fn hash_finish(mut hash: u32) -> u32 {
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85ebca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2ae35);
+
+    hash ^ (hash >> 16)
+}
+
+#[inline(always)]
+fn hash_addr6(mut hash: u32, addr: &[u8; 16]) -> u32 {
+    for i in 0..4 {
+        let j = i * 4;
+        let word = [addr[j], addr[j + 1], addr[j + 2], addr[j + 3]];
+        hash = hash_add(hash, u32::from_ne_bytes(word));
+    }
+
+    hash
+}
+
+// Returns the hash of the IP addresses, the protocol and the ports (for TCP and UDP) of the packet,
+// the same for all the packets of a flow. Like the RSS of the network devices, this keeps all the
+// packets of a flow on the same CPU, so that they are processed in order.
+//
+// Only the first fragment of an IPv4 packet has the UDP/TCP header, the bytes at the 'port' in the
+// other fragments are the payload. So the ports are left out for all the fragments (the first one
+// included), and the fragments of a flow are kept on the same CPU as well. The IPv6 fragments have
+// the Fragment header as the `nexthdr`, so they are hashed without the ports anyway.
+#[inline(always)]
+fn flow_hash(ctx: &XdpContext) -> Option<u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let eth = parse_ethhdr(ctx, &mut cursor)?;
+    let (hash, protocol, fragment) = match u16::from_be(unsafe { (*eth).h_proto }) {
+        ETH_P_IP => {
+            let iph = parse_iphdr(ctx, &mut cursor)?;
+            unsafe {
+                let hash = hash_add(hash_add(0, (*iph).saddr), (*iph).daddr);
+                // The 'more fragments' flag or the fragment offset (the lower 13 bits).
+                let fragment = u16::from_be((*iph).frag_off) & 0x3fff != 0;
+                (hash, (*iph).protocol, fragment)
+            }
+        }
+        ETH_P_IPV6 => {
+            let ip6h = parse_ip6hdr(ctx, &mut cursor)?;
+            unsafe {
+                let hash = hash_addr6(hash_addr6(0, &(*ip6h).saddr), &(*ip6h).daddr);
+                (hash, (*ip6h).nexthdr, false)
+            }
+        }
+        _ => return None,
+    };
+
+    let ports = match protocol {
+        _ if fragment => 0,
+        IPPROTO_TCP => {
+            let tcph = parse_tcphdr(ctx, &mut cursor)?;
+            unsafe { ((*tcph).source as u32) << 16 | (*tcph).dest as u32 }
+        }
+        IPPROTO_UDP => {
+            let udph = parse_udphdr(ctx, &mut cursor)?;
+            unsafe { ((*udph).source as u32) << 16 | (*udph).dest as u32 }
+        }
+        _ => 0,
+    };
+
+    Some(hash_finish(hash_add(
+        hash_add(hash, protocol as u32),
+        ports,
+    )))
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Redirects the packet to one of the CPUs selected by the runner, using the flow hash. The packet
+// is queued in the queue of the CPU and the rest of the processing (including building the
+// `sk_buff` and the network stack) is done on that CPU.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    count(&RX_STATS, ctx);
+
+    let num_cpus = match CPU_CONFIG.get(0) {
+        Some(config) if config.num_cpus > 0 => config.num_cpus,
+        _ => return Ok(xdp_action::XDP_PASS),
+    };
+
+    // The packets other than IPv4 and IPv6 are all redirected to the first CPU.
+    let hash = flow_hash(ctx).unwrap_or(0);
+    let Some(cpu) = CPUS_AVAILABLE.get(hash % num_cpus) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    // The lower bits of the flags are the action returned if the redirect fails (eg. there is no
+    // entry for the CPU in the map), so that the packet is processed on the current CPU.
+    Ok(CPU_MAP
+        .redirect(*cpu, xdp_action::XDP_PASS as u64)
+        .unwrap_or(xdp_action::XDP_PASS))
+}
+
+// The program run by the CPUMAP on the CPU to which the packet is redirected, before the `sk_buff`
+// is built for the packet. Counts the packets on every CPU, to show the spread of the packets.
+#[xdp(map = "cpumap")]
+pub fn {{to_snake_case tutorial_name}}_cpumap(ctx: XdpContext) -> u32 {
+    count(&CPU_STATS, &ctx);
+
+    xdp_action::XDP_PASS
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,56 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the blocked domains (rules).
+///
+/// The `id` of the rule of a domain in the `BLOCKED_DOMAINS` map is the index of the hit counter
+/// of the rule in the `DOMAIN_HITS` map.
+pub const MAX_DOMAINS: u32 = 1024;
+
+/// Maximum length of a query name (in the wire format, including the final zero length byte) that
+/// is matched. The queries with the longer names are passed.
+pub const MAX_NAME_LEN: usize = 128;
+
+/// Maximum length of a label of a name.
+pub const MAX_LABEL_LEN: u8 = 63;
+
+/// The UDP port of the DNS servers.
+pub const DNS_PORT: u16 = 53;
+
+/// The queries for the domain are dropped.
+pub const ACTION_DROP: u32 = 0;
+
+/// The queries for the domain are answered with a `NXDOMAIN` response.
+pub const ACTION_NXDOMAIN: u32 = 1;
+
+/// A query name, the key of the `BLOCKED_DOMAINS` map.
+///
+/// The name is in the wire format of the DNS - every label is preceded by its length and the name
+/// ends with a zero length byte (eg. `\x03www\x07example\x03com\x00`). The letters are in lower
+/// case and the rest of the array is zeros.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct DomainKey {
+    pub name: [u8; MAX_NAME_LEN],
+}
+
+/// The rule of a blocked domain, the value of the `BLOCKED_DOMAINS` map.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct DomainRule {
+    /// The id of the rule, the index of the hit counter in the `DOMAIN_HITS` map.
+    pub id: u32,
+
+    /// `ACTION_DROP` or `ACTION_NXDOMAIN`.
+    pub action: u32,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for DomainKey {}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for DomainRule {}
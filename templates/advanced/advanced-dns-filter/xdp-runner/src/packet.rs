@@ -0,0 +1,206 @@
+// Building and parsing the Ethernet + IPv4/IPv6 + UDP + DNS packets for the `test-run` checks.
+
+use std::net::IpAddr;
+
+pub(crate) const DNS_FLAG_QR: u16 = 0x8000;
+pub(crate) const DNS_FLAG_RD: u16 = 0x0100;
+pub(crate) const DNS_FLAG_RA: u16 = 0x0080;
+pub(crate) const DNS_RCODE_NXDOMAIN: u16 = 3;
+
+pub(crate) const DNS_TYPE_A: u16 = 1;
+pub(crate) const DNS_TYPE_AAAA: u16 = 28;
+
+const DNS_CLASS_IN: u16 = 1;
+
+/// An EDNS `OPT` record (the root name, the type 41 and a UDP payload size of 1232), as in the
+/// additional records of the queries of most resolvers.
+pub(crate) const EDNS_OPT: [u8; 11] = [0, 0, 41, 0x04, 0xd0, 0, 0, 0, 0, 0, 0];
+
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86DD;
+const IPPROTO_UDP: u8 = 17;
+
+const ETH_HLEN: usize = 14;
+const UDP_HLEN: usize = 8;
+const DNS_HLEN: usize = 12;
+
+/// The fields of a DNS packet used by the checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DnsPacket {
+    pub(crate) src_mac: [u8; 6],
+    pub(crate) dst_mac: [u8; 6],
+    pub(crate) src_addr: IpAddr,
+    pub(crate) dst_addr: IpAddr,
+    pub(crate) src_port: u16,
+    pub(crate) dst_port: u16,
+    pub(crate) id: u16,
+    pub(crate) flags: u16,
+
+    /// The number of the questions, the answers, the authority and the additional records.
+    pub(crate) counts: [u16; 4],
+
+    /// The message after the DNS header, the questions followed by the records.
+    pub(crate) body: Vec<u8>,
+}
+
+/// Returns a question for the `name` (used as is, without any checks) in the wire format.
+pub(crate) fn question(name: &str, qtype: u16) -> Vec<u8> {
+    let mut question = vec![];
+    for label in name.split('.').filter(|label| !label.is_empty()) {
+        question.push(label.len() as u8);
+        question.extend_from_slice(label.as_bytes());
+    }
+    question.push(0);
+    question.extend_from_slice(&qtype.to_be_bytes());
+    question.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
+
+    question
+}
+
+// Sums the 16 bit words (in the network byte order) of the data, without folding.
+fn sum_words(data: &[u8]) -> u32 {
+    data.chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)]) as u32)
+        .sum()
+}
+
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+// Sum of the pseudo header of the UDP checksum.
+fn pseudo_header_sum(src_addr: IpAddr, dst_addr: IpAddr, udp_len: usize) -> u32 {
+    let addrs = match (src_addr, dst_addr) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => [src.octets().to_vec(), dst.octets().to_vec()],
+        (IpAddr::V6(src), IpAddr::V6(dst)) => [src.octets().to_vec(), dst.octets().to_vec()],
+        _ => panic!("Addresses of different families"),
+    };
+
+    sum_words(&addrs.concat()) + IPPROTO_UDP as u32 + udp_len as u32
+}
+
+impl DnsPacket {
+    /// Builds the packet, with the valid IPv4 and UDP checksums.
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let udp_len = UDP_HLEN + DNS_HLEN + self.body.len();
+
+        let mut packet = vec![];
+        packet.extend_from_slice(&self.dst_mac);
+        packet.extend_from_slice(&self.src_mac);
+        match (self.src_addr, self.dst_addr) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                packet.extend_from_slice(&ETH_P_IP.to_be_bytes());
+                let mut iph = [0u8; 20];
+                iph[0] = 0x45;
+                iph[2..4].copy_from_slice(&((20 + udp_len) as u16).to_be_bytes());
+                iph[8] = 64;
+                iph[9] = IPPROTO_UDP;
+                iph[12..16].copy_from_slice(&src.octets());
+                iph[16..20].copy_from_slice(&dst.octets());
+                let check = fold(sum_words(&iph));
+                iph[10..12].copy_from_slice(&check.to_be_bytes());
+                packet.extend_from_slice(&iph);
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                packet.extend_from_slice(&ETH_P_IPV6.to_be_bytes());
+                packet.extend_from_slice(&[0x60, 0, 0, 0]);
+                packet.extend_from_slice(&(udp_len as u16).to_be_bytes());
+                packet.extend_from_slice(&[IPPROTO_UDP, 64]);
+                packet.extend_from_slice(&src.octets());
+                packet.extend_from_slice(&dst.octets());
+            }
+            _ => panic!("Addresses of different families"),
+        }
+
+        let mut udp = vec![];
+        udp.extend_from_slice(&self.src_port.to_be_bytes());
+        udp.extend_from_slice(&self.dst_port.to_be_bytes());
+        udp.extend_from_slice(&(udp_len as u16).to_be_bytes());
+        udp.extend_from_slice(&[0, 0]);
+        udp.extend_from_slice(&self.id.to_be_bytes());
+        udp.extend_from_slice(&self.flags.to_be_bytes());
+        for count in self.counts {
+            udp.extend_from_slice(&count.to_be_bytes());
+        }
+        udp.extend_from_slice(&self.body);
+        let sum = pseudo_header_sum(self.src_addr, self.dst_addr, udp_len) + sum_words(&udp);
+        let check = match fold(sum) {
+            0 => 0xffff,
+            check => check,
+        };
+        udp[6..8].copy_from_slice(&check.to_be_bytes());
+        packet.extend_from_slice(&udp);
+
+        packet
+    }
+
+    /// Parses the packet, checking the IPv4 and the UDP checksums.
+    pub(crate) fn parse(packet: &[u8]) -> Result<Self, String> {
+        let short = || "packet too short".to_string();
+        let eth = packet.get(..ETH_HLEN).ok_or_else(short)?;
+
+        let (src_addr, dst_addr, udp) = match u16::from_be_bytes([eth[12], eth[13]]) {
+            ETH_P_IP => {
+                let iph = packet.get(ETH_HLEN..ETH_HLEN + 20).ok_or_else(short)?;
+                if iph[0] != 0x45 || iph[9] != IPPROTO_UDP {
+                    return Err("not an IPv4 UDP packet without the options".to_string());
+                }
+                if fold(sum_words(iph)) != 0 {
+                    return Err("wrong IPv4 checksum".to_string());
+                }
+                let tot_len = u16::from_be_bytes([iph[2], iph[3]]) as usize;
+                if ETH_HLEN + tot_len != packet.len() {
+                    return Err(format!("IPv4 total length: {tot_len}"));
+                }
+                let src: [u8; 4] = iph[12..16].try_into().unwrap();
+                let dst: [u8; 4] = iph[16..20].try_into().unwrap();
+                let udp = &packet[ETH_HLEN + 20..];
+                (IpAddr::from(src), IpAddr::from(dst), udp)
+            }
+            ETH_P_IPV6 => {
+                let ip6h = packet.get(ETH_HLEN..ETH_HLEN + 40).ok_or_else(short)?;
+                if ip6h[0] >> 4 != 6 || ip6h[6] != IPPROTO_UDP {
+                    return Err("not an IPv6 UDP packet".to_string());
+                }
+                let payload_len = u16::from_be_bytes([ip6h[4], ip6h[5]]) as usize;
+                if ETH_HLEN + 40 + payload_len != packet.len() {
+                    return Err(format!("IPv6 payload length: {payload_len}"));
+                }
+                let src: [u8; 16] = ip6h[8..24].try_into().unwrap();
+                let dst: [u8; 16] = ip6h[24..40].try_into().unwrap();
+                let udp = &packet[ETH_HLEN + 40..];
+                (IpAddr::from(src), IpAddr::from(dst), udp)
+            }
+            _ => return Err("not an IP packet".to_string()),
+        };
+
+        if udp.len() < UDP_HLEN + DNS_HLEN {
+            return Err(short());
+        }
+        let udp_len = u16::from_be_bytes([udp[4], udp[5]]) as usize;
+        if udp_len != udp.len() {
+            return Err(format!("UDP length: {udp_len}"));
+        }
+        if fold(pseudo_header_sum(src_addr, dst_addr, udp.len()) + sum_words(udp)) != 0 {
+            return Err("wrong UDP checksum".to_string());
+        }
+
+        let word = |pos: usize| u16::from_be_bytes([udp[pos], udp[pos + 1]]);
+        Ok(Self {
+            dst_mac: eth[0..6].try_into().unwrap(),
+            src_mac: eth[6..12].try_into().unwrap(),
+            src_addr,
+            dst_addr,
+            src_port: word(0),
+            dst_port: word(2),
+            id: word(8),
+            flags: word(10),
+            counts: [word(12), word(14), word(16), word(18)],
+            body: udp[UDP_HLEN + DNS_HLEN..].to_vec(),
+        })
+    }
+}
@@ -0,0 +1,768 @@
+mod packet;
+mod test_run;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+
+use aya::maps::{HashMap, Map, MapData, PerCpuArray, PerCpuValues};
+use aya::programs::{links::FdLink, Xdp, XdpFlags};
+use aya::util::nr_cpus;
+use aya::EbpfLoader;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    DomainKey, DomainRule, ACTION_DROP, ACTION_NXDOMAIN, DNS_PORT, MAX_DOMAINS, MAX_LABEL_LEN,
+    MAX_NAME_LEN,
+};
+
+use packet::{
+    question, DnsPacket, DNS_FLAG_QR, DNS_FLAG_RA, DNS_FLAG_RD, DNS_RCODE_NXDOMAIN, DNS_TYPE_A,
+    DNS_TYPE_AAAA, EDNS_OPT,
+};
+
+#[derive(Debug, Parser)]
+enum CliCommand {
+    /// Pin: Attaches the program, pins it along with the maps and loads the domains from a file
+    Pin(PinOptions),
+
+    /// Load: Replaces the domains in the pinned maps with the domains from a file
+    Load(LoadOptions),
+
+    /// Add: Adds a domain to the pinned maps, or changes the action of the domain
+    Add(AddOptions),
+
+    /// Remove: Removes a domain from the pinned maps
+    Remove(DomainOptions),
+
+    /// List: Lists the domains in the pinned maps with the hit counters
+    List(PinnedOptions),
+
+    /// Test-run: Runs the program on the test queries (using `BPF_PROG_TEST_RUN`) and checks the
+    /// actions, the responses and the hit counters
+    TestRun(TestRunOptions),
+}
+
+// Handling of the 'pin' command.
+#[derive(Debug, Parser)]
+struct PinOptions {
+    /// File with the domains to block, one domain (and optionally the action) per line.
+    #[clap(short, long)]
+    domains: Option<PathBuf>,
+
+    /// Name of the 'eBPF' binary file.
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    /// Interface name to which the program is attached.
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    /// Run the binary in 'release' mode
+    #[clap(long)]
+    release: bool,
+}
+
+// Options to find the pinned maps, common to all the commands after 'pin'.
+#[derive(Debug, Parser)]
+struct PinnedOptions {
+    /// Interface name to which the program is attached.
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    /// Name of the 'tutorial' to search Pinned Maps in `/sys/fs/bpf`
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    name: String,
+}
+
+// Handling of the 'load' command.
+#[derive(Debug, Parser)]
+struct LoadOptions {
+    /// File with the domains to block, one domain (and optionally the action) per line.
+    #[clap(short, long)]
+    domains: PathBuf,
+
+    /// Keep running and load the file again when it is modified, checking every `INTERVAL`
+    /// seconds.
+    #[clap(long)]
+    interval: Option<u64>,
+
+    #[clap(flatten)]
+    pinned: PinnedOptions,
+}
+
+// Handling of the 'add' command.
+#[derive(Debug, Parser)]
+struct AddOptions {
+    /// The domain (eg. 'ads.example.com'), only the queries for this exact name are matched.
+    domain: Domain,
+
+    /// The action for the queries: 'drop' or 'nxdomain'.
+    #[clap(short, long, default_value = "drop")]
+    action: Action,
+
+    #[clap(flatten)]
+    pinned: PinnedOptions,
+}
+
+// Handling of the 'remove' command.
+#[derive(Debug, Parser)]
+struct DomainOptions {
+    /// The domain (eg. 'ads.example.com').
+    domain: Domain,
+
+    #[clap(flatten)]
+    pinned: PinnedOptions,
+}
+
+// Handling of the 'test-run' command.
+#[derive(Debug, Parser)]
+struct TestRunOptions {
+    /// Name of the 'eBPF' binary file.
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    /// Run the binary in 'release' mode
+    #[clap(long)]
+    release: bool,
+}
+
+// A domain name, in lower case and without the final '.'.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Domain(String);
+
+impl FromStr for Domain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_suffix('.').unwrap_or(s).to_ascii_lowercase();
+        for label in name.split('.') {
+            if label.is_empty() || label.len() > MAX_LABEL_LEN as usize {
+                return Err(anyhow::Error::msg(format!(
+                    "Labels of the domain should have between 1 and {MAX_LABEL_LEN} characters: '{s}'"
+                )));
+            }
+            if !label
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_')
+            {
+                return Err(anyhow::Error::msg(format!(
+                    "Invalid characters in the domain: '{s}'"
+                )));
+            }
+        }
+
+        // Every '.' becomes the length of the next label, along with the lengths of the first and
+        // the final (empty) labels.
+        if name.len() + 2 > MAX_NAME_LEN {
+            return Err(anyhow::Error::msg(format!(
+                "Length of the domain should be at most {} characters: '{s}'",
+                MAX_NAME_LEN - 2
+            )));
+        }
+
+        Ok(Domain(name))
+    }
+}
+
+impl fmt::Display for Domain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Domain {
+    // The key of the domain in the `BLOCKED_DOMAINS` map, the name in the wire format.
+    fn key(&self) -> DomainKey {
+        let mut key = DomainKey {
+            name: [0; MAX_NAME_LEN],
+        };
+        let mut pos = 0;
+        for label in self.0.split('.') {
+            key.name[pos] = label.len() as u8;
+            key.name[pos + 1..pos + 1 + label.len()].copy_from_slice(label.as_bytes());
+            pos += 1 + label.len();
+        }
+
+        key
+    }
+
+    fn from_key(key: &DomainKey) -> Self {
+        let mut labels = vec![];
+        let mut pos = 0;
+        while pos < MAX_NAME_LEN && key.name[pos] != 0 {
+            let end = (pos + 1 + key.name[pos] as usize).min(MAX_NAME_LEN);
+            labels.push(String::from_utf8_lossy(&key.name[pos + 1..end]).into_owned());
+            pos = end;
+        }
+
+        Domain(labels.join("."))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Drop,
+    Nxdomain,
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Action::Drop),
+            "nxdomain" => Ok(Action::Nxdomain),
+            _ => Err(anyhow::Error::msg(format!(
+                "Action should be 'drop' or 'nxdomain': '{s}'"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Drop => write!(f, "drop"),
+            Action::Nxdomain => write!(f, "nxdomain"),
+        }
+    }
+}
+
+impl Action {
+    fn number(self) -> u32 {
+        match self {
+            Action::Drop => ACTION_DROP,
+            Action::Nxdomain => ACTION_NXDOMAIN,
+        }
+    }
+
+    fn from_number(action: u32) -> Self {
+        if action == ACTION_NXDOMAIN {
+            Action::Nxdomain
+        } else {
+            Action::Drop
+        }
+    }
+}
+
+// A line of the file with the domains - the domain and the action (if not 'drop').
+#[derive(Debug, Clone)]
+struct Rule {
+    domain: Domain,
+    action: Action,
+}
+
+// Reads the domains from a file, one domain per line, optionally followed by the action. The empty
+// lines and the lines starting with a '#' are ignored.
+fn read_domains(path: &Path) -> anyhow::Result<Vec<Rule>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the domains from: '{}'", path.display()))?;
+
+    let mut rules = vec![];
+    for (lineno, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let context = || format!("{}:{}", path.display(), lineno + 1);
+        let mut fields = line.split_whitespace();
+        let domain = fields.next().unwrap().parse().with_context(context)?;
+        let action = match fields.next() {
+            Some(action) => action.parse().with_context(context)?,
+            None => Action::Drop,
+        };
+        if fields.next().is_some() {
+            let e = anyhow::Error::msg(format!("Unexpected fields after the action: '{line}'"));
+            return Err(e.context(context()));
+        }
+        rules.push(Rule { domain, action });
+    }
+
+    Ok(rules)
+}
+
+// The pinned maps of the program.
+struct DomainList {
+    domains: HashMap<MapData, DomainKey, DomainRule>,
+    hits: PerCpuArray<MapData, u64>,
+}
+
+impl DomainList {
+    // Opens the maps pinned by the 'pin' command for the given interface and tutorial.
+    fn open(opts: &PinnedOptions) -> anyhow::Result<Self> {
+        let map_pin_path = format!("/sys/fs/bpf/{}/{}/maps", opts.iface, opts.name);
+        if !Path::new(&map_pin_path).join("BLOCKED_DOMAINS").exists() {
+            return Err(anyhow::Error::msg(
+                "Domain maps are not pinned. Please run 'pin' to pin the maps.",
+            ));
+        }
+
+        Self::from_pin_path(&map_pin_path)
+    }
+
+    fn from_pin_path(map_pin_path: &str) -> anyhow::Result<Self> {
+        let open = |name: &str| MapData::from_pin(format!("{map_pin_path}/{name}"));
+        Ok(Self {
+            domains: Map::HashMap(open("BLOCKED_DOMAINS")?).try_into()?,
+            hits: Map::PerCpuArray(open("DOMAIN_HITS")?).try_into()?,
+        })
+    }
+
+    // Returns all the domains in the maps, with the rule of every domain.
+    fn rules(&self) -> anyhow::Result<BTreeMap<Domain, DomainRule>> {
+        let mut rules = BTreeMap::new();
+        for entry in self.domains.iter() {
+            let (key, rule) = entry?;
+            rules.insert(Domain::from_key(&key), rule);
+        }
+
+        Ok(rules)
+    }
+
+    fn hits(&self, id: u32) -> anyhow::Result<u64> {
+        Ok(self.hits.get(&id, 0)?.iter().sum())
+    }
+
+    // Adds the domain with the lowest unused rule id, the hit counter of the rule is reset. If the
+    // domain is already in the maps, only the action is changed.
+    fn add(&mut self, rule: &Rule) -> anyhow::Result<()> {
+        let rules = self.rules()?;
+        let key = rule.domain.key();
+        let action = rule.action.number();
+        if let Some(existing) = rules.get(&rule.domain) {
+            if existing.action == action {
+                info!("Domain '{}' is already in the list", rule.domain);
+            } else {
+                let id = existing.id;
+                self.domains.insert(key, DomainRule { id, action }, 0)?;
+                info!(
+                    "Action of the domain '{}' changed to: {}",
+                    rule.domain, rule.action
+                );
+            }
+            return Ok(());
+        }
+        let id = (0..MAX_DOMAINS)
+            .find(|id| !rules.values().any(|rule| rule.id == *id))
+            .ok_or_else(|| anyhow::Error::msg("Domain list is full"))?;
+
+        let zeros = PerCpuValues::try_from(vec![0u64; nr_cpus().map_err(|(_, e)| e)?])?;
+        self.hits.set(id, zeros, 0)?;
+        self.domains.insert(key, DomainRule { id, action }, 0)?;
+        info!(
+            "Domain '{}' added as the rule: {} ({})",
+            rule.domain, id, rule.action
+        );
+
+        Ok(())
+    }
+
+    fn remove(&mut self, domain: &Domain) -> anyhow::Result<()> {
+        self.domains
+            .remove(&domain.key())
+            .with_context(|| format!("Failed to remove the domain: '{domain}'"))?;
+        info!("Domain '{}' removed", domain);
+
+        Ok(())
+    }
+
+    // Makes the list the same as the given domains. The rules of the domains already in the list
+    // are kept, so that their hit counters are not reset.
+    fn load(&mut self, rules: &[Rule]) -> anyhow::Result<()> {
+        for domain in self.rules()?.into_keys() {
+            if !rules.iter().any(|rule| rule.domain == domain) {
+                self.remove(&domain)?;
+            }
+        }
+        for rule in rules {
+            self.add(rule)?;
+        }
+
+        Ok(())
+    }
+
+    fn print(&self) -> anyhow::Result<()> {
+        info!(
+            "{:<40} {:>8} {:>6} {:>12}",
+            "domain", "action", "rule", "hits"
+        );
+        for (domain, rule) in self.rules()? {
+            let action = Action::from_number(rule.action);
+            info!(
+                "{:<40} {:>8} {:>6} {:>12}",
+                domain.to_string(),
+                action.to_string(),
+                rule.id,
+                self.hits(rule.id)?
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn pin_program_and_maps(opts: PinOptions) -> anyhow::Result<()> {
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_file = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+
+    // The maps are pinned (and reused if already pinned) in this directory.
+    let map_pin_path = format!("/sys/fs/bpf/{}/{}/maps", opts.iface, opts.file);
+    std::fs::create_dir_all(&map_pin_path)?;
+
+    info!("Loading eBPF file: {bpf_file}");
+    let mut bpf = EbpfLoader::new()
+        .map_pin_path(&map_pin_path)
+        .load_file(bpf_file)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let link_id = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    // The link is pinned, so that the program stays attached after the runner exits.
+    let fd_link: FdLink = xdp.take_link(link_id)?.try_into()?;
+    let program_pin_path = format!("/sys/fs/bpf/{}/{}/programs", opts.iface, opts.file);
+    std::fs::create_dir_all(&program_pin_path)?;
+    let program_pin_path = format!("{}/{}", program_pin_path, program_name);
+    fd_link.pin(&program_pin_path)?;
+
+    info!(
+        "XDP Program: '{}' attached to interface: '{}' and pinned at path: '{}'",
+        program_name, &opts.iface, program_pin_path,
+    );
+
+    if let Some(domains) = opts.domains {
+        let pinned = PinnedOptions {
+            iface: opts.iface,
+            name: opts.file,
+        };
+        DomainList::open(&pinned)?.load(&read_domains(&domains)?)?;
+    }
+
+    Ok(())
+}
+
+fn modified(path: &Path) -> anyhow::Result<SystemTime> {
+    Ok(std::fs::metadata(path)?.modified()?)
+}
+
+// Loads the domains from the file, and if an interval is given, keeps loading the file again
+// every time it is modified until Ctrl-C.
+async fn load(opts: LoadOptions) -> anyhow::Result<()> {
+    let mut domain_list = DomainList::open(&opts.pinned)?;
+    domain_list.load(&read_domains(&opts.domains)?)?;
+
+    let Some(interval) = opts.interval else {
+        return Ok(());
+    };
+    if interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    info!(
+        "Watching '{}' for changes, waiting for Ctrl-C",
+        opts.domains.display()
+    );
+    let mut last_modified = modified(&opts.domains)?;
+    let mut poller_interval = time::interval(Duration::from_secs(interval));
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                let Ok(modified) = modified(&opts.domains) else {
+                    continue;
+                };
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                // A bad file does not stop the watching, the list is left as it was.
+                match read_domains(&opts.domains) {
+                    Ok(rules) => domain_list.load(&rules)?,
+                    Err(e) => warn!("{:#}", e),
+                }
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+const XDP_DROP: u32 = 1;
+const XDP_PASS: u32 = 2;
+const XDP_TX: u32 = 3;
+
+// The MAC addresses of the test packets.
+const CLIENT_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const SERVER_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+// The domains of the test run, the queries for these are sent twice for every family.
+const TEST_DROP_DOMAIN: &str = "blocked.example.com";
+const TEST_NXDOMAIN_DOMAIN: &str = "nx.example.com";
+
+// Runs the test packets through the program and keeps the number of the failed checks.
+struct TestRun<'a> {
+    prog_fd: BorrowedFd<'a>,
+    failed: u32,
+}
+
+impl TestRun<'_> {
+    // Runs the program on the packet and checks the action. Returns the packet after the program
+    // is run.
+    fn run(
+        &mut self,
+        name: &str,
+        packet: &DnsPacket,
+        expected_action: u32,
+    ) -> anyhow::Result<Vec<u8>> {
+        let (action, out) = test_run::test_run_xdp(self.prog_fd, &packet.build())
+            .context("Failed to run the program using `BPF_PROG_TEST_RUN`")?;
+        if action == expected_action {
+            info!("PASSED: {}", name);
+        } else {
+            error!(
+                "FAILED: {} (expected action: {}, returned action: {})",
+                name, expected_action, action
+            );
+            self.failed += 1;
+        }
+
+        Ok(out)
+    }
+
+    fn check(&mut self, name: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => info!("PASSED: {}", name),
+            Err(e) => {
+                error!("FAILED: {} ({})", name, e);
+                self.failed += 1;
+            }
+        }
+    }
+}
+
+// Checks that the response is a `NXDOMAIN` for the question of the query.
+fn check_nxdomain(query: &DnsPacket, question: &[u8], response: &DnsPacket) -> Result<(), String> {
+    if response.src_mac != query.dst_mac || response.dst_mac != query.src_mac {
+        return Err("MAC addresses not swapped".to_string());
+    }
+    if response.src_addr != query.dst_addr || response.dst_addr != query.src_addr {
+        return Err("IP addresses not swapped".to_string());
+    }
+    if response.src_port != query.dst_port || response.dst_port != query.src_port {
+        return Err("ports not swapped".to_string());
+    }
+    if response.id != query.id {
+        return Err(format!("ID: {:#06x}", response.id));
+    }
+    let flags = DNS_FLAG_QR | (query.flags & DNS_FLAG_RD) | DNS_FLAG_RA | DNS_RCODE_NXDOMAIN;
+    if response.flags != flags {
+        return Err(format!("flags: {:#06x}", response.flags));
+    }
+    if response.counts != [1, 0, 0, 0] || response.body != question {
+        return Err(format!(
+            "not only the question: counts: {:?}, {} bytes",
+            response.counts,
+            response.body.len()
+        ));
+    }
+
+    Ok(())
+}
+
+// Runs the queries for the blocked domains (in lower and in upper case), for the domains related
+// to the blocked domains, a response and a query to another port.
+fn test_queries(test: &mut TestRun, client: IpAddr, server: IpAddr) -> anyhow::Result<()> {
+    let family = if client.is_ipv4() { "IPv4" } else { "IPv6" };
+    let query = DnsPacket {
+        src_mac: CLIENT_MAC,
+        dst_mac: SERVER_MAC,
+        src_addr: client,
+        dst_addr: server,
+        src_port: 40000,
+        dst_port: DNS_PORT,
+        id: 0x1234,
+        flags: DNS_FLAG_RD,
+        counts: [1, 0, 0, 0],
+        body: question(TEST_DROP_DOMAIN, DNS_TYPE_A),
+    };
+
+    let name = format!("{family}: Query for a blocked domain dropped");
+    test.run(&name, &query, XDP_DROP)?;
+
+    let upper = DnsPacket {
+        body: question(&TEST_DROP_DOMAIN.to_ascii_uppercase(), DNS_TYPE_AAAA),
+        ..query.clone()
+    };
+    let name = format!("{family}: Query for a blocked domain in upper case dropped");
+    test.run(&name, &upper, XDP_DROP)?;
+
+    // The query has an EDNS record, which is removed from the response.
+    let nx_question = question(TEST_NXDOMAIN_DOMAIN, DNS_TYPE_A);
+    let nx = DnsPacket {
+        counts: [1, 0, 0, 1],
+        body: [nx_question.clone(), EDNS_OPT.to_vec()].concat(),
+        ..query.clone()
+    };
+    let name = format!("{family}: Query for a blocked domain answered with NXDOMAIN");
+    let out = test.run(&name, &nx, XDP_TX)?;
+    let name = format!("{family}: NXDOMAIN is the response to the query");
+    match DnsPacket::parse(&out) {
+        Ok(response) => test.check(&name, check_nxdomain(&nx, &nx_question, &response)),
+        Err(e) => test.check(&name, Err(e)),
+    }
+
+    let subdomain = DnsPacket {
+        body: question(&format!("www.{TEST_DROP_DOMAIN}"), DNS_TYPE_A),
+        ..query.clone()
+    };
+    let name = format!("{family}: Query for a subdomain of a blocked domain passed");
+    test.run(&name, &subdomain, XDP_PASS)?;
+
+    let parent = DnsPacket {
+        body: question(TEST_DROP_DOMAIN.split_once('.').unwrap().1, DNS_TYPE_A),
+        ..query.clone()
+    };
+    let name = format!("{family}: Query for the parent of a blocked domain passed");
+    test.run(&name, &parent, XDP_PASS)?;
+
+    let response = DnsPacket {
+        flags: DNS_FLAG_QR | DNS_FLAG_RD | DNS_FLAG_RA,
+        ..query.clone()
+    };
+    let name = format!("{family}: Response for a blocked domain passed");
+    test.run(&name, &response, XDP_PASS)?;
+
+    let other_port = DnsPacket {
+        dst_port: 5353,
+        ..query.clone()
+    };
+    let name = format!("{family}: Query for a blocked domain to another port passed");
+    test.run(&name, &other_port, XDP_PASS)?;
+
+    Ok(())
+}
+
+fn run_test_packets(bpf_file: &str, map_pin_path: &str) -> anyhow::Result<()> {
+    let mut bpf = EbpfLoader::new()
+        .map_pin_path(map_pin_path)
+        .load_file(bpf_file)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+
+    let mut domain_list = DomainList::from_pin_path(map_pin_path)?;
+    let rules = [
+        Rule {
+            domain: TEST_DROP_DOMAIN.parse()?,
+            action: Action::Drop,
+        },
+        Rule {
+            domain: TEST_NXDOMAIN_DOMAIN.parse()?,
+            action: Action::Nxdomain,
+        },
+    ];
+    domain_list.load(&rules)?;
+
+    let mut test = TestRun {
+        prog_fd: xdp.fd()?.as_fd(),
+        failed: 0,
+    };
+    test_queries(
+        &mut test,
+        IpAddr::V4(Ipv4Addr::new(10, 11, 1, 2)),
+        IpAddr::V4(Ipv4Addr::new(10, 11, 1, 1)),
+    )?;
+    test_queries(
+        &mut test,
+        IpAddr::V6(Ipv6Addr::new(0xfc00, 0x42de, 0xcafe, 1, 0, 0, 0, 2)),
+        IpAddr::V6(Ipv6Addr::new(0xfc00, 0x42de, 0xcafe, 1, 0, 0, 0, 1)),
+    )?;
+
+    // Two queries for the dropped domain and one for the answered domain, for every family.
+    let ids = domain_list.rules()?;
+    for (rule, expected) in rules.iter().zip([4, 2]) {
+        let hits = domain_list.hits(ids[&rule.domain].id)?;
+        let result = if hits == expected {
+            Ok(())
+        } else {
+            Err(format!("expected: {expected}, hits: {hits}"))
+        };
+        test.check(&format!("Hit counter of '{}'", rule.domain), result);
+    }
+
+    if test.failed > 0 {
+        return Err(anyhow::Error::msg(format!(
+            "{} checks failed.",
+            test.failed
+        )));
+    }
+    info!("All the checks passed.");
+
+    Ok(())
+}
+
+// Loads the program (without attaching it) and runs the test queries. The maps are pinned in a
+// separate directory, removed after the checks, so that the maps of a pinned program are not
+// changed.
+fn test_run(opts: TestRunOptions) -> anyhow::Result<()> {
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_file = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+
+    let map_pin_path = format!("/sys/fs/bpf/{}-test-run", opts.file);
+    if Path::new(&map_pin_path).exists() {
+        std::fs::remove_dir_all(&map_pin_path)?;
+    }
+    std::fs::create_dir_all(&map_pin_path)?;
+
+    let result = run_test_packets(&bpf_file, &map_pin_path);
+    std::fs::remove_dir_all(&map_pin_path)?;
+
+    result
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The program and the maps are pinned by the 'pin' command, the other commands work on the
+// pinned maps, so the domains can be changed while the program stays attached.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    env_logger::init();
+
+    let cli = CliCommand::parse();
+
+    match cli {
+        CliCommand::Pin(opts) => pin_program_and_maps(opts),
+        CliCommand::Load(opts) => load(opts).await,
+        CliCommand::Add(opts) => DomainList::open(&opts.pinned)?.add(&Rule {
+            domain: opts.domain,
+            action: opts.action,
+        }),
+        CliCommand::Remove(opts) => DomainList::open(&opts.pinned)?.remove(&opts.domain),
+        CliCommand::List(opts) => DomainList::open(&opts)?.print(),
+        CliCommand::TestRun(opts) => test_run(opts),
+    }
+}
@@ -0,0 +1,315 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use core::mem;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::bpf_xdp_adjust_tail,
+    macros::{map, xdp},
+    maps::{HashMap, PerCpuArray},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_dnshdr, parse_ethhdr, parse_ip6hdr, parse_iphdr, parse_udphdr, ptr_at, DnsHdr, EthHdr,
+    HdrCursor, Ipv4Hdr, Ipv6Hdr, UdpHdr, ETH_P_IP, ETH_P_IPV6, IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    DomainKey, DomainRule, ACTION_NXDOMAIN, DNS_PORT, MAX_DOMAINS, MAX_LABEL_LEN, MAX_NAME_LEN,
+};
+
+// The flags of the DNS header (in host byte order).
+const DNS_FLAG_QR: u16 = 0x8000;
+const DNS_OPCODE_MASK: u16 = 0x7800;
+const DNS_FLAG_RD: u16 = 0x0100;
+const DNS_FLAG_RA: u16 = 0x0080;
+const DNS_RCODE_NXDOMAIN: u16 = 3;
+
+// The type and the class following the name in a question.
+const QUESTION_TAIL_LEN: usize = 4;
+
+// Maximum length of the UDP datagram of a `NXDOMAIN` response, the headers and the question.
+const MAX_RESPONSE_LEN: usize =
+    mem::size_of::<UdpHdr>() + mem::size_of::<DnsHdr>() + MAX_NAME_LEN + QUESTION_TAIL_LEN;
+
+// The blocked domains, the maps are pinned, so that the Userspace program can update the domains
+// while the program is attached.
+#[map]
+static BLOCKED_DOMAINS: HashMap<DomainKey, DomainRule> =
+    HashMap::<DomainKey, DomainRule>::pinned(MAX_DOMAINS, 0);
+
+// Queries dropped or answered by every rule, indexed by the id of the rule.
+#[map]
+static DOMAIN_HITS: PerCpuArray<u64> = PerCpuArray::<u64>::pinned(MAX_DOMAINS, 0);
+
+// Adds the 16 bit words of the `data` (read in the native byte order) to the checksum `sum`.
+#[inline(always)]
+fn csum_add(mut sum: u32, data: *const u8, len: usize) -> u32 {
+    let words = data as *const u16;
+    for i in 0..len / 2 {
+        sum += unsafe { words.add(i).read_unaligned() } as u32;
+    }
+
+    sum
+}
+
+#[inline(always)]
+fn csum_fold(mut sum: u32) -> u16 {
+    sum = (sum & 0xffff) + (sum >> 16);
+    sum = (sum & 0xffff) + (sum >> 16);
+
+    !(sum as u16)
+}
+
+// Returns the checksum `check` updated for a 16 bit word changed from `old` to `new` (RFC 1624).
+#[inline(always)]
+fn csum_replace(check: u16, old: u16, new: u16) -> u16 {
+    csum_fold((!check) as u32 + (!old) as u32 + new as u32)
+}
+
+// Adds the `len` bytes of the packet at `pos` to the checksum `sum`, with a zero byte padding an
+// odd length. The loop is bounded by the maximum length of a response.
+#[inline(always)]
+fn csum_add_packet(ctx: &XdpContext, mut sum: u32, pos: usize, len: usize) -> Option<u32> {
+    for i in 0..MAX_RESPONSE_LEN / 2 {
+        if 2 * i + 2 > len {
+            break;
+        }
+        let word = ptr_at::<u16>(ctx, &HdrCursor { pos: pos + 2 * i })?;
+        sum += unsafe { word.read_unaligned() } as u32;
+    }
+    if len % 2 == 1 {
+        let byte = ptr_at::<u8>(ctx, &HdrCursor { pos: pos + len - 1 })?;
+        sum += unsafe { *byte } as u32;
+    }
+
+    Some(sum)
+}
+
+// Reads the name of the question at the cursor into the `key`, in lower case, and returns the
+// length of the name.
+//
+// The loop walks the name a byte at a time, up to `MAX_NAME_LEN` bytes, since the verifier only
+// accepts the loops with a bound. `label` is the position of the next length byte, the bytes
+// between two length bytes are the characters of a label. The compression pointers (and the other
+// length bytes above `MAX_LABEL_LEN`) are not expected in the queries, those are not matched.
+#[inline(always)]
+fn read_qname(ctx: &XdpContext, cursor: &HdrCursor, key: &mut DomainKey) -> Option<usize> {
+    let mut label = 0;
+    for i in 0..MAX_NAME_LEN {
+        let pos = HdrCursor {
+            pos: cursor.pos + i,
+        };
+        let byte = unsafe { *ptr_at::<u8>(ctx, &pos)? };
+        if i == label {
+            if byte == 0 {
+                return Some(i + 1);
+            }
+            if byte > MAX_LABEL_LEN {
+                return None;
+            }
+            label = i + byte as usize + 1;
+            key.name[i] = byte;
+        } else {
+            key.name[i] = byte.to_ascii_lowercase();
+        }
+    }
+
+    None
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Looks up the name of the DNS queries (over UDP, to the port 53) in the `BLOCKED_DOMAINS` map.
+// The queries for a blocked domain are either dropped or answered with a `NXDOMAIN` response,
+// depending on the action of the rule. All the other packets are passed.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    // The length of the IP header is kept for the response, from the header (not from the cursor),
+    // so that the verifier knows its bounds.
+    let (is_ipv4, ip_len) = match u16::from_be(unsafe { (*eth).h_proto }) {
+        ETH_P_IP => {
+            let Some(iph) = parse_iphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            // Only the first fragment has the UDP header, the fragments are left to the kernel.
+            let frag_off = u16::from_be(unsafe { (*iph).frag_off });
+            if unsafe { (*iph).protocol } != IPPROTO_UDP || frag_off & 0x3fff != 0 {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            (true, ((unsafe { (*iph).version_ihl } & 0x0F) as usize) * 4)
+        }
+        ETH_P_IPV6 => {
+            let Some(ip6h) = parse_ip6hdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            // The extension headers are not parsed, such packets are passed.
+            if unsafe { (*ip6h).nexthdr } != IPPROTO_UDP {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            (false, mem::size_of::<Ipv6Hdr>())
+        }
+        _ => return Ok(xdp_action::XDP_PASS),
+    };
+
+    let Some(udph) = parse_udphdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if u16::from_be(unsafe { (*udph).dest }) != DNS_PORT {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    // Only the standard queries with a single question are matched.
+    let Some(dnsh) = parse_dnshdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    let (flags, qdcount, ancount, nscount) = unsafe {
+        (
+            u16::from_be((*dnsh).flags),
+            u16::from_be((*dnsh).qdcount),
+            u16::from_be((*dnsh).ancount),
+            u16::from_be((*dnsh).nscount),
+        )
+    };
+    if flags & (DNS_FLAG_QR | DNS_OPCODE_MASK) != 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+    if qdcount != 1 || ancount != 0 || nscount != 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    let mut key = DomainKey {
+        name: [0; MAX_NAME_LEN],
+    };
+    let Some(name_len) = read_qname(ctx, &cursor, &mut key) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    let Some(rule) = (unsafe { BLOCKED_DOMAINS.get(&key) }) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    let (id, action) = (rule.id, rule.action);
+
+    if let Some(hits) = DOMAIN_HITS.get_ptr_mut(id) {
+        unsafe { *hits += 1 };
+    }
+
+    if action != ACTION_NXDOMAIN {
+        debug!(ctx, "Query dropped by the rule: {}", id);
+        return Ok(xdp_action::XDP_DROP);
+    }
+
+    // The question (the name, the type and the class) should be complete, since it is sent back.
+    cursor.pos += name_len;
+    if ptr_at::<[u8; QUESTION_TAIL_LEN]>(ctx, &cursor).is_none() {
+        return Ok(xdp_action::XDP_DROP);
+    }
+    let udp_len =
+        mem::size_of::<UdpHdr>() + mem::size_of::<DnsHdr>() + name_len + QUESTION_TAIL_LEN;
+
+    debug!(ctx, "Query answered with NXDOMAIN by the rule: {}", id);
+    nxdomain(ctx, is_ipv4, ip_len, udp_len)
+}
+
+// Turns the query into a `NXDOMAIN` response and sends it back.
+//
+// The headers are rewritten in place: the addresses and the ports are swapped, the flags and the
+// counts of the DNS header are set for a response with only the question, and the packet is
+// truncated after the question (removing the additional records of the query, eg. the EDNS
+// options) using `bpf_xdp_adjust_tail`.
+//
+// All the pointers to the packet are invalid after `bpf_xdp_adjust_tail`, the headers are parsed
+// again using the lengths of the headers of the query.
+#[inline(always)]
+fn nxdomain(ctx: &XdpContext, is_ipv4: bool, ip_len: usize, udp_len: usize) -> Result<u32, u32> {
+    let len = ctx.data_end() - ctx.data();
+    let new_len = mem::size_of::<EthHdr>() + ip_len + udp_len;
+    if unsafe { bpf_xdp_adjust_tail(ctx.ctx, new_len as i32 - len as i32) } != 0 {
+        return Err(xdp_action::XDP_ABORTED);
+    }
+
+    let mut cursor = HdrCursor::new(ctx);
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    unsafe { mem::swap(&mut (*eth).h_dest, &mut (*eth).h_source) };
+
+    // The sum of the pseudo header for the UDP checksum. Swapping the addresses does not change
+    // the IPv4 checksum, only the total length is updated.
+    let pseudo = (IPPROTO_UDP as u16).to_be() as u32 + (udp_len as u16).to_be() as u32;
+    let sum = if is_ipv4 {
+        let Some(iph) = ptr_at::<Ipv4Hdr>(ctx, &cursor) else {
+            return Err(xdp_action::XDP_ABORTED);
+        };
+        unsafe {
+            mem::swap(&mut (*iph).saddr, &mut (*iph).daddr);
+            let tot_len = ((ip_len + udp_len) as u16).to_be();
+            (*iph).check = csum_replace((*iph).check, (*iph).tot_len, tot_len);
+            (*iph).tot_len = tot_len;
+            csum_add(pseudo, &(*iph).saddr as *const u32 as *const u8, 8)
+        }
+    } else {
+        let Some(ip6h) = ptr_at::<Ipv6Hdr>(ctx, &cursor) else {
+            return Err(xdp_action::XDP_ABORTED);
+        };
+        unsafe {
+            mem::swap(&mut (*ip6h).saddr, &mut (*ip6h).daddr);
+            (*ip6h).payload_len = (udp_len as u16).to_be();
+            csum_add(pseudo, (*ip6h).saddr.as_ptr(), 32)
+        }
+    };
+    cursor.pos += ip_len;
+
+    let udp_pos = cursor.pos;
+    let Some(udph) = parse_udphdr(ctx, &mut cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    let Some(dnsh) = ptr_at::<DnsHdr>(ctx, &cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    unsafe {
+        mem::swap(&mut (*udph).source, &mut (*udph).dest);
+        (*udph).len = (udp_len as u16).to_be();
+        (*udph).check = 0;
+
+        // The ID, the opcode and the 'recursion desired' flag are kept from the query.
+        let flags = u16::from_be((*dnsh).flags) & (DNS_OPCODE_MASK | DNS_FLAG_RD);
+        (*dnsh).flags = (flags | DNS_FLAG_QR | DNS_FLAG_RA | DNS_RCODE_NXDOMAIN).to_be();
+        (*dnsh).ancount = 0;
+        (*dnsh).nscount = 0;
+        (*dnsh).arcount = 0;
+    }
+
+    let Some(sum) = csum_add_packet(ctx, sum, udp_pos, udp_len) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    // A zero checksum means no checksum for IPv4 (and is not allowed for IPv6), it is sent as all
+    // ones instead.
+    let check = match csum_fold(sum) {
+        0 => 0xffff,
+        check => check,
+    };
+    unsafe { (*udph).check = check };
+
+    Ok(xdp_action::XDP_TX)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,47 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the flows tracked at a time. When the map is full, the least recently used
+/// flows are evicted (without being exported).
+pub const MAX_FLOWS: u32 = 65536;
+
+/// The 5-tuple of a flow, the key of the `FLOWS` map.
+///
+/// The IPv4 addresses are stored as the IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`), so that a
+/// single map is used for both. The ports are in the host byte order and are `0` for the protocols
+/// other than TCP and UDP.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct FlowKey {
+    pub src_addr: [u8; 16],
+    pub dst_addr: [u8; 16],
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+    pub _pad: [u8; 3],
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for FlowKey {}
+
+/// Counters of a flow.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct FlowStats {
+    pub packets: u64,
+
+    pub bytes: u64,
+
+    /// Time the first packet of the flow was seen, from `bpf_ktime_get_ns` (`CLOCK_MONOTONIC`).
+    pub first_seen_ns: u64,
+
+    /// Time the last packet of the flow was seen, from `bpf_ktime_get_ns` (`CLOCK_MONOTONIC`).
+    pub last_seen_ns: u64,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for FlowStats {}
@@ -0,0 +1,579 @@
+// A minimal encoder and decoder for the IPFIX protocol, enough for exporting the flow records to
+// an IPFIX collector and for decoding the exported records in the local collector of the runner.
+//
+// Every message carries a Template Set with the two templates (for the IPv4 and the IPv6 flows),
+// followed by the Data Sets with the records. Sending the templates with every message keeps the
+// exporter stateless, which suits UDP, where the collector may miss any message. The decoder
+// supports the templates with any Information Elements of a fixed length, the Information
+// Elements not in a `FlowRecord` are skipped. See https://www.rfc-editor.org/rfc/rfc7011
+//
+// This module does not depend on the rest of the runner and can be copied as it is to the other
+// tutorials.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const IPFIX_VERSION: u16 = 10;
+
+const TEMPLATE_SET_ID: u16 = 2;
+const OPTIONS_TEMPLATE_SET_ID: u16 = 3;
+const MIN_DATA_SET_ID: u16 = 256;
+
+const TEMPLATE_ID_IPV4: u16 = 256;
+const TEMPLATE_ID_IPV6: u16 = 257;
+
+const MESSAGE_HEADER_LEN: usize = 16;
+const SET_HEADER_LEN: usize = 4;
+
+// The messages are kept smaller than the usual MTU, so that they are not fragmented.
+const MAX_MESSAGE_LEN: usize = 1400;
+
+// Bit of the Information Element id set for the enterprise specific elements.
+const ENTERPRISE_BIT: u16 = 0x8000;
+const VARIABLE_LENGTH: u16 = 0xFFFF;
+
+// The Information Elements used in the templates, from the IANA IPFIX registry.
+const OCTET_DELTA_COUNT: u16 = 1;
+const PACKET_DELTA_COUNT: u16 = 2;
+const PROTOCOL_IDENTIFIER: u16 = 4;
+const SOURCE_TRANSPORT_PORT: u16 = 7;
+const SOURCE_IPV4_ADDRESS: u16 = 8;
+const DESTINATION_TRANSPORT_PORT: u16 = 11;
+const DESTINATION_IPV4_ADDRESS: u16 = 12;
+const SOURCE_IPV6_ADDRESS: u16 = 27;
+const DESTINATION_IPV6_ADDRESS: u16 = 28;
+const FLOW_START_MILLISECONDS: u16 = 152;
+const FLOW_END_MILLISECONDS: u16 = 153;
+
+// Information Element ids and lengths of the fields of the templates, in the order the fields are
+// encoded in the records.
+const IPV4_TEMPLATE: [(u16, u16); 9] = [
+    (SOURCE_IPV4_ADDRESS, 4),
+    (DESTINATION_IPV4_ADDRESS, 4),
+    (SOURCE_TRANSPORT_PORT, 2),
+    (DESTINATION_TRANSPORT_PORT, 2),
+    (PROTOCOL_IDENTIFIER, 1),
+    (PACKET_DELTA_COUNT, 8),
+    (OCTET_DELTA_COUNT, 8),
+    (FLOW_START_MILLISECONDS, 8),
+    (FLOW_END_MILLISECONDS, 8),
+];
+
+const IPV6_TEMPLATE: [(u16, u16); 9] = [
+    (SOURCE_IPV6_ADDRESS, 16),
+    (DESTINATION_IPV6_ADDRESS, 16),
+    (SOURCE_TRANSPORT_PORT, 2),
+    (DESTINATION_TRANSPORT_PORT, 2),
+    (PROTOCOL_IDENTIFIER, 1),
+    (PACKET_DELTA_COUNT, 8),
+    (OCTET_DELTA_COUNT, 8),
+    (FLOW_START_MILLISECONDS, 8),
+    (FLOW_END_MILLISECONDS, 8),
+];
+
+/// A flow record, exported in the Data Sets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowRecord {
+    pub src_addr: IpAddr,
+    pub dst_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+    pub packets: u64,
+    pub bytes: u64,
+
+    /// Time of the first packet of the flow, as milliseconds since the UNIX Epoch.
+    pub start_ms: u64,
+
+    /// Time of the last packet of the flow, as milliseconds since the UNIX Epoch.
+    pub end_ms: u64,
+}
+
+impl FlowRecord {
+    fn template_id(&self) -> u16 {
+        match (self.src_addr, self.dst_addr) {
+            (IpAddr::V4(_), IpAddr::V4(_)) => TEMPLATE_ID_IPV4,
+            _ => TEMPLATE_ID_IPV6,
+        }
+    }
+
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match (self.src_addr, self.dst_addr) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                buf.extend_from_slice(&src.octets());
+                buf.extend_from_slice(&dst.octets());
+            }
+            (src, dst) => {
+                buf.extend_from_slice(&to_ipv6(src).octets());
+                buf.extend_from_slice(&to_ipv6(dst).octets());
+            }
+        }
+        buf.extend_from_slice(&self.src_port.to_be_bytes());
+        buf.extend_from_slice(&self.dst_port.to_be_bytes());
+        buf.push(self.protocol);
+        buf.extend_from_slice(&self.packets.to_be_bytes());
+        buf.extend_from_slice(&self.bytes.to_be_bytes());
+        buf.extend_from_slice(&self.start_ms.to_be_bytes());
+        buf.extend_from_slice(&self.end_ms.to_be_bytes());
+    }
+}
+
+fn to_ipv6(addr: IpAddr) -> Ipv6Addr {
+    match addr {
+        IpAddr::V4(addr) => addr.to_ipv6_mapped(),
+        IpAddr::V6(addr) => addr,
+    }
+}
+
+fn template_len(fields: &[(u16, u16)]) -> usize {
+    fields.iter().map(|(_, len)| *len as usize).sum()
+}
+
+/// Encodes the flow records into the IPFIX messages.
+pub struct Encoder {
+    domain_id: u32,
+    // Number of the data records sent in all the earlier messages.
+    sequence: u32,
+}
+
+impl Encoder {
+    pub fn new(domain_id: u32) -> Self {
+        Self {
+            domain_id,
+            sequence: 0,
+        }
+    }
+
+    /// Encodes the records into as many messages as needed, `export_time` is the time in seconds
+    /// since the UNIX Epoch.
+    pub fn encode(&mut self, records: &[FlowRecord], export_time: u32) -> Vec<Vec<u8>> {
+        let mut messages = vec![];
+        let mut message = Message::new(export_time, self.sequence, self.domain_id);
+        for record in records {
+            if !message.fits(record) {
+                self.sequence = self.sequence.wrapping_add(message.records);
+                messages.push(message.finish());
+                message = Message::new(export_time, self.sequence, self.domain_id);
+            }
+            message.push(record);
+        }
+        if message.records > 0 {
+            self.sequence = self.sequence.wrapping_add(message.records);
+            messages.push(message.finish());
+        }
+
+        messages
+    }
+}
+
+// A message being encoded.
+struct Message {
+    buf: Vec<u8>,
+    // Id and the offset of the Data Set being encoded.
+    set: Option<(u16, usize)>,
+    records: u32,
+}
+
+impl Message {
+    // Starts a message with the header and the Template Set.
+    fn new(export_time: u32, sequence: u32, domain_id: u32) -> Self {
+        let mut buf = Vec::with_capacity(MAX_MESSAGE_LEN);
+        buf.extend_from_slice(&IPFIX_VERSION.to_be_bytes());
+        // The length is set by `finish`.
+        buf.extend_from_slice(&0u16.to_be_bytes());
+        buf.extend_from_slice(&export_time.to_be_bytes());
+        buf.extend_from_slice(&sequence.to_be_bytes());
+        buf.extend_from_slice(&domain_id.to_be_bytes());
+
+        let start = buf.len();
+        buf.extend_from_slice(&TEMPLATE_SET_ID.to_be_bytes());
+        buf.extend_from_slice(&0u16.to_be_bytes());
+        for (id, fields) in [
+            (TEMPLATE_ID_IPV4, &IPV4_TEMPLATE),
+            (TEMPLATE_ID_IPV6, &IPV6_TEMPLATE),
+        ] {
+            buf.extend_from_slice(&id.to_be_bytes());
+            buf.extend_from_slice(&(fields.len() as u16).to_be_bytes());
+            for (ie, len) in fields {
+                buf.extend_from_slice(&ie.to_be_bytes());
+                buf.extend_from_slice(&len.to_be_bytes());
+            }
+        }
+        set_len(&mut buf, start);
+
+        Self {
+            buf,
+            set: None,
+            records: 0,
+        }
+    }
+
+    // Returns whether the record fits in the message, an empty message takes any record.
+    fn fits(&self, record: &FlowRecord) -> bool {
+        let id = record.template_id();
+        let mut len = self.buf.len() + Self::record_len(id);
+        if self.set.map(|(set_id, _)| set_id) != Some(id) {
+            len += SET_HEADER_LEN;
+        }
+
+        self.records == 0 || len <= MAX_MESSAGE_LEN
+    }
+
+    fn record_len(template_id: u16) -> usize {
+        if template_id == TEMPLATE_ID_IPV4 {
+            template_len(&IPV4_TEMPLATE)
+        } else {
+            template_len(&IPV6_TEMPLATE)
+        }
+    }
+
+    fn push(&mut self, record: &FlowRecord) {
+        let id = record.template_id();
+        if self.set.map(|(set_id, _)| set_id) != Some(id) {
+            self.close_set();
+            self.set = Some((id, self.buf.len()));
+            self.buf.extend_from_slice(&id.to_be_bytes());
+            self.buf.extend_from_slice(&0u16.to_be_bytes());
+        }
+        record.encode(&mut self.buf);
+        self.records += 1;
+    }
+
+    fn close_set(&mut self) {
+        if let Some((_, start)) = self.set.take() {
+            set_len(&mut self.buf, start);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.close_set();
+        let len = self.buf.len() as u16;
+        self.buf[2..4].copy_from_slice(&len.to_be_bytes());
+
+        self.buf
+    }
+}
+
+// Sets the length of the Set starting at `start` to the end of the buffer.
+fn set_len(buf: &mut [u8], start: usize) {
+    let len = (buf.len() - start) as u16;
+    buf[start + 2..start + 4].copy_from_slice(&len.to_be_bytes());
+}
+
+/// Decodes the IPFIX messages, keeping the templates received in the earlier messages.
+#[derive(Default)]
+pub struct Decoder {
+    templates: HashMap<u16, Vec<(u16, u16)>>,
+}
+
+impl Decoder {
+    /// Decodes the flow records in a message. The records of the Data Sets without a known
+    /// template are skipped.
+    pub fn decode(&mut self, msg: &[u8]) -> anyhow::Result<Vec<FlowRecord>> {
+        if msg.len() < MESSAGE_HEADER_LEN {
+            return Err(anyhow::Error::msg("Message too short"));
+        }
+        let version = be_u16(&msg[0..2]);
+        if version != IPFIX_VERSION {
+            return Err(anyhow::Error::msg(format!(
+                "Unsupported version: {version}"
+            )));
+        }
+        let len = be_u16(&msg[2..4]) as usize;
+        if len < MESSAGE_HEADER_LEN || len > msg.len() {
+            return Err(anyhow::Error::msg(format!("Invalid message length: {len}")));
+        }
+
+        let mut records = vec![];
+        let mut sets = &msg[MESSAGE_HEADER_LEN..len];
+        while !sets.is_empty() {
+            if sets.len() < SET_HEADER_LEN {
+                return Err(anyhow::Error::msg("Set header too short"));
+            }
+            let set_id = be_u16(&sets[0..2]);
+            let set_len = be_u16(&sets[2..4]) as usize;
+            if set_len < SET_HEADER_LEN || set_len > sets.len() {
+                return Err(anyhow::Error::msg(format!("Invalid set length: {set_len}")));
+            }
+            let body = &sets[SET_HEADER_LEN..set_len];
+            match set_id {
+                TEMPLATE_SET_ID => self.decode_templates(body)?,
+                OPTIONS_TEMPLATE_SET_ID => {}
+                id if id >= MIN_DATA_SET_ID => {
+                    if let Some(fields) = self.templates.get(&id) {
+                        decode_records(fields, body, &mut records);
+                    }
+                }
+                id => return Err(anyhow::Error::msg(format!("Invalid set id: {id}"))),
+            }
+            sets = &sets[set_len..];
+        }
+
+        Ok(records)
+    }
+
+    fn decode_templates(&mut self, mut body: &[u8]) -> anyhow::Result<()> {
+        // The rest of the set after the last template may be padding (shorter than a template
+        // header).
+        while body.len() >= 4 {
+            let id = be_u16(&body[0..2]);
+            let count = be_u16(&body[2..4]) as usize;
+            body = &body[4..];
+
+            let mut fields = vec![];
+            for _ in 0..count {
+                if body.len() < 4 {
+                    return Err(anyhow::Error::msg("Template record too short"));
+                }
+                let (ie, len) = (be_u16(&body[0..2]), be_u16(&body[2..4]));
+                body = &body[4..];
+                if len == VARIABLE_LENGTH {
+                    return Err(anyhow::Error::msg(
+                        "Variable length fields are not supported",
+                    ));
+                }
+                // The enterprise specific elements are followed by the enterprise number, such
+                // elements are never a field of the `FlowRecord`.
+                if ie & ENTERPRISE_BIT != 0 {
+                    if body.len() < 4 {
+                        return Err(anyhow::Error::msg("Template record too short"));
+                    }
+                    body = &body[4..];
+                }
+                fields.push((ie, len));
+            }
+            self.templates.insert(id, fields);
+        }
+
+        Ok(())
+    }
+}
+
+fn decode_records(fields: &[(u16, u16)], mut body: &[u8], records: &mut Vec<FlowRecord>) {
+    let len = template_len(fields);
+    if len == 0 {
+        return;
+    }
+    while body.len() >= len {
+        let mut record = FlowRecord {
+            src_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            dst_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            src_port: 0,
+            dst_port: 0,
+            protocol: 0,
+            packets: 0,
+            bytes: 0,
+            start_ms: 0,
+            end_ms: 0,
+        };
+        let mut pos = 0;
+        for (ie, len) in fields {
+            let value = &body[pos..pos + *len as usize];
+            pos += *len as usize;
+            match (*ie, value.len()) {
+                (SOURCE_IPV4_ADDRESS, 4) => record.src_addr = ipv4(value),
+                (DESTINATION_IPV4_ADDRESS, 4) => record.dst_addr = ipv4(value),
+                (SOURCE_IPV6_ADDRESS, 16) => record.src_addr = ipv6(value),
+                (DESTINATION_IPV6_ADDRESS, 16) => record.dst_addr = ipv6(value),
+                (SOURCE_TRANSPORT_PORT, _) => record.src_port = be_uint(value) as u16,
+                (DESTINATION_TRANSPORT_PORT, _) => record.dst_port = be_uint(value) as u16,
+                (PROTOCOL_IDENTIFIER, _) => record.protocol = be_uint(value) as u8,
+                (PACKET_DELTA_COUNT, _) => record.packets = be_uint(value),
+                (OCTET_DELTA_COUNT, _) => record.bytes = be_uint(value),
+                (FLOW_START_MILLISECONDS, _) => record.start_ms = be_uint(value),
+                (FLOW_END_MILLISECONDS, _) => record.end_ms = be_uint(value),
+                _ => {}
+            }
+        }
+        records.push(record);
+        body = &body[len..];
+    }
+}
+
+fn be_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+// The integer elements may be encoded in fewer bytes than their type ('reduced size encoding').
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | *byte as u64)
+}
+
+fn ipv4(bytes: &[u8]) -> IpAddr {
+    IpAddr::V4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3]))
+}
+
+fn ipv6(bytes: &[u8]) -> IpAddr {
+    let mut octets = [0u8; 16];
+    octets.copy_from_slice(bytes);
+
+    IpAddr::V6(Ipv6Addr::from(octets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_record(n: u8) -> FlowRecord {
+        FlowRecord {
+            src_addr: IpAddr::V4(Ipv4Addr::new(10, 11, 1, n)),
+            dst_addr: IpAddr::V4(Ipv4Addr::new(10, 11, 2, 1)),
+            src_port: 40000 + n as u16,
+            dst_port: 53,
+            protocol: 17,
+            packets: 3,
+            bytes: 210,
+            start_ms: 1_700_000_000_000,
+            end_ms: 1_700_000_001_500,
+        }
+    }
+
+    fn ipv6_record(n: u16) -> FlowRecord {
+        FlowRecord {
+            src_addr: IpAddr::V6(Ipv6Addr::new(0xfc00, 0xdead, 0xcafe, 1, 0, 0, 0, n)),
+            dst_addr: IpAddr::V6(Ipv6Addr::new(0xfc00, 0xdead, 0xcafe, 1, 0, 0, 0, 1)),
+            src_port: 50000 + n,
+            dst_port: 443,
+            protocol: 6,
+            packets: 12,
+            bytes: 9_000,
+            start_ms: 1_700_000_000_250,
+            end_ms: 1_700_000_004_000,
+        }
+    }
+
+    fn message_sequence(msg: &[u8]) -> u32 {
+        u32::from_be_bytes([msg[8], msg[9], msg[10], msg[11]])
+    }
+
+    #[test]
+    fn ipv4_and_ipv6_records_round_trip() {
+        let records = vec![
+            ipv4_record(1),
+            ipv6_record(2),
+            ipv6_record(3),
+            ipv4_record(4),
+        ];
+
+        let messages = Encoder::new(7).encode(&records, 1_700_000_005);
+        assert_eq!(messages.len(), 1);
+        let msg = &messages[0];
+        assert_eq!(be_u16(&msg[0..2]), IPFIX_VERSION);
+        assert_eq!(be_u16(&msg[2..4]) as usize, msg.len());
+        assert_eq!(&msg[4..8], &1_700_000_005u32.to_be_bytes());
+        assert_eq!(message_sequence(msg), 0);
+        assert_eq!(&msg[12..16], &7u32.to_be_bytes());
+
+        assert_eq!(Decoder::default().decode(msg).unwrap(), records);
+    }
+
+    #[test]
+    fn records_are_split_into_messages() {
+        let records = (0..60).map(ipv6_record).collect::<Vec<_>>();
+
+        let mut encoder = Encoder::new(0);
+        let messages = encoder.encode(&records, 0);
+        assert!(messages.len() > 1);
+
+        let mut decoder = Decoder::default();
+        let mut decoded = vec![];
+        for msg in &messages {
+            assert!(msg.len() <= MAX_MESSAGE_LEN);
+            // The sequence number is the number of the records in the earlier messages.
+            assert_eq!(message_sequence(msg) as usize, decoded.len());
+            decoded.extend(decoder.decode(msg).unwrap());
+        }
+        assert_eq!(decoded, records);
+
+        // The sequence numbers continue in the next call.
+        let next = encoder.encode(&[ipv4_record(1)], 0);
+        assert_eq!(message_sequence(&next[0]), 60);
+    }
+
+    #[test]
+    fn full_message_does_not_fit_another_record() {
+        let mut message = Message::new(0, 0, 0);
+        assert!(message.fits(&ipv6_record(0)));
+        while message.fits(&ipv6_record(0)) {
+            message.push(&ipv6_record(0));
+        }
+        assert!(message.buf.len() + Message::record_len(TEMPLATE_ID_IPV6) > MAX_MESSAGE_LEN);
+
+        // A record with the other template needs a new Data Set header as well.
+        let len = message.buf.len();
+        assert_eq!(
+            message.fits(&ipv4_record(0)),
+            len + SET_HEADER_LEN + Message::record_len(TEMPLATE_ID_IPV4) <= MAX_MESSAGE_LEN
+        );
+
+        // An empty message takes any record.
+        let empty = Message::new(0, 0, 0);
+        assert_eq!(empty.records, 0);
+        assert!(empty.fits(&ipv6_record(0)));
+    }
+
+    #[test]
+    fn template_set_is_decoded() {
+        let mut decoder = Decoder::default();
+        let records = decoder.decode(&Message::new(0, 0, 0).finish()).unwrap();
+        assert!(records.is_empty());
+
+        assert_eq!(decoder.templates.len(), 2);
+        assert_eq!(decoder.templates[&TEMPLATE_ID_IPV4], IPV4_TEMPLATE);
+        assert_eq!(decoder.templates[&TEMPLATE_ID_IPV6], IPV6_TEMPLATE);
+    }
+
+    // Builds a message from a Template Set with one template and a Data Set for the template.
+    fn message_with_template(template: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut msg = vec![0u8; MESSAGE_HEADER_LEN];
+        msg[0..2].copy_from_slice(&IPFIX_VERSION.to_be_bytes());
+        for (set_id, body) in [(TEMPLATE_SET_ID, template), (300, data)] {
+            msg.extend_from_slice(&set_id.to_be_bytes());
+            msg.extend_from_slice(&((SET_HEADER_LEN + body.len()) as u16).to_be_bytes());
+            msg.extend_from_slice(body);
+        }
+        let len = msg.len() as u16;
+        msg[2..4].copy_from_slice(&len.to_be_bytes());
+
+        msg
+    }
+
+    #[test]
+    fn foreign_template_is_decoded() {
+        // Template 300: the source IPv4 address, an enterprise specific element (skipped) and
+        // the packet count in the reduced size encoding (2 bytes).
+        let mut template = vec![];
+        for value in [300, 3, SOURCE_IPV4_ADDRESS, 4, ENTERPRISE_BIT | 1, 1] {
+            template.extend_from_slice(&value.to_be_bytes());
+        }
+        template.extend_from_slice(&29305u32.to_be_bytes());
+        for value in [PACKET_DELTA_COUNT, 2] {
+            template.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let data = [192, 0, 2, 1, 0xff, 0x01, 0x02];
+        let records = Decoder::default()
+            .decode(&message_with_template(&template, &data))
+            .unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].src_addr, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        assert_eq!(records[0].packets, 0x0102);
+    }
+
+    #[test]
+    fn records_without_template_are_skipped() {
+        let msg = &Encoder::new(0).encode(&[ipv4_record(1)], 0)[0];
+
+        // The same message without the Template Set.
+        let template_set_len = be_u16(&msg[MESSAGE_HEADER_LEN + 2..MESSAGE_HEADER_LEN + 4]);
+        let mut stripped = msg[..MESSAGE_HEADER_LEN].to_vec();
+        stripped.extend_from_slice(&msg[MESSAGE_HEADER_LEN + template_set_len as usize..]);
+        let len = stripped.len() as u16;
+        stripped[2..4].copy_from_slice(&len.to_be_bytes());
+
+        assert!(Decoder::default().decode(&stripped).unwrap().is_empty());
+    }
+}
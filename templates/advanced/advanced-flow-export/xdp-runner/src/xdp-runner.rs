@@ -0,0 +1,300 @@
+mod ipfix;
+
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+use aya::maps::{HashMap, MapData};
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::net::UdpSocket;
+use tokio::process::Command;
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{FlowKey, FlowStats};
+
+use ipfix::{Decoder, Encoder, FlowRecord};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Address of the IPFIX collector, to which the flow records are exported over UDP.
+    #[clap(long, default_value = "127.0.0.1:4739")]
+    collector: SocketAddr,
+
+    /// A flow is exported (and removed) when no packet is seen for `IDLE_TIMEOUT` seconds.
+    #[clap(long, default_value_t = 15)]
+    idle_timeout: u64,
+
+    /// Interval (in seconds) between two successive checks for the idle flows.
+    #[clap(long, default_value_t = 5)]
+    interval: u64,
+
+    /// Check the export by sending `CHECK` pings from inside the test environment of the
+    /// interface (created using the `testenv` scripts) to a local collector and exit.
+    #[clap(long)]
+    check: Option<u32>,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+const NSEC_PER_MSEC: u64 = 1_000_000;
+
+// The Observation Domain of the exported messages, identifying the exporter to the collector.
+const OBSERVATION_DOMAIN_ID: u32 = 1;
+
+const IPPROTO_ICMPV6: u8 = 58;
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+// The IPv4 addresses are stored as the IPv4-mapped IPv6 addresses by the eBPF program.
+fn flow_ip(addr: [u8; 16]) -> IpAddr {
+    let addr = Ipv6Addr::from(addr);
+    match addr.to_ipv4_mapped() {
+        Some(addr) => IpAddr::V4(addr),
+        None => IpAddr::V6(addr),
+    }
+}
+
+// The times of the flows are from `CLOCK_MONOTONIC` (time since boot), the records need the time
+// since the UNIX Epoch. The difference between the two clocks is added to the times of the flows.
+fn flow_record(key: &FlowKey, stats: &FlowStats, time_offset: u64) -> FlowRecord {
+    FlowRecord {
+        src_addr: flow_ip(key.src_addr),
+        dst_addr: flow_ip(key.dst_addr),
+        src_port: key.src_port,
+        dst_port: key.dst_port,
+        protocol: key.protocol,
+        packets: stats.packets,
+        bytes: stats.bytes,
+        start_ms: (stats.first_seen_ns + time_offset) / NSEC_PER_MSEC,
+        end_ms: (stats.last_seen_ns + time_offset) / NSEC_PER_MSEC,
+    }
+}
+
+// Removes the flows idle for more than `idle_timeout` (or all the flows if `None`) from the map
+// and returns the records of the removed flows.
+//
+// A packet of a flow received after the flow is read and before it is removed is not counted,
+// the next packet of the flow starts a new flow.
+fn expire_flows(
+    flows: &mut HashMap<MapData, FlowKey, FlowStats>,
+    idle_timeout: Option<Duration>,
+) -> anyhow::Result<Vec<FlowRecord>> {
+    let now = clock_ns(libc::CLOCK_MONOTONIC);
+    let time_offset = clock_ns(libc::CLOCK_REALTIME) - now;
+
+    let expired = flows
+        .iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|(_, stats)| match idle_timeout {
+            Some(timeout) => now.saturating_sub(stats.last_seen_ns) > timeout.as_nanos() as u64,
+            None => true,
+        })
+        .collect::<Vec<_>>();
+
+    let mut records = vec![];
+    for (key, stats) in expired {
+        // The flow may be evicted by the LRU map in the meantime.
+        if flows.remove(&key).is_ok() {
+            records.push(flow_record(&key, &stats, time_offset));
+        }
+    }
+
+    Ok(records)
+}
+
+// Exports the records to the collector, returns the number of messages sent.
+async fn export(
+    socket: &UdpSocket,
+    encoder: &mut Encoder,
+    collector: SocketAddr,
+    records: &[FlowRecord],
+) -> anyhow::Result<usize> {
+    let export_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32;
+    let messages = encoder.encode(records, export_time);
+    for message in &messages {
+        socket.send_to(message, collector).await?;
+    }
+    if !records.is_empty() {
+        info!(
+            "Exported {} flows in {} messages to {}",
+            records.len(),
+            messages.len(),
+            collector
+        );
+    }
+
+    Ok(messages.len())
+}
+
+// Sends the pings from inside the test environment and checks the records received by a local
+// collector. All the flows are exported after the pings, the records decoded by the collector
+// should be the same as the exported records and should have a flow for the pings.
+//
+// The `testenv` scripts create the namespace with the same name as the interface, with the
+// `veth0` interface inside. The pings are sent to the all nodes multicast address, so that the
+// address of the interface is not needed.
+async fn check_export(
+    iface: &str,
+    count: u32,
+    flows: &mut HashMap<MapData, FlowKey, FlowStats>,
+    socket: &UdpSocket,
+) -> anyhow::Result<()> {
+    // The exporter socket is bound in the address family of the `--collector`, so the local
+    // collector is bound to the loopback address of the same family.
+    let bind_addr = if socket.local_addr()?.is_ipv4() {
+        "127.0.0.1:0"
+    } else {
+        "[::1]:0"
+    };
+    let collector = UdpSocket::bind(bind_addr).await?;
+    let collector_addr = collector.local_addr()?;
+    info!("Local collector listening on {}", collector_addr);
+
+    let status = Command::new("ip")
+        .args([
+            "netns", "exec", iface, "ping", "-6", "-q", "-i", "0.2", "-I", "veth0",
+        ])
+        .args(["-c", &count.to_string(), "ff02::1"])
+        .status()
+        .await
+        .context("Failed to run 'ping' in the test environment")?;
+    if !status.success() {
+        warn!("'ping' exited with: {}", status);
+    }
+
+    let records = expire_flows(flows, None)?;
+    let mut encoder = Encoder::new(OBSERVATION_DOMAIN_ID);
+    let sent = export(socket, &mut encoder, collector_addr, &records).await?;
+
+    let mut decoder = Decoder::default();
+    let mut decoded = vec![];
+    let mut buf = vec![0u8; 65536];
+    for _ in 0..sent {
+        let len = time::timeout(Duration::from_secs(1), collector.recv(&mut buf))
+            .await
+            .context("Timed out waiting for the exported messages")??;
+        decoded.extend(decoder.decode(&buf[..len])?);
+    }
+
+    if decoded != records {
+        error!("FAILED: The decoded records are not the same as the exported records.");
+        return Err(anyhow::Error::msg("Export check failed."));
+    }
+
+    let multicast: IpAddr = "ff02::1".parse()?;
+    let pings = decoded
+        .iter()
+        .find(|record| record.protocol == IPPROTO_ICMPV6 && record.dst_addr == multicast)
+        .filter(|record| record.packets >= count as u64);
+    let Some(pings) = pings else {
+        error!("FAILED: No flow with all the pings was exported.");
+        return Err(anyhow::Error::msg("Export check failed."));
+    };
+    info!("Flow of the pings: {:?}", pings);
+    info!("PASSED: {} flows exported and decoded.", decoded.len());
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// Every `--interval` seconds, the flows idle for more than `--idle-timeout` seconds are removed
+// from the `FLOWS` map and exported to the collector. All the remaining flows are exported on
+// exit.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut flows = HashMap::try_from(bpf.take_map("FLOWS").unwrap())?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'!",
+        program_name, &opts.iface
+    );
+
+    let bind_addr = if opts.collector.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+
+    if let Some(count) = opts.check {
+        return check_export(&opts.iface, count, &mut flows, &socket).await;
+    }
+
+    info!(
+        "Exporting the flows to {}, waiting for Ctrl-C",
+        opts.collector
+    );
+    let mut encoder = Encoder::new(OBSERVATION_DOMAIN_ID);
+    let idle_timeout = Duration::from_secs(opts.idle_timeout);
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                let records = expire_flows(&mut flows, Some(idle_timeout))?;
+                if let Err(e) = export(&socket, &mut encoder, opts.collector, &records).await {
+                    warn!("Failed to export the flows: {:#}", e);
+                }
+            }
+            _ = signal::ctrl_c() => {
+                break;
+            }
+        }
+    }
+
+    let records = expire_flows(&mut flows, None)?;
+    export(&socket, &mut encoder, opts.collector, &records).await?;
+    info!("Exiting...");
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::bpf_ktime_get_ns,
+    macros::{map, xdp},
+    maps::LruHashMap,
+    programs::XdpContext,
+};
+
+use parsing_helpers::{
+    parse_ethhdr_vlan, parse_ip6hdr, parse_iphdr, parse_tcphdr, parse_udphdr, HdrCursor,
+    ETH_P_IP, ETH_P_IPV6, IPPROTO_TCP, IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{FlowKey, FlowStats, MAX_FLOWS};
+
+// The flows, keyed by the 5-tuple. The Userspace program removes the flows after exporting them.
+#[map]
+static FLOWS: LruHashMap<FlowKey, FlowStats> =
+    LruHashMap::<FlowKey, FlowStats>::with_max_entries(MAX_FLOWS, 0);
+
+// Returns the 5-tuple of the packet, the IPv4 addresses are mapped to the IPv6 addresses.
+#[inline(always)]
+fn flow_key(ctx: &XdpContext) -> Option<FlowKey> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let mut key = FlowKey {
+        src_addr: [0; 16],
+        dst_addr: [0; 16],
+        src_port: 0,
+        dst_port: 0,
+        protocol: 0,
+        _pad: [0; 3],
+    };
+
+    let (_, eth_type) = parse_ethhdr_vlan(ctx, &mut cursor)?;
+    match eth_type {
+        ETH_P_IP => {
+            let iph = parse_iphdr(ctx, &mut cursor)?;
+            let (saddr, daddr) = unsafe { ((*iph).saddr, (*iph).daddr) };
+            key.src_addr[10] = 0xff;
+            key.src_addr[11] = 0xff;
+            key.src_addr[12..].copy_from_slice(&saddr.to_ne_bytes());
+            key.dst_addr[10] = 0xff;
+            key.dst_addr[11] = 0xff;
+            key.dst_addr[12..].copy_from_slice(&daddr.to_ne_bytes());
+            key.protocol = unsafe { (*iph).protocol };
+        }
+        ETH_P_IPV6 => {
+            let ip6h = parse_ip6hdr(ctx, &mut cursor)?;
+            unsafe {
+                key.src_addr = (*ip6h).saddr;
+                key.dst_addr = (*ip6h).daddr;
+                key.protocol = (*ip6h).nexthdr;
+            }
+        }
+        _ => return None,
+    }
+
+    match key.protocol {
+        IPPROTO_TCP => {
+            let tcph = parse_tcphdr(ctx, &mut cursor)?;
+            unsafe {
+                key.src_port = u16::from_be((*tcph).source);
+                key.dst_port = u16::from_be((*tcph).dest);
+            }
+        }
+        IPPROTO_UDP => {
+            let udph = parse_udphdr(ctx, &mut cursor)?;
+            unsafe {
+                key.src_port = u16::from_be((*udph).source);
+                key.dst_port = u16::from_be((*udph).dest);
+            }
+        }
+        _ => {}
+    }
+
+    Some(key)
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Counts the packet in the flow of its 5-tuple, a new flow is added for the first packet. All the
+// packets are passed.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let Some(key) = flow_key(ctx) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let now = unsafe { bpf_ktime_get_ns() };
+    let len = (ctx.data_end() - ctx.data()) as u64;
+    match FLOWS.get_ptr_mut(&key) {
+        // The updates are not atomic, the packets of a flow are usually received on the same CPU
+        // (the NICs select the receive queue from a hash of the 5-tuple).
+        Some(stats) => unsafe {
+            (*stats).packets += 1;
+            (*stats).bytes += len;
+            (*stats).last_seen_ns = now;
+        },
+        None => {
+            let stats = FlowStats {
+                packets: 1,
+                bytes: len,
+                first_seen_ns: now,
+                last_seen_ns: now,
+            };
+            // Fails only if another CPU added the flow in the meantime, the packet is not counted.
+            let _ = FLOWS.insert(&key, &stats, 0);
+        }
+    }
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,112 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the VIPs (Virtual IP address, port and protocol) served by the load balancer.
+pub const MAX_VIPS: u32 = 16;
+
+/// Maximum number of the backends, for all the VIPs together.
+pub const MAX_BACKENDS: u32 = 256;
+
+/// Size of the Maglev lookup table of a VIP. This should be a prime number, much larger than the
+/// number of the backends of a VIP (at least 100 times) for an even distribution.
+pub const LOOKUP_TABLE_SIZE: u32 = 4099;
+
+/// The entry of a lookup table without a backend, the packets for such an entry are dropped.
+pub const NO_BACKEND: u32 = u32::MAX;
+
+/// The packets for the VIP are encapsulated in an IPv4 header (IP-in-IP).
+pub const ENCAP_IPIP: u32 = 0;
+
+/// The packets for the VIP are encapsulated in an IPv4 and a UDP header (GUE variant 1).
+pub const ENCAP_GUE: u32 = 1;
+
+/// Seed of the hash of the 5-tuple, used for selecting the backend of a packet.
+pub const FLOW_HASH_SEED: u32 = 0x4c42_0001;
+
+/// A VIP, the key of the `VIPS` map. The address is in the network byte order (as in the packet)
+/// and the port is in the host byte order.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord))]
+pub struct VipKey {
+    pub addr: u32,
+    pub port: u16,
+    pub protocol: u8,
+    pub _pad: u8,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for VipKey {}
+
+/// Information of a VIP, the value of the `VIPS` map.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct VipInfo {
+    /// Index of the lookup table of the VIP, the table starts at the entry
+    /// `index * LOOKUP_TABLE_SIZE` of the `LOOKUP_TABLES` map.
+    pub index: u32,
+
+    /// `ENCAP_IPIP` or `ENCAP_GUE`.
+    pub encap: u32,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for VipInfo {}
+
+/// Configuration of the load balancer, set by the Userspace program in the `LB_CONFIG` map.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct LbConfig {
+    /// Source address of the outer IPv4 header, in the network byte order.
+    pub source_addr: u32,
+
+    /// Destination UDP port of the GUE encapsulation, in the host byte order.
+    pub gue_port: u16,
+
+    /// MAC address of the next hop towards the backends.
+    pub next_hop_mac: [u8; 6],
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for LbConfig {}
+
+/// Hashes the words with a given seed (MurmurHash3 over 32 bit words).
+///
+/// The same function is used by the eBPF program for hashing the 5-tuple of a packet and by the
+/// Userspace program for building (and checking) the lookup tables, so it has to be `no_std`.
+#[inline(always)]
+pub fn hash_words<const N: usize>(words: [u32; N], seed: u32) -> u32 {
+    let mut h = seed;
+    let mut i = 0;
+    while i < N {
+        let k = words[i]
+            .wrapping_mul(0xcc9e_2d51)
+            .rotate_left(15)
+            .wrapping_mul(0x1b87_3593);
+        h = (h ^ k)
+            .rotate_left(13)
+            .wrapping_mul(5)
+            .wrapping_add(0xe654_6b64);
+        i += 1;
+    }
+    h ^= (N * 4) as u32;
+
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+
+    h
+}
+
+/// Hashes the 5-tuple of a flow, the addresses and the ports are in the host byte order.
+#[inline(always)]
+pub fn flow_hash(saddr: u32, daddr: u32, sport: u16, dport: u16, protocol: u8) -> u32 {
+    let ports = ((sport as u32) << 16) | dport as u32;
+    hash_words([saddr, daddr, ports, protocol as u32], FLOW_HASH_SEED)
+}
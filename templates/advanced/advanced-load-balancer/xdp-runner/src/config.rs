@@ -0,0 +1,190 @@
+// The configuration of the load balancer, read from a TOML file.
+//
+// ```toml
+// source_addr = "10.11.1.1"
+// next_hop_mac = "02:00:00:00:00:01"
+// gue_port = 6080
+//
+// [[vips]]
+// addr = "10.11.100.1"
+// port = 80
+// protocol = "tcp"
+// encap = "ipip"
+// backends = ["10.11.2.2", "10.11.3.2"]
+// ```
+
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use {{ to_snake_case tutorial_name }}_common::{VipKey, ENCAP_GUE, ENCAP_IPIP, MAX_BACKENDS, MAX_VIPS};
+
+// The UDP port assigned to GUE by the IANA.
+const GUE_PORT: u16 = 6080;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    pub(crate) fn number(self) -> u8 {
+        match self {
+            Protocol::Tcp => IPPROTO_TCP,
+            Protocol::Udp => IPPROTO_UDP,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Encap {
+    Ipip,
+    Gue,
+}
+
+impl Encap {
+    pub(crate) fn number(self) -> u32 {
+        match self {
+            Encap::Ipip => ENCAP_IPIP,
+            Encap::Gue => ENCAP_GUE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Vip {
+    pub(crate) addr: Ipv4Addr,
+    pub(crate) port: u16,
+    pub(crate) protocol: Protocol,
+    pub(crate) encap: Encap,
+
+    /// The backends of the VIP, the packets to a VIP without the backends are dropped.
+    pub(crate) backends: Vec<Ipv4Addr>,
+}
+
+impl Vip {
+    /// The key of the VIP in the `VIPS` map.
+    pub(crate) fn key(&self) -> VipKey {
+        VipKey {
+            addr: u32::from_ne_bytes(self.addr.octets()),
+            port: self.port,
+            protocol: self.protocol.number(),
+            _pad: 0,
+        }
+    }
+}
+
+impl fmt::Display for Vip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let protocol = match self.protocol {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+        };
+        write!(f, "{}:{}/{}", self.addr, self.port, protocol)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Source address of the outer IPv4 header (an address of the load balancer).
+    pub(crate) source_addr: Ipv4Addr,
+
+    /// MAC address of the next hop towards the backends, as 'xx:xx:xx:xx:xx:xx'.
+    pub(crate) next_hop_mac: String,
+
+    /// Destination UDP port of the GUE encapsulation.
+    #[serde(default = "default_gue_port")]
+    pub(crate) gue_port: u16,
+
+    #[serde(default)]
+    pub(crate) vips: Vec<Vip>,
+}
+
+fn default_gue_port() -> u16 {
+    GUE_PORT
+}
+
+impl Config {
+    /// Reads the configuration from the file and validates it.
+    pub(crate) fn read(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the config from: '{}'", path.display()))?;
+        let mut config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Invalid config: '{}'", path.display()))?;
+
+        config.validate()?;
+        // The lookup table depends on the order of the backends, so the backends are sorted to get
+        // the same table for the same backends in any order.
+        for vip in &mut config.vips {
+            vip.backends.sort();
+            vip.backends.dedup();
+        }
+
+        Ok(config)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        self.next_hop_mac()?;
+
+        if self.vips.len() > MAX_VIPS as usize {
+            return Err(anyhow::Error::msg(format!(
+                "At most {MAX_VIPS} VIPs are supported."
+            )));
+        }
+        for (i, vip) in self.vips.iter().enumerate() {
+            if self.vips[..i].iter().any(|other| other.key() == vip.key()) {
+                return Err(anyhow::Error::msg(format!("Duplicate VIP: {vip}")));
+            }
+        }
+        if self.backends().len() > MAX_BACKENDS as usize {
+            return Err(anyhow::Error::msg(format!(
+                "At most {MAX_BACKENDS} backends are supported."
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn next_hop_mac(&self) -> anyhow::Result<[u8; 6]> {
+        let mut mac = [0u8; 6];
+        let mut octets = self.next_hop_mac.split(':');
+        for octet in &mut mac {
+            *octet = octets
+                .next()
+                .and_then(|octet| u8::from_str_radix(octet, 16).ok())
+                .with_context(|| format!("Invalid MAC address: '{}'", self.next_hop_mac))?;
+        }
+        if octets.next().is_some() {
+            return Err(anyhow::Error::msg(format!(
+                "Invalid MAC address: '{}'",
+                self.next_hop_mac
+            )));
+        }
+
+        Ok(mac)
+    }
+
+    /// All the backends of all the VIPs, a backend of more than one VIP is included only once.
+    pub(crate) fn backends(&self) -> Vec<Ipv4Addr> {
+        let mut backends = self
+            .vips
+            .iter()
+            .flat_map(|vip| vip.backends.iter().copied())
+            .collect::<Vec<_>>();
+        backends.sort();
+        backends.dedup();
+
+        backends
+    }
+}
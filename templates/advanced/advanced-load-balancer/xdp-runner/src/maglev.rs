@@ -0,0 +1,248 @@
+// Building the Maglev lookup table of a VIP.
+//
+// Every backend has a permutation of the entries of the table, derived from the hash of the
+// address of the backend. The backends take turns to claim the next entry of their permutation
+// that is not yet claimed, until all the entries are claimed. Every backend gets (almost) the same
+// number of entries, and when a backend is added or removed, most of the entries of the other
+// backends stay the same, so most of the flows stay with their backends.
+//
+// See the 'Maglev: A Fast and Reliable Software Network Load Balancer' paper (NSDI 2016).
+
+use std::net::Ipv4Addr;
+
+use {{ to_snake_case tutorial_name }}_common::{flow_hash, hash_words, LOOKUP_TABLE_SIZE, NO_BACKEND};
+
+use crate::config::Vip;
+
+// Seeds of the two hashes of the address of a backend, for the offset and the skip of the
+// permutation.
+const OFFSET_SEED: u32 = 0x6d61_676c;
+const SKIP_SEED: u32 = 0x6576_3432;
+
+/// Returns the lookup table for the backends, an entry is the index of the backend in `backends`
+/// (or `NO_BACKEND` if there are no backends).
+pub(crate) fn lookup_table(backends: &[Ipv4Addr]) -> Vec<u32> {
+    let size = LOOKUP_TABLE_SIZE;
+    let mut table = vec![NO_BACKEND; size as usize];
+    if backends.is_empty() {
+        return table;
+    }
+
+    // The permutation of a backend is `(offset + j * skip) % size` for `j` in `0..size`, every
+    // entry appears exactly once, since the size is a prime and the skip is not zero.
+    let permutations = backends
+        .iter()
+        .map(|addr| {
+            let addr = u32::from(*addr);
+            let offset = hash_words([addr], OFFSET_SEED) % size;
+            let skip = hash_words([addr], SKIP_SEED) % (size - 1) + 1;
+            (offset, skip)
+        })
+        .collect::<Vec<_>>();
+
+    let mut next = vec![0u32; backends.len()];
+    let mut filled = 0;
+    loop {
+        for (backend, &(offset, skip)) in permutations.iter().enumerate() {
+            let mut entry = (offset as u64 + next[backend] as u64 * skip as u64) % size as u64;
+            while table[entry as usize] != NO_BACKEND {
+                next[backend] += 1;
+                entry = (offset as u64 + next[backend] as u64 * skip as u64) % size as u64;
+            }
+            table[entry as usize] = backend as u32;
+            next[backend] += 1;
+
+            filled += 1;
+            if filled == size {
+                return table;
+            }
+        }
+    }
+}
+
+// Returns the backend selected for a flow to the VIP from the lookup table of the VIP, the same
+// way as the eBPF program.
+pub(crate) fn select_backend(
+    vip: &Vip,
+    table: &[u32],
+    saddr: Ipv4Addr,
+    sport: u16,
+) -> Option<Ipv4Addr> {
+    let hash = flow_hash(
+        u32::from(saddr),
+        u32::from(vip.addr),
+        sport,
+        vip.port,
+        vip.protocol.number(),
+    );
+    match table[(hash % LOOKUP_TABLE_SIZE) as usize] {
+        NO_BACKEND => None,
+        backend => Some(vip.backends[backend as usize]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::config::{Encap, Protocol};
+
+    // Number of the flows to the VIP used by the tests.
+    const FLOWS: u32 = 20_000;
+
+    // A backend should get the flows within this fraction of the average number of the flows.
+    const MAX_IMBALANCE: f64 = 0.15;
+
+    // When a backend is added or removed, at most this fraction of the other flows may move to a
+    // different backend.
+    const MAX_DISRUPTION: f64 = 0.05;
+
+    fn vip(backends: &[Ipv4Addr]) -> Vip {
+        Vip {
+            addr: Ipv4Addr::new(10, 11, 100, 1),
+            port: 80,
+            protocol: Protocol::Tcp,
+            encap: Encap::Ipip,
+            backends: backends.to_vec(),
+        }
+    }
+
+    // The backends `10.11.2.2`, `10.11.3.2`, ... (sorted, as in a `Config`).
+    fn backends(count: u8) -> Vec<Ipv4Addr> {
+        (0..count)
+            .map(|i| Ipv4Addr::new(10, 11, 2 + i, 2))
+            .collect()
+    }
+
+    fn select_backends(vip: &Vip, flows: &[(Ipv4Addr, u16)]) -> Vec<Option<Ipv4Addr>> {
+        let table = lookup_table(&vip.backends);
+        flows
+            .iter()
+            .map(|(saddr, sport)| select_backend(vip, &table, *saddr, *sport))
+            .collect()
+    }
+
+    // Returns the fraction of the flows that are moved from a backend other than `changed` to
+    // another backend other than `changed`, when the backends change.
+    fn disruption(
+        before: &[Option<Ipv4Addr>],
+        after: &[Option<Ipv4Addr>],
+        changed: Ipv4Addr,
+    ) -> f64 {
+        let others = before
+            .iter()
+            .zip(after)
+            .filter(|(before, after)| **before != Some(changed) && **after != Some(changed))
+            .collect::<Vec<_>>();
+        let moved = others
+            .iter()
+            .filter(|(before, after)| before != after)
+            .count();
+
+        moved as f64 / others.len() as f64
+    }
+
+    #[test]
+    fn lookup_table_without_backends_is_empty() {
+        let table = lookup_table(&[]);
+        assert_eq!(table.len(), LOOKUP_TABLE_SIZE as usize);
+        assert!(table.iter().all(|backend| *backend == NO_BACKEND));
+
+        let flows = crate::test_flows(100);
+        assert!(select_backends(&vip(&[]), &flows)
+            .iter()
+            .all(Option::is_none));
+    }
+
+    #[test]
+    fn lookup_table_entries_are_balanced() {
+        for count in [1, 2, 3, 7, 10] {
+            let table = lookup_table(&backends(count));
+            let mut entries = vec![0u32; count as usize];
+            for backend in &table {
+                entries[*backend as usize] += 1;
+            }
+
+            let (min, max) = (
+                LOOKUP_TABLE_SIZE / count as u32,
+                LOOKUP_TABLE_SIZE.div_ceil(count as u32),
+            );
+            assert!(
+                entries.iter().all(|entries| (min..=max).contains(entries)),
+                "{count} backends: {entries:?}, expected {min} or {max}"
+            );
+        }
+    }
+
+    #[test]
+    fn lookup_table_is_deterministic() {
+        assert_eq!(lookup_table(&backends(5)), lookup_table(&backends(5)));
+    }
+
+    #[test]
+    fn flows_are_balanced() {
+        let flows = crate::test_flows(FLOWS);
+        for count in [2, 3, 10] {
+            let vip = vip(&backends(count));
+            let mut counts = BTreeMap::new();
+            for backend in select_backends(&vip, &flows) {
+                *counts.entry(backend.unwrap()).or_insert(0u32) += 1;
+            }
+
+            let average = FLOWS as f64 / count as f64;
+            for backend in &vip.backends {
+                let flows = counts.get(backend).copied().unwrap_or(0) as f64;
+                let imbalance = (flows - average).abs() / average;
+                assert!(
+                    imbalance <= MAX_IMBALANCE,
+                    "{count} backends: {counts:?}, {backend} off by {:.1}%",
+                    imbalance * 100.0
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn removing_a_backend_keeps_the_other_flows() {
+        let flows = crate::test_flows(FLOWS);
+        let vip = vip(&backends(5));
+        let before = select_backends(&vip, &flows);
+
+        for removed in &vip.backends {
+            let mut changed = vip.clone();
+            changed.backends.retain(|backend| backend != removed);
+            let after = select_backends(&changed, &flows);
+
+            // The flows of the removed backend are moved to the other backends.
+            assert!(after.iter().all(|backend| *backend != Some(*removed)));
+            let disruption = disruption(&before, &after, *removed);
+            assert!(
+                disruption <= MAX_DISRUPTION,
+                "removing {removed} moved {:.2}% of the other flows",
+                disruption * 100.0
+            );
+        }
+    }
+
+    #[test]
+    fn adding_a_backend_keeps_the_other_flows() {
+        let flows = crate::test_flows(FLOWS);
+        let vip = vip(&backends(5));
+        let before = select_backends(&vip, &flows);
+
+        let added = Ipv4Addr::new(10, 11, 50, 2);
+        let mut changed = vip.clone();
+        changed.backends.push(added);
+        changed.backends.sort();
+        let after = select_backends(&changed, &flows);
+
+        assert!(after.contains(&Some(added)));
+        let disruption = disruption(&before, &after, added);
+        assert!(
+            disruption <= MAX_DISRUPTION,
+            "adding {added} moved {:.2}% of the other flows",
+            disruption * 100.0
+        );
+    }
+}
@@ -0,0 +1,550 @@
+mod config;
+mod maglev;
+mod test_run;
+
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+
+use aya::maps::{Array, HashMap, MapData, PerCpuArray, PerCpuValues};
+use aya::programs::{Xdp, XdpFlags};
+use aya::util::nr_cpus;
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    hash_words, LbConfig, VipInfo, VipKey, LOOKUP_TABLE_SIZE, MAX_BACKENDS, MAX_VIPS, NO_BACKEND,
+};
+
+use config::{Config, Encap, Protocol, Vip};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// The configuration of the load balancer (the VIPs and their backends).
+    #[clap(short, long, default_value = "{{tutorial_name}}/lb.toml")]
+    config: PathBuf,
+
+    /// Interval (in seconds) between two successive displays of the counters, the configuration is
+    /// applied again if the file is modified.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Run the program on the generated packets to the VIPs (using `BPF_PROG_TEST_RUN`) and check
+    /// the encapsulated packets, instead of attaching the program to the interface.
+    #[clap(long)]
+    test_run: bool,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Number of the flows (packets) to every VIP used by the `--test-run` checks.
+const TEST_RUN_FLOWS: u32 = 200;
+
+// Seed of the hash generating the flows of `test_flows`.
+const FLOWS_SEED: u32 = 0x666c_6f77;
+
+// The MAC addresses of the generated packets, the packets are sent by the client to the load
+// balancer.
+const CLIENT_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const LB_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+const XDP_DROP: u32 = 1;
+const XDP_PASS: u32 = 2;
+const XDP_TX: u32 = 3;
+
+// The maps of the program, along with the indices of the VIPs and the backends in the maps.
+//
+// The indices are kept while the VIPs and the backends are in the configuration, so that changing
+// the configuration does not reset the counters of the other backends, and only the entries of
+// the lookup tables that are changed are written.
+struct Balancer {
+    config: Array<MapData, LbConfig>,
+    vips: HashMap<MapData, VipKey, VipInfo>,
+    lookup_tables: Array<MapData, u32>,
+    backends: Array<MapData, u32>,
+    backend_stats: PerCpuArray<MapData, u64>,
+
+    // The index and the lookup table (as written to the `LOOKUP_TABLES` map) of every VIP.
+    vip_tables: BTreeMap<VipKey, (u32, Vec<u32>)>,
+    backend_indices: BTreeMap<Ipv4Addr, u32>,
+}
+
+// Returns the lowest index below `max`, that is not in `used`.
+fn unused_index(used: impl Iterator<Item = u32> + Clone, max: u32) -> Option<u32> {
+    (0..max).find(|index| !used.clone().any(|used| used == *index))
+}
+
+impl Balancer {
+    fn new(bpf: &mut Ebpf) -> anyhow::Result<Self> {
+        Ok(Self {
+            config: Array::try_from(bpf.take_map("LB_CONFIG").unwrap())?,
+            vips: HashMap::try_from(bpf.take_map("VIPS").unwrap())?,
+            lookup_tables: Array::try_from(bpf.take_map("LOOKUP_TABLES").unwrap())?,
+            backends: Array::try_from(bpf.take_map("BACKENDS").unwrap())?,
+            backend_stats: PerCpuArray::try_from(bpf.take_map("BACKEND_STATS").unwrap())?,
+            vip_tables: BTreeMap::new(),
+            backend_indices: BTreeMap::new(),
+        })
+    }
+
+    // Makes the maps the same as the configuration.
+    //
+    // The VIPs not in the configuration are removed first, the new backends are added before the
+    // lookup tables referring to them are written, and the backends not in the configuration are
+    // forgotten only after no lookup table refers to them.
+    //
+    // A configuration that does not fit in the maps is refused before any map is changed. The
+    // indices and the lookup tables are only updated after the maps are written, so that they
+    // stay the same as the maps if writing a map fails, and the next `apply` writes the rest.
+    fn apply(&mut self, config: &Config) -> anyhow::Result<()> {
+        let backends = config.backends();
+        let new_backends = backends
+            .iter()
+            .filter(|addr| !self.backend_indices.contains_key(addr))
+            .count();
+        // The backends being removed keep their indices until the lookup tables are written, so
+        // they have to fit along with the new backends.
+        if self.backend_indices.len() + new_backends > MAX_BACKENDS as usize {
+            return Err(anyhow::Error::msg(format!(
+                "{} new backends do not fit along with the {} current backends (at most {}), add the backends in smaller steps.",
+                new_backends,
+                self.backend_indices.len(),
+                MAX_BACKENDS
+            )));
+        }
+
+        let lb_config = LbConfig {
+            source_addr: u32::from_ne_bytes(config.source_addr.octets()),
+            gue_port: config.gue_port,
+            next_hop_mac: config.next_hop_mac()?,
+        };
+        self.config.set(0, lb_config, 0)?;
+
+        let keys = config.vips.iter().map(Vip::key).collect::<Vec<_>>();
+        let stale = self
+            .vip_tables
+            .keys()
+            .filter(|key| !keys.contains(key))
+            .copied()
+            .collect::<Vec<_>>();
+        for key in stale {
+            self.vips.remove(&key)?;
+            self.vip_tables.remove(&key);
+        }
+
+        for addr in &backends {
+            if self.backend_indices.contains_key(addr) {
+                continue;
+            }
+            let index = unused_index(self.backend_indices.values().copied(), MAX_BACKENDS)
+                .context("No free index for a backend, remove some backends first")?;
+            let zeros = PerCpuValues::try_from(vec![0u64; nr_cpus().map_err(|(_, e)| e)?])?;
+            self.backend_stats.set(index, zeros, 0)?;
+            self.backends
+                .set(index, u32::from_ne_bytes(addr.octets()), 0)?;
+            self.backend_indices.insert(*addr, index);
+        }
+
+        for vip in &config.vips {
+            let key = vip.key();
+            // The entries of the lookup table in the map, unknown for a new VIP.
+            let (index, mut written) = match self.vip_tables.get(&key) {
+                Some((index, table)) => (*index, Some(table.clone())),
+                None => {
+                    let used = self.vip_tables.values().map(|(index, _)| *index);
+                    let index = unused_index(used, MAX_VIPS).context("No free lookup table")?;
+                    (index, None)
+                }
+            };
+
+            let table = maglev::lookup_table(&vip.backends)
+                .into_iter()
+                .map(|backend| match backend {
+                    NO_BACKEND => NO_BACKEND,
+                    backend => self.backend_indices[&vip.backends[backend as usize]],
+                })
+                .collect::<Vec<_>>();
+            let mut changed = 0;
+            for (entry, backend) in table.iter().enumerate() {
+                if written
+                    .as_ref()
+                    .is_some_and(|written| written[entry] == *backend)
+                {
+                    continue;
+                }
+                let map_entry = index * LOOKUP_TABLE_SIZE + entry as u32;
+                if let Err(e) = self.lookup_tables.set(map_entry, backend, 0) {
+                    // A new VIP is not in the `VIPS` map yet, its lookup table is written again
+                    // from the start.
+                    if let Some(written) = written {
+                        self.vip_tables.insert(key, (index, written));
+                    }
+                    return Err(e.into());
+                }
+                if let Some(written) = written.as_mut() {
+                    written[entry] = *backend;
+                }
+                changed += 1;
+            }
+
+            let info = VipInfo {
+                index,
+                encap: vip.encap.number(),
+            };
+            self.vips.insert(key, info, 0)?;
+            self.vip_tables.insert(key, (index, table));
+            info!(
+                "VIP: {} backends: {} lookup table entries changed: {}",
+                vip,
+                vip.backends.len(),
+                changed
+            );
+        }
+
+        self.backend_indices
+            .retain(|addr, _| backends.contains(addr));
+
+        Ok(())
+    }
+
+    fn print_stats(&self) -> anyhow::Result<()> {
+        for (addr, index) in &self.backend_indices {
+            let packets: u64 = self.backend_stats.get(index, 0)?.iter().sum();
+            info!("backend: {:<15} packets: {:>10}", addr.to_string(), packets);
+        }
+
+        Ok(())
+    }
+}
+
+// Deterministic pseudo random flows (the source address and the source port) to a VIP, the same
+// for every run, so that the checks (and the tests of the `maglev` module) are repeatable.
+fn test_flows(count: u32) -> Vec<(Ipv4Addr, u16)> {
+    (0..count)
+        .map(|i| {
+            let h = hash_words([i], FLOWS_SEED);
+            let saddr = Ipv4Addr::from(0x0a00_0000 | (h >> 8));
+            let sport = 1024 + (hash_words([i, h], FLOWS_SEED) % 64512) as u16;
+            (saddr, sport)
+        })
+        .collect()
+}
+
+// Computes the checksum of the IPv4 header, the result is zero for a header with a valid checksum.
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+// Builds an Ethernet + IPv4 + TCP or UDP packet to the VIP.
+fn build_packet(vip: &Vip, saddr: Ipv4Addr, sport: u16) -> Vec<u8> {
+    let payload = b"load balancer test";
+    let l4_len = match vip.protocol {
+        Protocol::Tcp => 20,
+        Protocol::Udp => 8,
+    };
+
+    let mut packet = vec![];
+    packet.extend_from_slice(&LB_MAC);
+    packet.extend_from_slice(&CLIENT_MAC);
+    packet.extend_from_slice(&0x0800u16.to_be_bytes());
+
+    let mut iph = [0u8; 20];
+    iph[0] = 0x45;
+    iph[2..4].copy_from_slice(&((20 + l4_len + payload.len()) as u16).to_be_bytes());
+    iph[8] = 64;
+    iph[9] = vip.protocol.number();
+    iph[12..16].copy_from_slice(&saddr.octets());
+    iph[16..20].copy_from_slice(&vip.addr.octets());
+    let check = ipv4_checksum(&iph);
+    iph[10..12].copy_from_slice(&check.to_be_bytes());
+    packet.extend_from_slice(&iph);
+
+    packet.extend_from_slice(&sport.to_be_bytes());
+    packet.extend_from_slice(&vip.port.to_be_bytes());
+    match vip.protocol {
+        // Sequence number, acknowledgment number, data offset (5 words) with SYN, window, checksum
+        // and urgent pointer. The checksums are not checked by the program.
+        Protocol::Tcp => {
+            packet.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0x50, 0x02, 0xff, 0xff, 0, 0, 0, 0])
+        }
+        Protocol::Udp => {
+            packet.extend_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+            packet.extend_from_slice(&[0, 0]);
+        }
+    }
+    packet.extend_from_slice(payload);
+
+    packet
+}
+
+// Checks the packet encapsulated by the program for the backend. The inner packet should be the
+// same as the IPv4 packet sent to the VIP.
+fn check_encap(
+    config: &Config,
+    vip: &Vip,
+    packet: &[u8],
+    out: &[u8],
+    backend: Ipv4Addr,
+) -> Result<(), String> {
+    let encap_len = match vip.encap {
+        Encap::Ipip => 20,
+        Encap::Gue => 28,
+    };
+    if out.len() != packet.len() + encap_len {
+        return Err(format!(
+            "length {}, expected {}",
+            out.len(),
+            packet.len() + encap_len
+        ));
+    }
+
+    let next_hop_mac = config.next_hop_mac().map_err(|e| e.to_string())?;
+    if out[0..6] != next_hop_mac || out[6..12] != packet[0..6] || out[12..14] != [0x08, 0x00] {
+        return Err("wrong Ethernet header".to_string());
+    }
+
+    let outer = &out[14..34];
+    let protocol = match vip.encap {
+        Encap::Ipip => 4,
+        Encap::Gue => 17,
+    };
+    if outer[0] != 0x45
+        || u16::from_be_bytes([outer[2], outer[3]]) as usize != out.len() - 14
+        || outer[9] != protocol
+        || ipv4_checksum(outer) != 0
+        || outer[12..16] != config.source_addr.octets()
+    {
+        return Err("wrong outer IPv4 header".to_string());
+    }
+    if outer[16..20] != backend.octets() {
+        return Err(format!(
+            "sent to {}, expected {}",
+            Ipv4Addr::new(outer[16], outer[17], outer[18], outer[19]),
+            backend
+        ));
+    }
+
+    if vip.encap == Encap::Gue {
+        let udph = &out[34..42];
+        if u16::from_be_bytes([udph[2], udph[3]]) != config.gue_port
+            || u16::from_be_bytes([udph[4], udph[5]]) as usize != out.len() - 34
+        {
+            return Err("wrong UDP header".to_string());
+        }
+    }
+
+    if out[14 + encap_len..] != packet[14..] {
+        return Err("inner packet changed".to_string());
+    }
+
+    Ok(())
+}
+
+// Runs the program on the packets of the flows to every VIP in the configuration (the maps should
+// have the configuration) and checks the action and the encapsulated packets. Returns the number
+// of the failed checks.
+fn test_run_config(
+    prog_fd: BorrowedFd<'_>,
+    config: &Config,
+    flows: &[(Ipv4Addr, u16)],
+) -> anyhow::Result<u32> {
+    let mut failed = 0;
+    for vip in &config.vips {
+        let table = maglev::lookup_table(&vip.backends);
+        let mut errors = vec![];
+        for (saddr, sport) in flows {
+            let packet = build_packet(vip, *saddr, *sport);
+            let (action, out) = test_run::test_run_xdp(prog_fd, &packet)
+                .context("Failed to run the program using `BPF_PROG_TEST_RUN`")?;
+            let result = match maglev::select_backend(vip, &table, *saddr, *sport) {
+                None if action == XDP_DROP => Ok(()),
+                None => Err(format!("action {action}, expected XDP_DROP")),
+                Some(backend) if action == XDP_TX => {
+                    check_encap(config, vip, &packet, &out, backend)
+                }
+                Some(_) => Err(format!("action {action}, expected XDP_TX")),
+            };
+            if let Err(e) = result {
+                errors.push(format!("{saddr}:{sport}: {e}"));
+            }
+        }
+
+        if errors.is_empty() {
+            info!(
+                "PASSED: VIP: {} all the {} packets handled as expected.",
+                vip,
+                flows.len()
+            );
+        } else {
+            error!(
+                "FAILED: VIP: {} {} of {} packets not handled as expected, first: {}",
+                vip,
+                errors.len(),
+                flows.len(),
+                errors[0]
+            );
+            failed += 1;
+        }
+    }
+
+    // A packet to an address and a port that is not a VIP is passed.
+    let other = Vip {
+        addr: Ipv4Addr::new(192, 0, 2, 1),
+        port: 9,
+        protocol: Protocol::Udp,
+        encap: Encap::Ipip,
+        backends: vec![],
+    };
+    if !config.vips.iter().any(|vip| vip.key() == other.key()) {
+        let packet = build_packet(&other, Ipv4Addr::new(10, 0, 0, 1), 1024);
+        let (action, _) = test_run::test_run_xdp(prog_fd, &packet)
+            .context("Failed to run the program using `BPF_PROG_TEST_RUN`")?;
+        if action == XDP_PASS {
+            info!("PASSED: Packet to {} passed.", other);
+        } else {
+            error!(
+                "FAILED: Packet to {} action {}, expected XDP_PASS.",
+                other, action
+            );
+            failed += 1;
+        }
+    }
+
+    Ok(failed)
+}
+
+// Runs the packets with the configuration, and then again after removing a backend of the first
+// VIP with more than one backend, so that updating the maps is checked too.
+fn run_test_packets(xdp: &Xdp, balancer: &mut Balancer, config: &Config) -> anyhow::Result<()> {
+    let prog_fd = xdp.fd()?.as_fd();
+    let flows = test_flows(TEST_RUN_FLOWS);
+
+    balancer.apply(config)?;
+    let mut failed = test_run_config(prog_fd, config, &flows)?;
+
+    if let Some(i) = config.vips.iter().position(|vip| vip.backends.len() > 1) {
+        let mut changed = config.clone();
+        let removed = changed.vips[i].backends.pop().unwrap();
+        info!(
+            "Removing the backend {} of the VIP {}",
+            removed, changed.vips[i]
+        );
+        balancer.apply(&changed)?;
+        failed += test_run_config(prog_fd, &changed, &flows)?;
+    }
+
+    if failed > 0 {
+        return Err(anyhow::Error::msg(format!("{failed} checks failed.")));
+    }
+    info!("All the checks passed.");
+
+    Ok(())
+}
+
+fn modified(path: &Path) -> anyhow::Result<SystemTime> {
+    Ok(std::fs::metadata(path)?.modified()?)
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The VIPs and the backends are set in the maps from the `--config` file before the program is
+// attached, the counters of the backends are displayed every `--interval` seconds and the file is
+// applied again when it is modified.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    let config = Config::read(&opts.config)?;
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut balancer = Balancer::new(&mut bpf)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+
+    if opts.test_run {
+        return run_test_packets(xdp, &mut balancer, &config);
+    }
+
+    balancer.apply(&config)?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'!",
+        program_name, &opts.iface
+    );
+
+    info!("Now waiting for Ctrl-C");
+    let mut last_modified = modified(&opts.config)?;
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                if let Ok(modified) = modified(&opts.config) {
+                    if modified != last_modified {
+                        last_modified = modified;
+                        // A bad file, or a configuration that cannot be applied, does not stop
+                        // the load balancer, the maps are left as they were.
+                        let applied = Config::read(&opts.config)
+                            .and_then(|config| balancer.apply(&config));
+                        if let Err(e) = applied {
+                            warn!("Failed to apply the config: {:#}", e);
+                        }
+                    }
+                }
+                balancer.print_stats()?;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use core::mem;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::bpf_xdp_adjust_head,
+    macros::{map, xdp},
+    maps::{Array, HashMap, PerCpuArray},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr, parse_iphdr, parse_tcphdr, parse_udphdr, ptr_at, HdrCursor, Ipv4Hdr, UdpHdr,
+    ETH_P_IP, IPPROTO_TCP, IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    flow_hash, LbConfig, VipInfo, VipKey, ENCAP_GUE, LOOKUP_TABLE_SIZE, MAX_BACKENDS, MAX_VIPS,
+    NO_BACKEND,
+};
+
+const IPPROTO_IPIP: u8 = 4;
+
+// TTL of the outer IPv4 header.
+const OUTER_TTL: u8 = 64;
+
+// Configuration of the load balancer, set by the Userspace program.
+#[map]
+static LB_CONFIG: Array<LbConfig> = Array::<LbConfig>::with_max_entries(1, 0);
+
+// The VIPs served by the load balancer.
+#[map]
+static VIPS: HashMap<VipKey, VipInfo> = HashMap::<VipKey, VipInfo>::with_max_entries(MAX_VIPS, 0);
+
+// The Maglev lookup tables of all the VIPs, one after the other. An entry is the index of the
+// backend in the `BACKENDS` map (or `NO_BACKEND`).
+#[map]
+static LOOKUP_TABLES: Array<u32> = Array::<u32>::with_max_entries(MAX_VIPS * LOOKUP_TABLE_SIZE, 0);
+
+// Addresses of the backends (in the network byte order), indexed by the backend.
+#[map]
+static BACKENDS: Array<u32> = Array::<u32>::with_max_entries(MAX_BACKENDS, 0);
+
+// Packets sent to every backend.
+#[map]
+static BACKEND_STATS: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(MAX_BACKENDS, 0);
+
+// Computes the checksum of an IPv4 header without the options.
+#[inline(always)]
+fn ipv4_csum(iph: *const Ipv4Hdr) -> u16 {
+    let words = iph as *const u16;
+    let mut sum = 0u32;
+    for i in 0..mem::size_of::<Ipv4Hdr>() / 2 {
+        sum += unsafe { *words.add(i) } as u32;
+    }
+    sum = (sum & 0xffff) + (sum >> 16);
+    sum = (sum & 0xffff) + (sum >> 16);
+
+    !(sum as u16)
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Selects the backend for the packets to a VIP from the Maglev lookup table of the VIP, using the
+// hash of the 5-tuple, encapsulates the packet and sends it back out of the interface.
+//
+// The backend depends only on the 5-tuple, so all the packets of a flow go to the same backend
+// without keeping any state for the flow (as long as the backends of the VIP do not change).
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if unsafe { (*eth).h_proto } != ETH_P_IP.to_be() {
+        return Ok(xdp_action::XDP_PASS);
+    }
+    let Some(iph) = parse_iphdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    // Only the first fragment has the UDP/TCP header, the bytes at the 'port' in the other
+    // fragments are the payload (the fragment offset is in the lower 13 bits). Their VIP can't be
+    // looked up, so they are passed to the kernel network stack.
+    if u16::from_be(unsafe { (*iph).frag_off }) & 0x1fff != 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    let protocol = unsafe { (*iph).protocol };
+    let (sport, dport) = match protocol {
+        IPPROTO_TCP => {
+            let Some(tcph) = parse_tcphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            unsafe { (u16::from_be((*tcph).source), u16::from_be((*tcph).dest)) }
+        }
+        IPPROTO_UDP => {
+            let Some(udph) = parse_udphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            unsafe { (u16::from_be((*udph).source), u16::from_be((*udph).dest)) }
+        }
+        _ => return Ok(xdp_action::XDP_PASS),
+    };
+
+    let (saddr, daddr) = unsafe { ((*iph).saddr, (*iph).daddr) };
+    let vip_key = VipKey {
+        addr: daddr,
+        port: dport,
+        protocol,
+        _pad: 0,
+    };
+    let Some(vip) = (unsafe { VIPS.get(&vip_key) }) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    let Some(config) = LB_CONFIG.get(0) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+
+    let hash = flow_hash(
+        u32::from_be(saddr),
+        u32::from_be(daddr),
+        sport,
+        dport,
+        protocol,
+    );
+    let index = vip.index * LOOKUP_TABLE_SIZE + hash % LOOKUP_TABLE_SIZE;
+    let Some(&backend) = LOOKUP_TABLES.get(index) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    if backend == NO_BACKEND {
+        return Ok(xdp_action::XDP_DROP);
+    }
+    let Some(&backend_addr) = BACKENDS.get(backend) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    if let Some(count) = BACKEND_STATS.get_ptr_mut(backend) {
+        unsafe { *count += 1 };
+    }
+    debug!(ctx, "Packet to the VIP sent to the backend: {}", backend);
+
+    encap(ctx, vip, config, backend_addr, hash)
+}
+
+// Adds the outer IPv4 header (and the UDP header for GUE) between the Ethernet header and the
+// IPv4 header of the packet, and returns `XDP_TX`.
+//
+// The packet was sent to the MAC address of the interface, which is the source MAC address of the
+// encapsulated packet, the destination MAC address is the next hop towards the backends.
+#[inline(always)]
+fn encap(
+    ctx: &XdpContext,
+    vip: &VipInfo,
+    config: &LbConfig,
+    backend_addr: u32,
+    hash: u32,
+) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    let Some(inner) = parse_iphdr(ctx, &mut cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    let lb_mac = unsafe { (*eth).h_dest };
+    let (inner_len, tos) = unsafe { (u16::from_be((*inner).tot_len), (*inner).tos) };
+
+    let gue = vip.encap == ENCAP_GUE;
+    let encap_len = if gue {
+        mem::size_of::<Ipv4Hdr>() + mem::size_of::<UdpHdr>()
+    } else {
+        mem::size_of::<Ipv4Hdr>()
+    };
+    if unsafe { bpf_xdp_adjust_head(ctx.ctx, -(encap_len as i32)) } != 0 {
+        return Err(xdp_action::XDP_ABORTED);
+    }
+
+    // After `bpf_xdp_adjust_head`, all the headers have to be parsed (bounds checked) again.
+    let mut cursor = HdrCursor::new(ctx);
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    unsafe {
+        (*eth).h_dest = config.next_hop_mac;
+        (*eth).h_source = lb_mac;
+        (*eth).h_proto = ETH_P_IP.to_be();
+    }
+
+    // The outer headers are not initialized yet, so they are not parsed, only bounds checked.
+    let Some(outer) = ptr_at::<Ipv4Hdr>(ctx, &cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    cursor.pos += mem::size_of::<Ipv4Hdr>();
+    unsafe {
+        (*outer).version_ihl = 0x45;
+        (*outer).tos = tos;
+        (*outer).tot_len = (inner_len + encap_len as u16).to_be();
+        (*outer).id = 0;
+        (*outer).frag_off = 0;
+        (*outer).ttl = OUTER_TTL;
+        (*outer).protocol = if gue { IPPROTO_UDP } else { IPPROTO_IPIP };
+        (*outer).check = 0;
+        (*outer).saddr = config.source_addr;
+        (*outer).daddr = backend_addr;
+        (*outer).check = ipv4_csum(outer);
+    }
+
+    if gue {
+        let Some(udph) = ptr_at::<UdpHdr>(ctx, &cursor) else {
+            return Err(xdp_action::XDP_ABORTED);
+        };
+        // The source port is from the hash of the inner 5-tuple, so that the backends (and the
+        // routers in between) can spread the flows over the CPUs and the paths. The checksum is
+        // optional for UDP over IPv4.
+        unsafe {
+            (*udph).source = (0xc000 | (hash as u16 & 0x3fff)).to_be();
+            (*udph).dest = config.gue_port.to_be();
+            (*udph).len = (inner_len + mem::size_of::<UdpHdr>() as u16).to_be();
+            (*udph).check = 0;
+        }
+    }
+
+    Ok(xdp_action::XDP_TX)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
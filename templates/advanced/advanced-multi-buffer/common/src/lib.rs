@@ -0,0 +1,44 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Frames in a single buffer, the whole frame is between `data` and `data_end`.
+pub const BUFFER_LINEAR: u32 = 0;
+
+/// Multi-buffer frames, only the first part of the frame is between `data` and `data_end`, the
+/// rest is in the fragments.
+pub const BUFFER_MULTI: u32 = 1;
+
+/// Number of the kinds of the frames, the size of the `FRAME_STATS` map.
+pub const NUM_BUFFER_KINDS: u32 = 2;
+
+/// Counters of the frames of a kind, the value of the `FRAME_STATS` map.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct FrameStats {
+    pub packets: u64,
+
+    /// The total length of the frames (`bpf_xdp_get_buff_len`), including the fragments.
+    pub bytes: u64,
+
+    /// The length of the linear part of the frames (`data_end - data`).
+    pub linear_bytes: u64,
+}
+
+/// Configuration of the program, the value at the index 0 of the `CONFIG` map.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct Config {
+    /// The UDP packets to this destination port are dropped, `0` to pass all the packets.
+    pub drop_port: u16,
+    pub _pad: u16,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for FrameStats {}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for Config {}
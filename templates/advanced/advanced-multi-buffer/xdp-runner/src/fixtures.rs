@@ -0,0 +1,158 @@
+// Fixture packets for testing the program using `BPF_PROG_TEST_RUN`.
+//
+// For a frags-aware program, the kernel puts the packet in a multi-buffer frame if it does not
+// fit in a page (with the headroom and the tailroom), so the jumbo packets test the multi-buffer
+// path of the program. The checksums in the packets are not computed, as the program does not
+// verify them.
+
+use {{ to_snake_case tutorial_name }}_common::{BUFFER_LINEAR, BUFFER_MULTI};
+
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86DD;
+const IPPROTO_UDP: u8 = 17;
+
+const XDP_DROP: u32 = 1;
+const XDP_PASS: u32 = 2;
+
+/// Frame size of the jumbo packets (for the MTU of 9000).
+const JUMBO_FRAME_LEN: usize = 9014;
+
+/// Frame size of the smallest multi-buffer packets, a little larger than a page.
+const PAGE_FRAME_LEN: usize = 4200;
+
+/// A packet, the XDP action expected for the packet and the counters (`BUFFER_*`) in which the
+/// packet is expected to be counted.
+pub(crate) struct Fixture {
+    pub(crate) name: &'static str,
+    pub(crate) packet: Vec<u8>,
+    pub(crate) expected: u32,
+    pub(crate) buffer: u32,
+}
+
+fn ethhdr(h_proto: u16) -> Vec<u8> {
+    let mut hdr = vec![];
+    hdr.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x02]);
+    hdr.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+    hdr.extend_from_slice(&h_proto.to_be_bytes());
+
+    hdr
+}
+
+// UDP header and the payload, making a UDP packet of `udp_len` bytes.
+fn udp(dest: u16, udp_len: usize) -> Vec<u8> {
+    let mut udp = vec![];
+    udp.extend_from_slice(&40000u16.to_be_bytes());
+    udp.extend_from_slice(&dest.to_be_bytes());
+    udp.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend((0..udp_len - 8).map(|i| i as u8));
+
+    udp
+}
+
+// IPv4 UDP packet, of `frame_len` bytes including the Ethernet header.
+fn ipv4_udp(dest: u16, frame_len: usize) -> Vec<u8> {
+    let udp_len = frame_len - 14 - 20;
+
+    let mut pkt = ethhdr(ETH_P_IP);
+    pkt.extend_from_slice(&[0x45, 0]);
+    pkt.extend_from_slice(&((20 + udp_len) as u16).to_be_bytes());
+    pkt.extend_from_slice(&[0, 0, 0x40, 0, 64, IPPROTO_UDP, 0, 0]);
+    pkt.extend_from_slice(&[10, 11, 1, 2]);
+    pkt.extend_from_slice(&[10, 11, 1, 1]);
+    pkt.extend(udp(dest, udp_len));
+
+    pkt
+}
+
+// IPv6 UDP packet, of `frame_len` bytes including the Ethernet header.
+fn ipv6_udp(dest: u16, frame_len: usize) -> Vec<u8> {
+    let udp_len = frame_len - 14 - 40;
+
+    let mut pkt = ethhdr(ETH_P_IPV6);
+    pkt.extend_from_slice(&[0x60, 0, 0, 0]);
+    pkt.extend_from_slice(&(udp_len as u16).to_be_bytes());
+    pkt.extend_from_slice(&[IPPROTO_UDP, 64]);
+    pkt.extend_from_slice(&[
+        0xfc, 0x00, 0x42, 0xde, 0xca, 0xfe, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2,
+    ]);
+    pkt.extend_from_slice(&[
+        0xfc, 0x00, 0x42, 0xde, 0xca, 0xfe, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1,
+    ]);
+    pkt.extend(udp(dest, udp_len));
+
+    pkt
+}
+
+/// Returns the fixture packets, for the program configured with the `drop_port`.
+pub(crate) fn fixtures(drop_port: u16) -> Vec<Fixture> {
+    let other_port = drop_port.wrapping_add(1).max(1);
+
+    vec![
+        Fixture {
+            name: "IPv4 UDP, single buffer",
+            packet: ipv4_udp(other_port, 128),
+            expected: XDP_PASS,
+            buffer: BUFFER_LINEAR,
+        },
+        Fixture {
+            name: "IPv4 UDP to the drop port, single buffer",
+            packet: ipv4_udp(drop_port, 128),
+            expected: XDP_DROP,
+            buffer: BUFFER_LINEAR,
+        },
+        Fixture {
+            name: "IPv4 UDP, multi-buffer",
+            packet: ipv4_udp(other_port, PAGE_FRAME_LEN),
+            expected: XDP_PASS,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "IPv4 UDP to the drop port, multi-buffer",
+            packet: ipv4_udp(drop_port, PAGE_FRAME_LEN),
+            expected: XDP_DROP,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "IPv4 UDP jumbo frame",
+            packet: ipv4_udp(other_port, JUMBO_FRAME_LEN),
+            expected: XDP_PASS,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "IPv4 UDP jumbo frame to the drop port",
+            packet: ipv4_udp(drop_port, JUMBO_FRAME_LEN),
+            expected: XDP_DROP,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "IPv6 UDP jumbo frame",
+            packet: ipv6_udp(other_port, JUMBO_FRAME_LEN),
+            expected: XDP_PASS,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "IPv6 UDP jumbo frame to the drop port",
+            packet: ipv6_udp(drop_port, JUMBO_FRAME_LEN),
+            expected: XDP_DROP,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "IPv4 jumbo frame with invalid header length",
+            packet: {
+                let mut pkt = ipv4_udp(drop_port, JUMBO_FRAME_LEN);
+                // IHL of 4 (16 bytes) is less than the minimum IPv4 header length.
+                pkt[14] = 0x44;
+                pkt
+            },
+            expected: XDP_PASS,
+            buffer: BUFFER_MULTI,
+        },
+        Fixture {
+            name: "Truncated IPv4 header",
+            packet: ipv4_udp(drop_port, 128)[..24].to_vec(),
+            expected: XDP_PASS,
+            buffer: BUFFER_LINEAR,
+        },
+    ]
+}
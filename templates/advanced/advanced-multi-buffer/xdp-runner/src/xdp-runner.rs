@@ -0,0 +1,259 @@
+mod fixtures;
+mod test_run;
+
+use std::os::fd::AsFd;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use aya::maps::{Array, MapData, PerCpuArray};
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::process::Command;
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    Config, FrameStats, BUFFER_LINEAR, BUFFER_MULTI, NUM_BUFFER_KINDS,
+};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Drop the UDP packets to this destination port.
+    #[clap(long)]
+    drop_port: Option<u16>,
+
+    /// Set the MTU of the interface (and of the `veth0` interface inside the test environment of
+    /// the interface, if there is one) before attaching the program, eg. 9000 for jumbo frames.
+    #[clap(long)]
+    mtu: Option<u32>,
+
+    /// Interval (in seconds) between two successive displays of the counters.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Run the program on the fixture packets (using `BPF_PROG_TEST_RUN`) and check the actions
+    /// returned and the counters, instead of attaching the program to the interface.
+    #[clap(long)]
+    test_run: bool,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Names of the kinds of the frames, the index in this array is the `BUFFER_*` kind.
+const BUFFER_NAMES: [&str; NUM_BUFFER_KINDS as usize] = ["single buffer", "multi-buffer"];
+
+// The drop port used by the `--test-run`, if no `--drop-port` is given.
+const TEST_DROP_PORT: u16 = 5201;
+
+// Returns the counters of the given kind of the frames, summed for all the CPUs.
+fn frame_stats(stats: &PerCpuArray<MapData, FrameStats>, kind: u32) -> anyhow::Result<FrameStats> {
+    let mut total = FrameStats::default();
+    for cpu_stats in stats.get(&kind, 0)?.iter() {
+        total.packets += cpu_stats.packets;
+        total.bytes += cpu_stats.bytes;
+        total.linear_bytes += cpu_stats.linear_bytes;
+    }
+
+    Ok(total)
+}
+
+fn print_stats(stats: &PerCpuArray<MapData, FrameStats>) -> anyhow::Result<()> {
+    for kind in [BUFFER_LINEAR, BUFFER_MULTI] {
+        let total = frame_stats(stats, kind)?;
+        let average = |bytes: u64| bytes.checked_div(total.packets).unwrap_or(0);
+        info!(
+            "{:<13} packets: {:>10} bytes: {:>12} average length: {:>5} (linear part: {:>5})",
+            BUFFER_NAMES[kind as usize],
+            total.packets,
+            total.bytes,
+            average(total.bytes),
+            average(total.linear_bytes)
+        );
+    }
+
+    Ok(())
+}
+
+// Runs the command, returning an error if it fails.
+async fn run_command(program: &str, args: &[&str]) -> anyhow::Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .await
+        .with_context(|| format!("Failed to run '{program}'"))?;
+    if !status.success() {
+        return Err(anyhow::Error::msg(format!(
+            "'{} {}' exited with: {}",
+            program,
+            args.join(" "),
+            status
+        )));
+    }
+
+    Ok(())
+}
+
+// Sets the MTU of the interface and of the `veth0` interface inside the namespace of the test
+// environment (the `testenv` scripts create the namespace with the same name as the interface).
+// Both the ends of a `veth` pair need the larger MTU, for the jumbo frames to be sent and
+// received.
+async fn set_mtu(iface: &str, mtu: u32) -> anyhow::Result<()> {
+    let mtu = mtu.to_string();
+    run_command("ip", &["link", "set", "dev", iface, "mtu", &mtu]).await?;
+
+    let peer = [
+        "netns", "exec", iface, "ip", "link", "set", "dev", "veth0", "mtu", &mtu,
+    ];
+    if let Err(e) = run_command("ip", &peer).await {
+        warn!("Failed to set the MTU inside the test environment: {}", e);
+    }
+    info!("MTU of '{}' set to {}", iface, mtu);
+
+    Ok(())
+}
+
+// Runs the program on all the fixture packets and reports the fixtures for which the action
+// returned by the program or the change of the counters is not the expected one.
+fn run_fixtures(
+    xdp: &Xdp,
+    stats: &PerCpuArray<MapData, FrameStats>,
+    drop_port: u16,
+) -> Result<(), anyhow::Error> {
+    let prog_fd = xdp.fd()?.as_fd();
+
+    let mut failed = 0;
+    let fixtures = fixtures::fixtures(drop_port);
+    for fixture in &fixtures {
+        let before = frame_stats(stats, fixture.buffer)?;
+        let (action, _) = test_run::test_run_xdp(prog_fd, &fixture.packet)
+            .context("Failed to run the program using `BPF_PROG_TEST_RUN`")?;
+        let after = frame_stats(stats, fixture.buffer)?;
+
+        let packets = after.packets - before.packets;
+        let bytes = after.bytes - before.bytes;
+        if action != fixture.expected {
+            error!(
+                "FAILED: {} (expected action: {}, returned action: {})",
+                fixture.name, fixture.expected, action
+            );
+            failed += 1;
+        } else if packets != 1 || bytes != fixture.packet.len() as u64 {
+            error!(
+                "FAILED: {} (expected 1 {} packet of {} bytes, counted: {} packets of {} bytes)",
+                fixture.name,
+                BUFFER_NAMES[fixture.buffer as usize],
+                fixture.packet.len(),
+                packets,
+                bytes
+            );
+            failed += 1;
+        } else {
+            info!("PASSED: {}", fixture.name);
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::Error::msg(format!(
+            "{} of {} fixtures failed.",
+            failed,
+            fixtures.len()
+        )));
+    }
+    info!("All {} fixtures passed.", fixtures.len());
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The program is frags-aware (in the `xdp.frags` section), so it is loaded with the
+// `BPF_F_XDP_HAS_FRAGS` flag and the kernel allows attaching it to an interface with an MTU larger
+// than a page. The counters of the single buffer and the multi-buffer frames are displayed every
+// `--interval` seconds.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+    if opts.drop_port == Some(0) {
+        return Err(anyhow::Error::msg("Drop port should not be 0."));
+    }
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    // `0` disables the dropping, except for the `--test-run`, which needs a drop port.
+    let drop_port = match opts.drop_port {
+        Some(drop_port) => drop_port,
+        None if opts.test_run => TEST_DROP_PORT,
+        None => 0,
+    };
+    let mut config = Array::try_from(bpf.map_mut("CONFIG").unwrap())?;
+    config.set(0, Config { drop_port, _pad: 0 }, 0)?;
+    let stats = PerCpuArray::try_from(bpf.take_map("FRAME_STATS").unwrap())?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+
+    if opts.test_run {
+        return run_fixtures(xdp, &stats, drop_port);
+    }
+
+    if let Some(mtu) = opts.mtu {
+        set_mtu(&opts.iface, mtu).await?;
+    }
+
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'! Now waiting for Ctrl-C",
+        program_name, &opts.iface
+    );
+    if drop_port != 0 {
+        info!("Dropping the UDP packets to the port: {}", drop_port);
+    }
+
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                print_stats(&stats)?;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::bpf_xdp_get_buff_len,
+    macros::{map, xdp},
+    maps::{Array, PerCpuArray},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr, parse_ip6hdr, parse_iphdr, parse_udphdr, HdrCursor, ETH_P_IP, ETH_P_IPV6,
+    IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    Config, FrameStats, BUFFER_LINEAR, BUFFER_MULTI, NUM_BUFFER_KINDS,
+};
+
+// Configuration of the program, set by the runner.
+#[map]
+static CONFIG: Array<Config> = Array::<Config>::with_max_entries(1, 0);
+
+// Counters of the single buffer and the multi-buffer frames, indexed by `BUFFER_*`.
+#[map]
+static FRAME_STATS: PerCpuArray<FrameStats> =
+    PerCpuArray::<FrameStats>::with_max_entries(NUM_BUFFER_KINDS, 0);
+
+// Returns the destination port of the UDP packets.
+#[inline(always)]
+fn udp_dest_port(ctx: &XdpContext) -> Option<u16> {
+    let mut cursor = HdrCursor { offset: 0 };
+
+    let ip_type = match parse_ethhdr(ctx, &mut cursor)? {
+        ETH_P_IP => parse_iphdr(ctx, &mut cursor)?,
+        ETH_P_IPV6 => parse_ip6hdr(ctx, &mut cursor)?,
+        _ => return None,
+    };
+    if ip_type != IPPROTO_UDP {
+        return None;
+    }
+
+    parse_udphdr(ctx, &mut cursor)
+}
+
+// The `frags` makes the program 'frags-aware' (the `xdp.frags` section), the program is loaded
+// with the `BPF_F_XDP_HAS_FRAGS` flag and can be attached to the interfaces with an MTU larger
+// than a page, on which it gets the multi-buffer frames.
+#[xdp(frags)]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Counts the frame with the full length of the frame, and drops the UDP packets to the
+// `drop_port` of the `CONFIG`.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    // `data_end - data` is only the length of the linear part, `bpf_xdp_get_buff_len` returns the
+    // length of the linear part and all the fragments.
+    let len = unsafe { bpf_xdp_get_buff_len(ctx.ctx) };
+    let linear_len = (ctx.data_end() - ctx.data()) as u64;
+    let kind = if len > linear_len {
+        BUFFER_MULTI
+    } else {
+        BUFFER_LINEAR
+    };
+
+    if let Some(stats) = FRAME_STATS.get_ptr_mut(kind) {
+        unsafe {
+            (*stats).packets += 1;
+            (*stats).bytes += len;
+            (*stats).linear_bytes += linear_len;
+        }
+    }
+
+    let Some(config) = CONFIG.get(0) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if config.drop_port == 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    match udp_dest_port(ctx) {
+        Some(port) if port == config.drop_port => {
+            debug!(ctx, "Dropping a UDP packet to the port: {}", port);
+            Ok(xdp_action::XDP_DROP)
+        }
+        _ => Ok(xdp_action::XDP_PASS),
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
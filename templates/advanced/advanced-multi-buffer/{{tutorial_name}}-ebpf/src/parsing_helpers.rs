@@ -0,0 +1,132 @@
+// Helpers for parsing the packet headers of the multi-buffer frames.
+//
+// The headers are the same as in the 'packet03-redirecting' tutorial, but they are copied from
+// the frame using `bpf_xdp_load_bytes` instead of being accessed in place, since a header may not
+// be (wholly) in the linear part of a multi-buffer frame.
+
+use core::{ffi::c_void, mem};
+
+use aya_ebpf::{helpers::bpf_xdp_load_bytes, programs::XdpContext};
+
+pub const ETH_P_IP: u16 = 0x0800;
+pub const ETH_P_IPV6: u16 = 0x86DD;
+
+pub const IPPROTO_UDP: u8 = 17;
+
+/// Ethernet Header.
+#[repr(C)]
+pub struct EthHdr {
+    pub h_dest: [u8; 6],
+    pub h_source: [u8; 6],
+    /// Protocol of the next header (in network byte order).
+    pub h_proto: u16,
+}
+
+/// IPv4 Header (without the options).
+#[repr(C)]
+pub struct Ipv4Hdr {
+    /// Version (upper 4 bits) and the Header length in 32 bit words (lower 4 bits).
+    pub version_ihl: u8,
+    pub tos: u8,
+    pub tot_len: u16,
+    pub id: u16,
+    pub frag_off: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub check: u16,
+    pub saddr: u32,
+    pub daddr: u32,
+}
+
+/// IPv6 Header.
+#[repr(C)]
+pub struct Ipv6Hdr {
+    pub priority_version: u8,
+    pub flow_lbl: [u8; 3],
+    pub payload_len: u16,
+    pub nexthdr: u8,
+    pub hop_limit: u8,
+    pub saddr: [u8; 16],
+    pub daddr: [u8; 16],
+}
+
+/// UDP Header.
+#[repr(C)]
+pub struct UdpHdr {
+    pub source: u16,
+    pub dest: u16,
+    pub len: u16,
+    pub check: u16,
+}
+
+/// Keeps track of the current parsing position in the frame.
+///
+/// Every successful `parse_*` call advances the cursor to the start of the next header.
+pub struct HdrCursor {
+    /// Offset of the current parsing position from the start of the frame.
+    ///
+    /// Unlike the address of a header in the linear part, the offset is not checked by the
+    /// verifier, it is checked by `bpf_xdp_load_bytes` against the length of the whole frame.
+    pub offset: usize,
+}
+
+/// Copies a `T` at the cursor from the frame, only if the whole `T` lies within the frame. The
+/// `T` may be in the linear part, in the fragments or span both.
+#[inline(always)]
+pub fn load_at<T>(ctx: &XdpContext, cursor: &HdrCursor) -> Option<T> {
+    let mut hdr = mem::MaybeUninit::<T>::uninit();
+    let ret = unsafe {
+        bpf_xdp_load_bytes(
+            ctx.ctx,
+            cursor.offset as u32,
+            hdr.as_mut_ptr() as *mut c_void,
+            mem::size_of::<T>() as u32,
+        )
+    };
+    if ret != 0 {
+        return None;
+    }
+
+    Some(unsafe { hdr.assume_init() })
+}
+
+/// Parses the Ethernet header and returns the protocol of the next header (in host byte order).
+#[inline(always)]
+pub fn parse_ethhdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u16> {
+    let eth: EthHdr = load_at(ctx, cursor)?;
+    cursor.offset += mem::size_of::<EthHdr>();
+
+    Some(u16::from_be(eth.h_proto))
+}
+
+/// Parses the IPv4 header (including the options) and returns the protocol of the next header.
+#[inline(always)]
+pub fn parse_iphdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u8> {
+    let iph: Ipv4Hdr = load_at(ctx, cursor)?;
+
+    let hdrsize = ((iph.version_ihl & 0x0F) as usize) * 4;
+    if hdrsize < mem::size_of::<Ipv4Hdr>() {
+        return None;
+    }
+    cursor.offset += hdrsize;
+
+    Some(iph.protocol)
+}
+
+/// Parses the IPv6 header and returns the protocol of the next header.
+#[inline(always)]
+pub fn parse_ip6hdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u8> {
+    let ip6h: Ipv6Hdr = load_at(ctx, cursor)?;
+    cursor.offset += mem::size_of::<Ipv6Hdr>();
+
+    Some(ip6h.nexthdr)
+}
+
+/// Parses the UDP header and returns the destination port (in host byte order).
+#[inline(always)]
+pub fn parse_udphdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u16> {
+    let udph: UdpHdr = load_at(ctx, cursor)?;
+    cursor.offset += mem::size_of::<UdpHdr>();
+
+    Some(u16::from_be(udph.dest))
+}
@@ -0,0 +1,10 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the prefixes (rules) in the blocklist, for IPv4 and IPv6 together.
+///
+/// The value of a prefix in the `BLOCKLIST_V4` and the `BLOCKLIST_V6` maps is the id of the rule,
+/// which is the index of the hit counter of the rule in the `RULE_HITS` map.
+pub const MAX_RULES: u32 = 1024;
@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+
+use aya::maps::lpm_trie::Key;
+use aya::maps::{LpmTrie, Map, MapData, PerCpuArray, PerCpuValues};
+use aya::programs::{links::FdLink, Xdp, XdpFlags};
+use aya::util::nr_cpus;
+use aya::EbpfLoader;
+
+use clap::Parser;
+use log::{info, warn};
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::MAX_RULES;
+
+#[derive(Debug, Parser)]
+enum CliCommand {
+    /// Pin: Attaches the program, pins it along with the maps and loads the prefixes from a file
+    Pin(PinOptions),
+
+    /// Load: Replaces the prefixes in the pinned blocklist with the prefixes from a file
+    Load(LoadOptions),
+
+    /// Add: Adds a prefix to the pinned blocklist
+    Add(PrefixOptions),
+
+    /// Remove: Removes a prefix from the pinned blocklist
+    Remove(PrefixOptions),
+
+    /// List: Lists the prefixes in the pinned blocklist with the hit counters
+    List(PinnedOptions),
+}
+
+// Handling of the 'pin' command.
+#[derive(Debug, Parser)]
+struct PinOptions {
+    /// File with the prefixes to block, one prefix per line.
+    #[clap(short, long)]
+    prefixes: Option<PathBuf>,
+
+    /// Name of the 'eBPF' binary file.
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    /// Interface name to which the program is attached.
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    /// Run the binary in 'release' mode
+    #[clap(long)]
+    release: bool,
+}
+
+// Options to find the pinned maps, common to all the commands after 'pin'.
+#[derive(Debug, Parser)]
+struct PinnedOptions {
+    /// Interface name to which the program is attached.
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    /// Name of the 'tutorial' to search Pinned Maps in `/sys/fs/bpf`
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    name: String,
+}
+
+// Handling of the 'load' command.
+#[derive(Debug, Parser)]
+struct LoadOptions {
+    /// File with the prefixes to block, one prefix per line.
+    #[clap(short, long)]
+    prefixes: PathBuf,
+
+    /// Keep running and load the file again when it is modified, checking every `INTERVAL`
+    /// seconds.
+    #[clap(long)]
+    interval: Option<u64>,
+
+    #[clap(flatten)]
+    pinned: PinnedOptions,
+}
+
+// Handling of the 'add' and the 'remove' commands.
+#[derive(Debug, Parser)]
+struct PrefixOptions {
+    /// The prefix (eg. '192.0.2.0/24' or '2001:db8::/32'), an address without the length is a
+    /// prefix of the full length.
+    prefix: Prefix,
+
+    #[clap(flatten)]
+    pinned: PinnedOptions,
+}
+
+// An IPv4 or IPv6 prefix. The bits of the address after the length of the prefix are always zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Prefix {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl FromStr for Prefix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .with_context(|| format!("Invalid address in the prefix: '{s}'"))?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let len = match len {
+            Some(len) => len
+                .parse()
+                .with_context(|| format!("Invalid length of the prefix: '{s}'"))?,
+            None => max_len,
+        };
+        if len > max_len {
+            return Err(anyhow::Error::msg(format!(
+                "Length of the prefix should be at most {max_len}: '{s}'"
+            )));
+        }
+
+        // Clear the bits after the length of the prefix, the kernel ignores those bits, but they
+        // would show up in the list.
+        let addr = match addr {
+            IpAddr::V4(addr) => {
+                let mask = u32::MAX.checked_shl(32 - len as u32).unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from(u32::from(addr) & mask))
+            }
+            IpAddr::V6(addr) => {
+                let mask = u128::MAX.checked_shl(128 - len as u32).unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(addr) & mask))
+            }
+        };
+
+        Ok(Prefix { addr, len })
+    }
+}
+
+impl fmt::Display for Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.len)
+    }
+}
+
+// Reads the prefixes from a file, one prefix per line. The empty lines and the lines starting
+// with a '#' are ignored.
+fn read_prefixes(path: &Path) -> anyhow::Result<Vec<Prefix>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the prefixes from: '{}'", path.display()))?;
+
+    let mut prefixes = vec![];
+    for (lineno, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let prefix = line
+            .parse()
+            .with_context(|| format!("{}:{}", path.display(), lineno + 1))?;
+        prefixes.push(prefix);
+    }
+
+    Ok(prefixes)
+}
+
+// The pinned maps of the program.
+struct Blocklist {
+    v4: LpmTrie<MapData, [u8; 4], u32>,
+    v6: LpmTrie<MapData, [u8; 16], u32>,
+    hits: PerCpuArray<MapData, u64>,
+}
+
+impl Blocklist {
+    // Opens the maps pinned by the 'pin' command for the given interface and tutorial.
+    fn open(opts: &PinnedOptions) -> anyhow::Result<Self> {
+        let map_pin_path = format!("/sys/fs/bpf/{}/{}/maps", opts.iface, opts.name);
+        if !Path::new(&map_pin_path).join("BLOCKLIST_V4").exists() {
+            return Err(anyhow::Error::msg(
+                "Blocklist maps are not pinned. Please run 'pin' to pin the maps.",
+            ));
+        }
+
+        let open = |name: &str| MapData::from_pin(format!("{map_pin_path}/{name}"));
+        Ok(Self {
+            v4: Map::LpmTrie(open("BLOCKLIST_V4")?).try_into()?,
+            v6: Map::LpmTrie(open("BLOCKLIST_V6")?).try_into()?,
+            hits: Map::PerCpuArray(open("RULE_HITS")?).try_into()?,
+        })
+    }
+
+    // Returns all the rules in the blocklist, with the id of every rule.
+    fn rules(&self) -> anyhow::Result<BTreeMap<Prefix, u32>> {
+        let mut rules = BTreeMap::new();
+        for entry in self.v4.iter() {
+            let (key, rule) = entry?;
+            let prefix = Prefix {
+                addr: IpAddr::V4(Ipv4Addr::from(key.data())),
+                len: key.prefix_len() as u8,
+            };
+            rules.insert(prefix, rule);
+        }
+        for entry in self.v6.iter() {
+            let (key, rule) = entry?;
+            let prefix = Prefix {
+                addr: IpAddr::V6(Ipv6Addr::from(key.data())),
+                len: key.prefix_len() as u8,
+            };
+            rules.insert(prefix, rule);
+        }
+
+        Ok(rules)
+    }
+
+    // Adds the prefix with the lowest unused rule id, the hit counter of the rule is reset.
+    fn add(&mut self, prefix: Prefix) -> anyhow::Result<()> {
+        let rules = self.rules()?;
+        if rules.contains_key(&prefix) {
+            info!("Prefix '{}' is already in the blocklist", prefix);
+            return Ok(());
+        }
+        let rule = (0..MAX_RULES)
+            .find(|id| !rules.values().any(|rule| rule == id))
+            .ok_or_else(|| anyhow::Error::msg("Blocklist is full"))?;
+
+        let zeros = PerCpuValues::try_from(vec![0u64; nr_cpus().map_err(|(_, e)| e)?])?;
+        self.hits.set(rule, zeros, 0)?;
+        let len = prefix.len as u32;
+        match prefix.addr {
+            IpAddr::V4(addr) => self.v4.insert(&Key::new(len, addr.octets()), rule, 0)?,
+            IpAddr::V6(addr) => self.v6.insert(&Key::new(len, addr.octets()), rule, 0)?,
+        }
+        info!("Prefix '{}' added as the rule: {}", prefix, rule);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, prefix: Prefix) -> anyhow::Result<()> {
+        let len = prefix.len as u32;
+        match prefix.addr {
+            IpAddr::V4(addr) => self.v4.remove(&Key::new(len, addr.octets())),
+            IpAddr::V6(addr) => self.v6.remove(&Key::new(len, addr.octets())),
+        }
+        .with_context(|| format!("Failed to remove the prefix: '{prefix}'"))?;
+        info!("Prefix '{}' removed", prefix);
+
+        Ok(())
+    }
+
+    // Makes the blocklist the same as the given prefixes. The rules of the prefixes already in the
+    // blocklist are kept, so that their hit counters are not reset.
+    //
+    // The number of the prefixes is checked before the blocklist is changed, so that the blocklist
+    // is left as it was if the prefixes do not fit.
+    fn load(&mut self, prefixes: &[Prefix]) -> anyhow::Result<()> {
+        let count = prefixes.iter().collect::<BTreeSet<_>>().len();
+        if count > MAX_RULES as usize {
+            return Err(anyhow::Error::msg(format!(
+                "Too many prefixes: {count} (at most {MAX_RULES})"
+            )));
+        }
+
+        for prefix in self.rules()?.into_keys() {
+            if !prefixes.contains(&prefix) {
+                self.remove(prefix)?;
+            }
+        }
+        for prefix in prefixes {
+            self.add(*prefix)?;
+        }
+
+        Ok(())
+    }
+
+    fn print(&self) -> anyhow::Result<()> {
+        info!("{:<45} {:>6} {:>12}", "prefix", "rule", "hits");
+        for (prefix, rule) in self.rules()? {
+            let hits: u64 = self.hits.get(&rule, 0)?.iter().sum();
+            info!("{:<45} {:>6} {:>12}", prefix.to_string(), rule, hits);
+        }
+
+        Ok(())
+    }
+}
+
+fn pin_program_and_maps(opts: PinOptions) -> anyhow::Result<()> {
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_file = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+
+    // The maps are pinned (and reused if already pinned) in this directory.
+    let map_pin_path = format!("/sys/fs/bpf/{}/{}/maps", opts.iface, opts.file);
+    std::fs::create_dir_all(&map_pin_path)?;
+
+    info!("Loading eBPF file: {bpf_file}");
+    let mut bpf = EbpfLoader::new()
+        .map_pin_path(&map_pin_path)
+        .load_file(bpf_file)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let link_id = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    // The link is pinned, so that the program stays attached after the runner exits.
+    let fd_link: FdLink = xdp.take_link(link_id)?.try_into()?;
+    let program_pin_path = format!("/sys/fs/bpf/{}/{}/programs", opts.iface, opts.file);
+    std::fs::create_dir_all(&program_pin_path)?;
+    let program_pin_path = format!("{}/{}", program_pin_path, program_name);
+    fd_link.pin(&program_pin_path)?;
+
+    info!(
+        "XDP Program: '{}' attached to interface: '{}' and pinned at path: '{}'",
+        program_name, &opts.iface, program_pin_path,
+    );
+
+    if let Some(prefixes) = opts.prefixes {
+        let pinned = PinnedOptions {
+            iface: opts.iface,
+            name: opts.file,
+        };
+        Blocklist::open(&pinned)?.load(&read_prefixes(&prefixes)?)?;
+    }
+
+    Ok(())
+}
+
+fn modified(path: &Path) -> anyhow::Result<SystemTime> {
+    Ok(std::fs::metadata(path)?.modified()?)
+}
+
+// Loads the prefixes from the file, and if an interval is given, keeps loading the file again
+// every time it is modified until Ctrl-C.
+async fn load(opts: LoadOptions) -> anyhow::Result<()> {
+    let mut blocklist = Blocklist::open(&opts.pinned)?;
+    blocklist.load(&read_prefixes(&opts.prefixes)?)?;
+
+    let Some(interval) = opts.interval else {
+        return Ok(());
+    };
+    if interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    info!(
+        "Watching '{}' for changes, waiting for Ctrl-C",
+        opts.prefixes.display()
+    );
+    let mut last_modified = modified(&opts.prefixes)?;
+    let mut poller_interval = time::interval(Duration::from_secs(interval));
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                let Ok(modified) = modified(&opts.prefixes) else {
+                    continue;
+                };
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                // A bad file, or prefixes that cannot be loaded, do not stop the watching, the
+                // blocklist is left as it was until the file is modified again.
+                let loaded = read_prefixes(&opts.prefixes)
+                    .and_then(|prefixes| blocklist.load(&prefixes));
+                if let Err(e) = loaded {
+                    warn!("Failed to load the prefixes: {:#}", e);
+                }
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The program and the maps are pinned by the 'pin' command, the other commands work on the
+// pinned maps, so the blocklist can be changed while the program stays attached.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    env_logger::init();
+
+    let cli = CliCommand::parse();
+
+    match cli {
+        CliCommand::Pin(opts) => pin_program_and_maps(opts),
+        CliCommand::Load(opts) => load(opts).await,
+        CliCommand::Add(opts) => Blocklist::open(&opts.pinned)?.add(opts.prefix),
+        CliCommand::Remove(opts) => Blocklist::open(&opts.pinned)?.remove(opts.prefix),
+        CliCommand::List(opts) => Blocklist::open(&opts)?.print(),
+    }
+}
@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::{xdp_action, BPF_F_NO_PREALLOC},
+    macros::{map, xdp},
+    maps::{lpm_trie::Key, LpmTrie, PerCpuArray},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr_vlan, parse_ip6hdr, parse_iphdr, HdrCursor, ETH_P_IP, ETH_P_IPV6,
+};
+
+use {{ to_snake_case tutorial_name }}_common::MAX_RULES;
+
+// The blocked prefixes, the value is the id of the rule. The maps are pinned, so that the
+// Userspace program can update the prefixes while the program is attached.
+//
+// The data of the key is the address in the network byte order, as in the packet. The LPM tries
+// must be created with the `BPF_F_NO_PREALLOC` flag.
+#[map]
+static BLOCKLIST_V4: LpmTrie<[u8; 4], u32> =
+    LpmTrie::<[u8; 4], u32>::pinned(MAX_RULES, BPF_F_NO_PREALLOC);
+
+#[map]
+static BLOCKLIST_V6: LpmTrie<[u8; 16], u32> =
+    LpmTrie::<[u8; 16], u32>::pinned(MAX_RULES, BPF_F_NO_PREALLOC);
+
+// Packets dropped by every rule, indexed by the id of the rule.
+#[map]
+static RULE_HITS: PerCpuArray<u64> = PerCpuArray::<u64>::pinned(MAX_RULES, 0);
+
+// Returns the id of the rule for the longest prefix matching the source address of the packet.
+#[inline(always)]
+fn match_rule(ctx: &XdpContext) -> Option<u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let (_, eth_type) = parse_ethhdr_vlan(ctx, &mut cursor)?;
+    let rule = match eth_type {
+        ETH_P_IP => {
+            let iph = parse_iphdr(ctx, &mut cursor)?;
+            let saddr = unsafe { (*iph).saddr }.to_ne_bytes();
+            BLOCKLIST_V4.get(&Key::new(32, saddr))?
+        }
+        ETH_P_IPV6 => {
+            let ip6h = parse_ip6hdr(ctx, &mut cursor)?;
+            let saddr = unsafe { (*ip6h).saddr };
+            BLOCKLIST_V6.get(&Key::new(128, saddr))?
+        }
+        _ => return None,
+    };
+
+    Some(*rule)
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Drops the packets with the source address matching any of the prefixes in the blocklist. The
+// lookup with the full address (prefix length 32 or 128) returns the longest matching prefix.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let Some(rule) = match_rule(ctx) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    if let Some(hits) = RULE_HITS.get_ptr_mut(rule) {
+        unsafe { *hits += 1 };
+    }
+    debug!(ctx, "Packet dropped by the rule: {}", rule);
+
+    Ok(xdp_action::XDP_DROP)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,56 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the source addresses tracked by the rate limiter. When the map is full, the
+/// least recently used entries are evicted.
+pub const MAX_SOURCES: u32 = 16384;
+
+/// Configuration of the rate limiter, set by the Userspace program in the `RATE_LIMIT_CONFIG` map
+/// and read by the eBPF program for every packet.
+#[repr(C)]
+#[cfg_attr(feature = "user", derive(Copy, Debug, Clone))]
+pub struct RateLimitConfig {
+    /// Number of packets per second allowed from a source address, `0` disables the rate limiting.
+    pub rate: u64,
+
+    /// Maximum number of packets that can be sent in a burst (the size of the bucket).
+    pub burst: u64,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for RateLimitConfig {}
+
+/// Source address of a packet, the key of the `TOKEN_BUCKETS` map.
+///
+/// The IPv4 addresses are stored as the IPv4-mapped IPv6 addresses (`::ffff:a.b.c.d`), so that a
+/// single map is used for both.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct SourceAddr {
+    pub addr: [u8; 16],
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for SourceAddr {}
+
+/// The token bucket of a source address, and the packets passed and dropped for the address.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct TokenBucket {
+    /// Number of packets that can be passed right now.
+    pub tokens: u64,
+
+    /// Time the tokens were last refilled, from `bpf_ktime_get_ns`.
+    pub last_refill_ns: u64,
+
+    pub passed: u64,
+
+    pub dropped: u64,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for TokenBucket {}
@@ -0,0 +1,178 @@
+use std::net::{IpAddr, Ipv6Addr};
+
+use anyhow::Context;
+
+use aya::maps::{Array, HashMap, MapData};
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{info, warn};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::signal;
+
+use {{ to_snake_case tutorial_name }}_common::{RateLimitConfig, SourceAddr, TokenBucket};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Number of packets per second allowed from a source address (`0` disables the limiting).
+    #[clap(long, default_value_t = 10)]
+    rate: u64,
+
+    /// Maximum number of packets allowed from a source address in a burst.
+    #[clap(long, default_value_t = 20)]
+    burst: u64,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Number of the top talkers displayed by default.
+const DEFAULT_TOP: usize = 10;
+
+const HELP: &str = "Commands:
+    set <rate> <burst>      Set the rate (packets per second, 0 disables the limiting) and the burst
+    top [<count>]           Display the source addresses sending the most packets
+    help                    Display this help";
+
+fn set_config(
+    config: &mut Array<MapData, RateLimitConfig>,
+    rate: u64,
+    burst: u64,
+) -> anyhow::Result<()> {
+    if rate > 1_000_000_000 {
+        return Err(anyhow::Error::msg(
+            "Rate should be at most 1000000000 packets per second.",
+        ));
+    }
+    if burst == 0 {
+        return Err(anyhow::Error::msg("Burst should be at least 1 packet."));
+    }
+    config.set(0, RateLimitConfig { rate, burst }, 0)?;
+    info!("Rate set to {} packets per second, burst: {}", rate, burst);
+
+    Ok(())
+}
+
+// The IPv4 addresses are stored as the IPv4-mapped IPv6 addresses by the eBPF program.
+fn source_ip(source: &SourceAddr) -> IpAddr {
+    let addr = Ipv6Addr::from(source.addr);
+    match addr.to_ipv4_mapped() {
+        Some(addr) => IpAddr::V4(addr),
+        None => IpAddr::V6(addr),
+    }
+}
+
+// Displays the `count` source addresses with the most packets (passed and dropped).
+fn print_top_talkers(
+    buckets: &HashMap<MapData, SourceAddr, TokenBucket>,
+    count: usize,
+) -> anyhow::Result<()> {
+    let mut talkers = buckets.iter().collect::<Result<Vec<_>, _>>()?;
+    talkers.sort_by_key(|(_, bucket)| std::cmp::Reverse(bucket.passed + bucket.dropped));
+
+    info!("{:<40} {:>10} {:>10}", "source", "passed", "dropped");
+    for (source, bucket) in talkers.iter().take(count) {
+        info!(
+            "{:<40} {:>10} {:>10}",
+            source_ip(source),
+            bucket.passed,
+            bucket.dropped
+        );
+    }
+
+    Ok(())
+}
+
+// Runs a command read from the `stdin`. The configuration is read by the eBPF program for every
+// packet, so a new rate applies to the next packet.
+fn run_command(
+    line: &str,
+    config: &mut Array<MapData, RateLimitConfig>,
+    buckets: &HashMap<MapData, SourceAddr, TokenBucket>,
+) -> anyhow::Result<()> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["set", rate, burst] => {
+            let rate = rate.parse().context("Invalid rate")?;
+            let burst = burst.parse().context("Invalid burst")?;
+            set_config(config, rate, burst)?;
+        }
+        ["top"] => print_top_talkers(buckets, DEFAULT_TOP)?,
+        ["top", count] => print_top_talkers(buckets, count.parse().context("Invalid count")?)?,
+        [] => {}
+        _ => println!("{HELP}"),
+    }
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The rate and the burst are set in the `RATE_LIMIT_CONFIG` map before attaching the program, and
+// can be changed using the commands read from the `stdin` while the program is attached.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut config = Array::try_from(bpf.take_map("RATE_LIMIT_CONFIG").unwrap())?;
+    let buckets = HashMap::try_from(bpf.take_map("TOKEN_BUCKETS").unwrap())?;
+    set_config(&mut config, opts.rate, opts.burst)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'! Now waiting for commands or Ctrl-C",
+        program_name, &opts.iface
+    );
+    println!("{HELP}");
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    // `stdin` is closed, keep running until Ctrl-C.
+                    signal::ctrl_c().await?;
+                    break;
+                };
+                if let Err(e) = run_command(&line, &mut config, &buckets) {
+                    warn!("{:#}", e);
+                }
+            }
+            _ = signal::ctrl_c() => {
+                break;
+            }
+        }
+    }
+    info!("Exiting...");
+
+    Ok(())
+}
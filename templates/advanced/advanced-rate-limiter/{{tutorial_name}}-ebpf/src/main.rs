@@ -0,0 +1,129 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::bpf_ktime_get_ns,
+    macros::{map, xdp},
+    maps::{Array, LruHashMap},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr_vlan, parse_ip6hdr, parse_iphdr, HdrCursor, ETH_P_IP, ETH_P_IPV6,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    RateLimitConfig, SourceAddr, TokenBucket, MAX_SOURCES,
+};
+
+const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+// Configuration of the rate limiter, set (and updated) by the Userspace program.
+#[map]
+static RATE_LIMIT_CONFIG: Array<RateLimitConfig> = Array::<RateLimitConfig>::with_max_entries(1, 0);
+
+// The token buckets, keyed by the source address. An LRU map evicts the buckets of the sources
+// not seen recently when the map is full, instead of failing to add a new source.
+#[map]
+static TOKEN_BUCKETS: LruHashMap<SourceAddr, TokenBucket> =
+    LruHashMap::<SourceAddr, TokenBucket>::with_max_entries(MAX_SOURCES, 0);
+
+// Returns the source address of the packet, the IPv4 addresses are mapped to the IPv6 addresses.
+#[inline(always)]
+fn source_addr(ctx: &XdpContext) -> Option<SourceAddr> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let (_, eth_type) = parse_ethhdr_vlan(ctx, &mut cursor)?;
+    match eth_type {
+        ETH_P_IP => {
+            let iph = parse_iphdr(ctx, &mut cursor)?;
+            let saddr = unsafe { (*iph).saddr }.to_ne_bytes();
+            let mut addr = [0u8; 16];
+            addr[10] = 0xff;
+            addr[11] = 0xff;
+            addr[12..].copy_from_slice(&saddr);
+            Some(SourceAddr { addr })
+        }
+        ETH_P_IPV6 => {
+            let ip6h = parse_ip6hdr(ctx, &mut cursor)?;
+            Some(SourceAddr {
+                addr: unsafe { (*ip6h).saddr },
+            })
+        }
+        _ => None,
+    }
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Takes a token from the bucket of the source address of the packet. The packet is passed if a
+// token is available and dropped otherwise.
+//
+// The bucket is refilled with one token every `NSEC_PER_SEC / rate` nanoseconds, up to `burst`
+// tokens. The bucket of a new source address starts full.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let Some(config) = RATE_LIMIT_CONFIG.get(0) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    if config.rate == 0 || config.rate > NSEC_PER_SEC || config.burst == 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    let Some(source) = source_addr(ctx) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let now = unsafe { bpf_ktime_get_ns() };
+    let Some(bucket) = TOKEN_BUCKETS.get_ptr_mut(&source) else {
+        let bucket = TokenBucket {
+            tokens: config.burst - 1,
+            last_refill_ns: now,
+            passed: 1,
+            dropped: 0,
+        };
+        // Fails only if another CPU added the source in the meantime, the packet is passed anyway.
+        let _ = TOKEN_BUCKETS.insert(&source, &bucket, 0);
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    // The bucket is shared by all the CPUs, the updates below are not atomic, so the counts are
+    // approximate when packets from the same source are received on more than one CPU.
+    let bucket = unsafe { &mut *bucket };
+    let interval = NSEC_PER_SEC / config.rate;
+    let refill = now.saturating_sub(bucket.last_refill_ns) / interval;
+    if refill > 0 {
+        if bucket.tokens + refill >= config.burst {
+            bucket.tokens = config.burst;
+            bucket.last_refill_ns = now;
+        } else {
+            bucket.tokens += refill;
+            // The remainder of the elapsed time is kept for the next refill.
+            bucket.last_refill_ns += refill * interval;
+        }
+    }
+
+    if bucket.tokens == 0 {
+        bucket.dropped += 1;
+        debug!(ctx, "Packet dropped, dropped: {}", bucket.dropped);
+        return Ok(xdp_action::XDP_DROP);
+    }
+    bucket.tokens -= 1;
+    bucket.passed += 1;
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
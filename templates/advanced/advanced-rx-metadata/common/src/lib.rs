@@ -0,0 +1,76 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// The RX hash (`bpf_xdp_metadata_rx_hash`).
+pub const HINT_HASH: u32 = 0;
+
+/// The RX timestamp (`bpf_xdp_metadata_rx_timestamp`).
+pub const HINT_TIMESTAMP: u32 = 1;
+
+/// The VLAN tag (`bpf_xdp_metadata_rx_vlan_tag`).
+pub const HINT_VLAN: u32 = 2;
+
+/// Number of the metadata hints read by the program.
+pub const NUM_HINTS: u32 = 3;
+
+/// The kfunc returned the hint.
+pub const RESULT_OK: u32 = 0;
+
+/// The kfunc returned `-ENODATA`, the device does not have the hint for the packet (eg. the
+/// packet has no VLAN tag or the timestamping is not enabled).
+pub const RESULT_NO_DATA: u32 = 1;
+
+/// The kfunc returned `-EOPNOTSUPP`, the driver does not implement the kfunc.
+pub const RESULT_UNSUPPORTED: u32 = 2;
+
+/// The kfunc returned any other error.
+pub const RESULT_ERROR: u32 = 3;
+
+/// Number of the results of a kfunc.
+///
+/// The `HINT_STATS` map has a counter for every result of every hint, at the index
+/// `hint * NUM_RESULTS + result`.
+pub const NUM_RESULTS: u32 = 4;
+
+/// Number of the buckets of the RX hash distribution (the `HASH_BUCKETS` map). A packet is
+/// counted in the bucket `rx_hash % NUM_HASH_BUCKETS`, like the indirection table of the RSS
+/// selects the receive queue.
+pub const NUM_HASH_BUCKETS: u32 = 16;
+
+/// Number of the recent `RxSample`s kept for every CPU in the `RX_SAMPLES` map.
+pub const MAX_SAMPLES: u32 = 32;
+
+/// The metadata hints of a received packet, stored in the `RX_SAMPLES` map.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct RxSample {
+    /// Sequence number of the sample on the CPU, starting with 1 (0 for an unused entry).
+    pub seq: u64,
+
+    /// The RX timestamp (in nanoseconds) from the device, if `HINT_TIMESTAMP` is set in `hints`.
+    pub rx_timestamp: u64,
+
+    /// The time (`CLOCK_MONOTONIC`, in nanoseconds) at which the program was run.
+    pub xdp_timestamp: u64,
+
+    /// The RX hash, if `HINT_HASH` is set in `hints`.
+    pub rx_hash: u32,
+
+    /// The type of the RX hash (`enum xdp_rss_hash_type`), the headers used for the hash.
+    pub rss_type: u32,
+
+    /// The VLAN protocol (in host byte order), if `HINT_VLAN` is set in `hints`.
+    pub vlan_proto: u16,
+
+    /// The VLAN TCI (ID and priority), if `HINT_VLAN` is set in `hints`.
+    pub vlan_tci: u16,
+
+    /// The hints returned by the device, bit `1 << HINT_*` is set for every hint.
+    pub hints: u32,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for RxSample {}
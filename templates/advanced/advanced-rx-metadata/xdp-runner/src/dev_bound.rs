@@ -0,0 +1,612 @@
+// Loading a program as a device-bound XDP program using `BPF_PROG_LOAD`, and attaching it to the
+// interface using `BPF_LINK_CREATE`.
+//
+// `Xdp::load` has no option for the index of the interface (`prog_ifindex`) and the
+// `BPF_F_XDP_DEV_BOUND_ONLY` flag, so the instructions of the program are read from the object
+// file here. The maps are still created by aya (`Ebpf::load`): the instructions loading the
+// address of a map are given the fd of the map, and the calls to the kfuncs are given the BTF id
+// of the kfunc in the kernel. These are the only relocations supported, so the program can't call
+// the functions that are not inlined or use the global data (eg. the strings of `aya-log`).
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+
+use anyhow::Context;
+
+use aya::obj::btf::BtfKind;
+use aya::Btf;
+
+const BPF_PROG_LOAD: libc::c_long = 5;
+const BPF_LINK_CREATE: libc::c_long = 28;
+
+const BPF_PROG_TYPE_XDP: u32 = 6;
+const BPF_XDP: u32 = 37;
+const BPF_F_XDP_DEV_BOUND_ONLY: u32 = 1 << 6;
+
+// The `src_reg` of the instructions loading the fd of a map, and of the calls to the kfuncs.
+const BPF_PSEUDO_MAP_FD: u8 = 1;
+const BPF_PSEUDO_KFUNC_CALL: u8 = 2;
+
+// The opcodes of the first half of a 64 bit load (`BPF_LD | BPF_IMM | BPF_DW`) and of a call
+// (`BPF_JMP | BPF_CALL`).
+const BPF_LD_IMM64: u8 = 0x18;
+const BPF_CALL: u8 = 0x85;
+
+const BPF_INSN_SIZE: usize = 8;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_REL: u32 = 9;
+const SHT_NOBITS: u32 = 8;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+const SHN_UNDEF: u16 = 0;
+
+// The section of the maps declared with the `#[map]` macro.
+const MAPS_SECTION: &str = "maps";
+
+// Size of the buffer of the verifier log, which is only requested when the load fails.
+const LOG_SIZE: usize = 64 * 1024;
+
+// The `struct bpf_insn` (see `include/uapi/linux/bpf.h`).
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct BpfInsn {
+    code: u8,
+    // `dst_reg` in the low 4 bits, `src_reg` in the high 4 bits.
+    regs: u8,
+    off: i16,
+    imm: i32,
+}
+
+impl BpfInsn {
+    fn from_bytes(b: &[u8]) -> Self {
+        BpfInsn {
+            code: b[0],
+            regs: b[1],
+            off: i16::from_le_bytes([b[2], b[3]]),
+            imm: i32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+        }
+    }
+
+    fn set_src_reg(&mut self, src_reg: u8) {
+        self.regs = (self.regs & 0x0F) | (src_reg << 4);
+    }
+}
+
+// The `prog_load` member of the `union bpf_attr`, up to the `expected_attach_type`.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct BpfProgLoadAttr {
+    prog_type: u32,
+    insn_cnt: u32,
+    insns: u64,
+    license: u64,
+    log_level: u32,
+    log_size: u32,
+    log_buf: u64,
+    kern_version: u32,
+    prog_flags: u32,
+    prog_name: [u8; 16],
+    prog_ifindex: u32,
+    expected_attach_type: u32,
+}
+
+// The `link_create` member of the `union bpf_attr`, up to the `flags`.
+#[repr(C)]
+#[derive(Debug, Default)]
+struct BpfLinkCreateAttr {
+    prog_fd: u32,
+    target_ifindex: u32,
+    attach_type: u32,
+    flags: u32,
+}
+
+// What a relocation of the program refers to.
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Map(RawFd),
+    Kfunc(u32),
+}
+
+struct Section<'a> {
+    name: String,
+    kind: u32,
+    data: &'a [u8],
+    info: u32,
+}
+
+struct Symbol {
+    name: String,
+    kind: u8,
+    section: u16,
+    value: u64,
+    size: u64,
+}
+
+// The symbols referred by the relocations of a program, with the index of the relocated
+// instruction.
+type Relocations<'a> = Vec<(usize, &'a Symbol)>;
+
+// The sections and the symbols of a (64 bit, little endian) ELF object file, which is all that is
+// needed to find the instructions of a program and their relocations.
+struct Elf<'a> {
+    sections: Vec<Section<'a>>,
+    symbols: Vec<Symbol>,
+}
+
+fn read_bytes<const N: usize>(b: &[u8], at: usize) -> anyhow::Result<[u8; N]> {
+    b.get(at..at + N)
+        .map(|b| b.try_into().unwrap())
+        .context("Truncated object file")
+}
+
+fn read_u16(b: &[u8], at: usize) -> anyhow::Result<u16> {
+    Ok(u16::from_le_bytes(read_bytes(b, at)?))
+}
+
+fn read_u32(b: &[u8], at: usize) -> anyhow::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(b, at)?))
+}
+
+fn read_u64(b: &[u8], at: usize) -> anyhow::Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(b, at)?))
+}
+
+// Reads the null terminated string at `at` in a string table.
+fn read_str(strtab: &[u8], at: u32) -> String {
+    let s = strtab.get(at as usize..).unwrap_or_default();
+    let len = s.iter().position(|c| *c == 0).unwrap_or(s.len());
+    String::from_utf8_lossy(&s[..len]).into_owned()
+}
+
+impl<'a> Elf<'a> {
+    fn parse(data: &'a [u8]) -> anyhow::Result<Self> {
+        if data.get(..6) != Some(b"\x7fELF\x02\x01") {
+            return Err(anyhow::Error::msg(
+                "Not a 64 bit little endian ELF object file",
+            ));
+        }
+
+        let shoff = read_u64(data, 0x28)? as usize;
+        let shentsize = read_u16(data, 0x3A)? as usize;
+        let shnum = read_u16(data, 0x3C)? as usize;
+        let shstrndx = read_u16(data, 0x3E)? as usize;
+
+        // name, type, data, link and info of every section header.
+        let mut headers = vec![];
+        for index in 0..shnum {
+            let at = shoff + index * shentsize;
+            let kind = read_u32(data, at + 0x04)?;
+            let offset = read_u64(data, at + 0x18)? as usize;
+            let size = read_u64(data, at + 0x20)? as usize;
+            let section = if kind == SHT_NOBITS {
+                &[][..]
+            } else {
+                data.get(offset..offset + size)
+                    .context("Truncated object file")?
+            };
+            headers.push((
+                read_u32(data, at)?,
+                kind,
+                section,
+                read_u32(data, at + 0x28)?,
+                read_u32(data, at + 0x2C)?,
+            ));
+        }
+
+        let shstrtab = headers
+            .get(shstrndx)
+            .map(|h| h.2)
+            .context("No section names")?;
+        let mut symbols = vec![];
+        let mut sections = vec![];
+        for &(name, kind, section, link, info) in &headers {
+            if kind == SHT_SYMTAB {
+                // The `link` of the symbol table is the string table of the symbol names.
+                let strtab = headers.get(link as usize).map(|h| h.2).unwrap_or_default();
+                for sym in section.chunks_exact(24) {
+                    symbols.push(Symbol {
+                        name: read_str(strtab, read_u32(sym, 0)?),
+                        kind: sym[4] & 0x0F,
+                        section: read_u16(sym, 6)?,
+                        value: read_u64(sym, 8)?,
+                        size: read_u64(sym, 16)?,
+                    });
+                }
+            }
+            sections.push(Section {
+                name: read_str(shstrtab, name),
+                kind,
+                data: section,
+                info,
+            });
+        }
+
+        Ok(Elf { sections, symbols })
+    }
+
+    fn section_name(&self, index: u16) -> &str {
+        self.sections
+            .get(index as usize)
+            .map(|s| s.name.as_str())
+            .unwrap_or_default()
+    }
+
+    // Returns the instructions and the relocations of the program.
+    fn program(&self, name: &str) -> anyhow::Result<(Vec<BpfInsn>, Relocations<'_>)> {
+        let program = self
+            .symbols
+            .iter()
+            .find(|s| s.kind == STT_FUNC && s.name == name)
+            .with_context(|| format!("Unable to find the program '{name}'"))?;
+        let section = self
+            .sections
+            .get(program.section as usize)
+            .context("Invalid section of the program")?;
+
+        let (start, end) = (
+            program.value as usize,
+            (program.value + program.size) as usize,
+        );
+        let insns = section
+            .data
+            .get(start..end)
+            .context("Truncated program")?
+            .chunks_exact(BPF_INSN_SIZE)
+            .map(BpfInsn::from_bytes)
+            .collect();
+
+        let mut relocations = vec![];
+        for rel in &self.sections {
+            if rel.kind != SHT_REL || rel.info != program.section as u32 {
+                continue;
+            }
+            for entry in rel.data.chunks_exact(16) {
+                let offset = read_u64(entry, 0)? as usize;
+                if offset < start || offset >= end {
+                    continue;
+                }
+                let symbol = self
+                    .symbols
+                    .get((read_u64(entry, 8)? >> 32) as usize)
+                    .context("Invalid symbol of a relocation")?;
+                relocations.push(((offset - start) / BPF_INSN_SIZE, symbol));
+            }
+        }
+
+        Ok((insns, relocations))
+    }
+
+    // Returns the name of the map at `offset` in the section of the maps.
+    fn map_at(&self, section: u16, offset: u64) -> Option<&str> {
+        self.symbols
+            .iter()
+            .find(|s| s.section == section && s.value == offset && s.kind != STT_SECTION)
+            .map(|s| s.name.as_str())
+    }
+}
+
+// Updates the instruction for the target of its relocation.
+fn relocate(insn: &mut BpfInsn, target: Target) -> anyhow::Result<()> {
+    match target {
+        Target::Map(fd) if insn.code == BPF_LD_IMM64 => {
+            insn.set_src_reg(BPF_PSEUDO_MAP_FD);
+            insn.imm = fd;
+        }
+        Target::Kfunc(btf_id) if insn.code == BPF_CALL => {
+            insn.set_src_reg(BPF_PSEUDO_KFUNC_CALL);
+            insn.imm = btf_id as i32;
+            // The kfunc is in the BTF of the kernel, not of a module.
+            insn.off = 0;
+        }
+        target => {
+            return Err(anyhow::Error::msg(format!(
+                "Unexpected instruction {:#04x} for the relocation to {:?}",
+                insn.code, target
+            )))
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the programs of an object file as device-bound programs of an interface.
+pub(crate) struct DevBoundLoader<'a> {
+    elf: Elf<'a>,
+    license: CString,
+    maps: HashMap<&'a str, BorrowedFd<'a>>,
+    btf: Btf,
+    ifindex: u32,
+}
+
+impl<'a> DevBoundLoader<'a> {
+    /// `maps` are the maps created by aya for the object file, by name.
+    pub(crate) fn new(
+        object: &'a [u8],
+        maps: impl IntoIterator<Item = (&'a str, BorrowedFd<'a>)>,
+        ifindex: u32,
+    ) -> anyhow::Result<Self> {
+        let elf = Elf::parse(object).context("Failed to parse the object file")?;
+        // Same as aya, the programs are GPL licensed if the object file has no license.
+        let license = elf
+            .sections
+            .iter()
+            .find(|s| s.name == "license")
+            .map(|s| read_str(s.data, 0))
+            .unwrap_or_else(|| "GPL".to_string());
+        let btf = Btf::from_sys_fs().context("Failed to read the BTF of the kernel")?;
+
+        Ok(DevBoundLoader {
+            elf,
+            license: CString::new(license)?,
+            maps: maps.into_iter().collect(),
+            btf,
+            ifindex,
+        })
+    }
+
+    fn target(&self, symbol: &Symbol, insn: &BpfInsn) -> anyhow::Result<Target> {
+        if symbol.section == SHN_UNDEF {
+            let btf_id = self
+                .btf
+                .id_by_type_name_kind(&symbol.name, BtfKind::Func)
+                .with_context(|| format!("The kernel does not have the kfunc '{}'", symbol.name))?;
+            return Ok(Target::Kfunc(btf_id));
+        }
+
+        if self.elf.section_name(symbol.section) == MAPS_SECTION {
+            // A relocation to the section itself has the offset of the map in the instruction.
+            let name = if symbol.kind == STT_SECTION {
+                self.elf
+                    .map_at(symbol.section, insn.imm as u64)
+                    .context("Unable to find the map of a relocation")?
+            } else {
+                symbol.name.as_str()
+            };
+            let fd = self
+                .maps
+                .get(name)
+                .with_context(|| format!("Unable to find the map '{name}'"))?;
+            return Ok(Target::Map(fd.as_raw_fd()));
+        }
+
+        Err(anyhow::Error::msg(format!(
+            "Unsupported relocation to '{}' in the section '{}'",
+            symbol.name,
+            self.elf.section_name(symbol.section)
+        )))
+    }
+
+    /// Loads the program as a device-bound program of the interface.
+    pub(crate) fn load(&self, name: &str) -> anyhow::Result<OwnedFd> {
+        let (mut insns, relocations) = self.elf.program(name)?;
+        for (index, symbol) in relocations {
+            let insn = insns
+                .get_mut(index)
+                .context("Invalid instruction of a relocation")?;
+            let target = self.target(symbol, insn)?;
+            relocate(insn, target)?;
+        }
+
+        match self.prog_load(name, &insns, None) {
+            Ok(fd) => Ok(fd),
+            Err(e) => {
+                // Load the program again with the verifier log, to tell why it was refused.
+                let mut log = vec![0u8; LOG_SIZE];
+                let _ = self.prog_load(name, &insns, Some(&mut log));
+                let log = read_str(&log, 0);
+                let reason = log.lines().rev().find(|l| !l.trim().is_empty());
+                Err(anyhow::Error::from(e).context(match reason {
+                    Some(reason) => format!("The kernel refused the program: {reason}"),
+                    None => "The kernel refused the program".to_string(),
+                }))
+            }
+        }
+    }
+
+    fn prog_load(
+        &self,
+        name: &str,
+        insns: &[BpfInsn],
+        log: Option<&mut [u8]>,
+    ) -> io::Result<OwnedFd> {
+        let mut attr = BpfProgLoadAttr {
+            prog_type: BPF_PROG_TYPE_XDP,
+            insn_cnt: insns.len() as u32,
+            insns: insns.as_ptr() as u64,
+            license: self.license.as_ptr() as u64,
+            prog_flags: BPF_F_XDP_DEV_BOUND_ONLY,
+            prog_ifindex: self.ifindex,
+            expected_attach_type: BPF_XDP,
+            ..Default::default()
+        };
+        // The name of the program is truncated to 15 characters by the kernel as well.
+        for (dst, src) in attr.prog_name.iter_mut().zip(name.bytes().take(15)) {
+            *dst = src;
+        }
+        if let Some(log) = log {
+            attr.log_level = 1;
+            attr.log_size = log.len() as u32;
+            attr.log_buf = log.as_mut_ptr() as u64;
+        }
+
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_bpf,
+                BPF_PROG_LOAD,
+                &mut attr as *mut BpfProgLoadAttr,
+                std::mem::size_of::<BpfProgLoadAttr>(),
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(unsafe { OwnedFd::from_raw_fd(ret as RawFd) })
+    }
+}
+
+/// Attaches the XDP program to the interface, the program stays attached until the returned link
+/// is closed. A device-bound program can only be attached in the native mode, to its interface.
+pub(crate) fn attach(prog_fd: BorrowedFd<'_>, ifindex: u32) -> io::Result<OwnedFd> {
+    let mut attr = BpfLinkCreateAttr {
+        prog_fd: prog_fd.as_raw_fd() as u32,
+        target_ifindex: ifindex,
+        attach_type: BPF_XDP,
+        ..Default::default()
+    };
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            BPF_LINK_CREATE,
+            &mut attr as *mut BpfLinkCreateAttr,
+            std::mem::size_of::<BpfLinkCreateAttr>(),
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(unsafe { OwnedFd::from_raw_fd(ret as RawFd) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXIT: [u8; 8] = [0x95, 0, 0, 0, 0, 0, 0, 0];
+
+    // Builds an object file with the section `xdp` holding the programs `other` (one instruction)
+    // and `prog` (loading the address of the map `COUNTS`, calling the kfunc `bpf_kfunc` and
+    // exiting), and the relocations of `prog`.
+    fn object() -> Vec<u8> {
+        let mut strtab = vec![0u8];
+        let mut names = HashMap::new();
+        for name in [
+            "xdp",
+            "maps",
+            ".symtab",
+            ".strtab",
+            ".relxdp",
+            "other",
+            "prog",
+            "COUNTS",
+            "bpf_kfunc",
+        ] {
+            names.insert(name, strtab.len() as u32);
+            strtab.extend(name.as_bytes());
+            strtab.push(0);
+        }
+        let name = |name: &str| names[name];
+
+        let mut xdp = EXIT.to_vec();
+        xdp.extend([0x18, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        xdp.extend([0x85, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        xdp.extend(EXIT);
+
+        let symbol = |name: u32, info: u8, section: u16, value: u64, size: u64| {
+            let mut s = name.to_le_bytes().to_vec();
+            s.extend([info, 0]);
+            s.extend(section.to_le_bytes());
+            s.extend(value.to_le_bytes());
+            s.extend(size.to_le_bytes());
+            s
+        };
+        let mut symtab = vec![0u8; 24];
+        symtab.extend(symbol(name("other"), 0x12, 1, 0, 8));
+        symtab.extend(symbol(name("prog"), 0x12, 1, 8, 32));
+        symtab.extend(symbol(name("COUNTS"), 0x11, 2, 0, 16));
+        symtab.extend(symbol(name("bpf_kfunc"), 0x10, SHN_UNDEF, 0, 0));
+
+        let mut relxdp = vec![];
+        for (offset, symbol) in [(8u64, 3u64), (24, 4)] {
+            relxdp.extend(offset.to_le_bytes());
+            relxdp.extend(((symbol << 32) | 1).to_le_bytes());
+        }
+
+        // name, type, data, link and info of the sections after the null section.
+        let sections = [
+            (name("xdp"), 1, xdp, 0, 0),
+            (name("maps"), 1, vec![0u8; 16], 0, 0),
+            (name(".symtab"), SHT_SYMTAB, symtab, 4, 0),
+            (name(".strtab"), 3, strtab, 0, 0),
+            (name(".relxdp"), SHT_REL, relxdp, 3, 1),
+        ];
+
+        let mut data = vec![0u8; 64];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        let mut headers = vec![0u8; 64];
+        for (name, kind, section, link, info) in sections {
+            let mut header = vec![0u8; 64];
+            header[0..4].copy_from_slice(&name.to_le_bytes());
+            header[4..8].copy_from_slice(&u32::to_le_bytes(kind));
+            header[0x18..0x20].copy_from_slice(&(data.len() as u64).to_le_bytes());
+            header[0x20..0x28].copy_from_slice(&(section.len() as u64).to_le_bytes());
+            header[0x28..0x2C].copy_from_slice(&u32::to_le_bytes(link));
+            header[0x2C..0x30].copy_from_slice(&u32::to_le_bytes(info));
+            headers.extend(header);
+            data.extend(section);
+        }
+        let shoff = data.len() as u64;
+        data.extend(headers);
+        data[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        data[0x3A..0x3C].copy_from_slice(&64u16.to_le_bytes());
+        data[0x3C..0x3E].copy_from_slice(&6u16.to_le_bytes());
+        data[0x3E..0x40].copy_from_slice(&4u16.to_le_bytes());
+
+        data
+    }
+
+    #[test]
+    fn reads_the_instructions_and_the_relocations_of_a_program() {
+        let data = object();
+        let elf = Elf::parse(&data).unwrap();
+
+        let (insns, relocations) = elf.program("prog").unwrap();
+        assert_eq!(insns.len(), 4);
+        assert_eq!(insns[0].code, BPF_LD_IMM64);
+        assert_eq!(insns[2].code, BPF_CALL);
+        assert_eq!(insns[2].imm, -1);
+        assert_eq!(insns[3], BpfInsn::from_bytes(&EXIT));
+
+        let relocations: Vec<_> = relocations
+            .iter()
+            .map(|(index, symbol)| (*index, symbol.name.as_str(), symbol.section))
+            .collect();
+        assert_eq!(relocations, [(0, "COUNTS", 2), (2, "bpf_kfunc", SHN_UNDEF)]);
+        assert_eq!(elf.section_name(2), MAPS_SECTION);
+        assert_eq!(elf.map_at(2, 0), Some("COUNTS"));
+
+        let (insns, relocations) = elf.program("other").unwrap();
+        assert_eq!(insns.len(), 1);
+        assert!(relocations.is_empty());
+
+        assert!(elf.program("missing").is_err());
+    }
+
+    #[test]
+    fn refuses_other_files() {
+        assert!(Elf::parse(b"#!/bin/sh").is_err());
+
+        let mut data = object();
+        data.truncate(100);
+        assert!(Elf::parse(&data).is_err());
+    }
+
+    #[test]
+    fn relocates_the_maps_and_the_kfuncs() {
+        let mut ld = BpfInsn::from_bytes(&[0x18, 0x01, 0, 0, 0, 0, 0, 0]);
+        relocate(&mut ld, Target::Map(7)).unwrap();
+        assert_eq!((ld.regs, ld.imm), (0x11, 7));
+
+        let mut call = BpfInsn::from_bytes(&[0x85, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        relocate(&mut call, Target::Kfunc(1234)).unwrap();
+        assert_eq!((call.regs, call.off, call.imm), (0x20, 0, 1234));
+
+        assert!(relocate(&mut call, Target::Map(7)).is_err());
+        assert!(relocate(&mut ld, Target::Kfunc(1234)).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+mod dev_bound;
+
+use std::ffi::CString;
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::time::Duration;
+
+use anyhow::Context;
+
+use aya::maps::{IterableMap, MapData, PerCpuArray};
+use aya::Ebpf;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::process::Command;
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    RxSample, HINT_HASH, HINT_TIMESTAMP, HINT_VLAN, MAX_SAMPLES, NUM_HASH_BUCKETS, NUM_HINTS,
+    NUM_RESULTS, RESULT_NO_DATA, RESULT_OK, RESULT_UNSUPPORTED,
+};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Interval (in seconds) between two successive displays of the hints.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Send `CHECK` pings from inside the test environment of the interface (created using the
+    /// `testenv` scripts), display the hints provided by the device for them and exit.
+    #[clap(long)]
+    check: Option<u32>,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// The maps of the program.
+struct Maps {
+    stats: PerCpuArray<MapData, u64>,
+    buckets: PerCpuArray<MapData, u64>,
+    samples: PerCpuArray<MapData, RxSample>,
+    seq: PerCpuArray<MapData, u64>,
+}
+
+impl Maps {
+    // The fds of the maps by name, for the relocations of the program (see `dev_bound`).
+    fn fds(&self) -> [(&'static str, BorrowedFd<'_>); 4] {
+        [
+            ("HINT_STATS", self.stats.map().fd().as_fd()),
+            ("HASH_BUCKETS", self.buckets.map().fd().as_fd()),
+            ("RX_SAMPLES", self.samples.map().fd().as_fd()),
+            ("SAMPLE_SEQ", self.seq.map().fd().as_fd()),
+        ]
+    }
+}
+
+// Names of the hints, the index in this array is the hint.
+const HINT_NAMES: [&str; NUM_HINTS as usize] = ["hash", "timestamp", "vlan"];
+
+// Names of the results of the kfuncs, the index in this array is the result.
+const RESULT_NAMES: [&str; NUM_RESULTS as usize] = ["ok", "no data", "unsupported", "error"];
+
+// Maximum number of the new samples displayed at a time.
+const MAX_PRINTED_SAMPLES: usize = 8;
+
+// The variants of the program (the suffixes of the name), in the order they are tried, along with
+// the hints read by every variant (bit `1 << HINT_*`).
+const PROGRAM_VARIANTS: [(&str, u32); 3] = [
+    (
+        "",
+        (1 << HINT_HASH) | (1 << HINT_TIMESTAMP) | (1 << HINT_VLAN),
+    ),
+    ("_no_vlan", (1 << HINT_HASH) | (1 << HINT_TIMESTAMP)),
+    ("_no_kfuncs", 0),
+];
+
+// Loads the first variant of the program that the kernel accepts as a device-bound program of the
+// interface, returns the name of the variant, the hints it reads and the fd of the program.
+//
+// A variant calling a kfunc that the kernel does not have (eg. `bpf_xdp_metadata_rx_vlan_tag`
+// before Linux 6.8) is refused, as well as the variants calling any of the RX metadata kfuncs if
+// the kernel (before Linux 6.3) or the driver of the interface does not allow the device-bound
+// programs. The last variant, without the kfuncs, is only there to display the samples in that
+// case.
+fn load_program(
+    loader: &dev_bound::DevBoundLoader,
+    program: &str,
+) -> anyhow::Result<(String, u32, OwnedFd)> {
+    for (suffix, hints) in PROGRAM_VARIANTS {
+        let name = format!("{program}{suffix}");
+        match loader.load(&name) {
+            Ok(fd) => return Ok((name, hints, fd)),
+            Err(e) => warn!(
+                "Failed to load the program '{}', trying the next variant: {:#}",
+                name, e
+            ),
+        }
+    }
+
+    Err(anyhow::Error::msg(
+        "No variant of the program could be loaded.",
+    ))
+}
+
+fn ifindex_from_name(iface: &str) -> Result<u32, anyhow::Error> {
+    let name = CString::new(iface)?;
+    let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if ifindex == 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Interface '{iface}' not found"));
+    }
+
+    Ok(ifindex)
+}
+
+// Returns the number of the packets received by the program, summed for all the CPUs.
+fn received_packets(seq: &PerCpuArray<MapData, u64>) -> anyhow::Result<u64> {
+    Ok(seq.get(&0, 0)?.iter().sum())
+}
+
+// Returns the counters of the results of every hint, summed for all the CPUs.
+fn hint_counts(
+    stats: &PerCpuArray<MapData, u64>,
+) -> anyhow::Result<Vec<[u64; NUM_RESULTS as usize]>> {
+    let mut counts = vec![];
+    for hint in 0..NUM_HINTS {
+        let mut results = [0; NUM_RESULTS as usize];
+        for (result, count) in results.iter_mut().enumerate() {
+            *count = stats
+                .get(&(hint * NUM_RESULTS + result as u32), 0)?
+                .iter()
+                .sum();
+        }
+        counts.push(results);
+    }
+
+    Ok(counts)
+}
+
+// Describes whether the device provided the hint, from the results of the kfunc of the hint.
+fn hint_status(results: &[u64; NUM_RESULTS as usize], read: bool) -> &'static str {
+    if !read {
+        "not read by the loaded program"
+    } else if results[RESULT_OK as usize] > 0 {
+        "provided"
+    } else if results[RESULT_UNSUPPORTED as usize] > 0 {
+        "not supported by the driver"
+    } else if results[RESULT_NO_DATA as usize] > 0 {
+        "supported, but no data for the packets"
+    } else if results.iter().sum::<u64>() > 0 {
+        "failed"
+    } else {
+        "no packets"
+    }
+}
+
+// Displays the results of the kfuncs of every hint, `hints` are the hints read by the loaded
+// program.
+fn print_hints(stats: &PerCpuArray<MapData, u64>, hints: u32) -> anyhow::Result<()> {
+    for (hint, results) in hint_counts(stats)?.iter().enumerate() {
+        let counts = RESULT_NAMES
+            .iter()
+            .zip(results)
+            .map(|(name, count)| format!("{name}: {count:>8}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!(
+            "hint: {:<9} {} ({})",
+            HINT_NAMES[hint],
+            counts,
+            hint_status(results, hints & (1 << hint) != 0)
+        );
+    }
+
+    Ok(())
+}
+
+fn print_hash_buckets(buckets: &PerCpuArray<MapData, u64>) -> anyhow::Result<()> {
+    let mut counts = vec![];
+    for bucket in 0..NUM_HASH_BUCKETS {
+        counts.push(buckets.get(&bucket, 0)?.iter().sum::<u64>());
+    }
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return Ok(());
+    }
+
+    let distribution = counts
+        .iter()
+        .map(|count| format!("{:.0}%", *count as f64 * 100.0 / total as f64))
+        .collect::<Vec<_>>()
+        .join(" ");
+    info!("RX hash buckets: {}", distribution);
+
+    Ok(())
+}
+
+// Reads the samples stored by the program after the last read. `last_seq` has the sequence number
+// of the last sample read for every CPU.
+fn new_samples(
+    samples: &PerCpuArray<MapData, RxSample>,
+    last_seq: &mut Vec<u64>,
+) -> anyhow::Result<Vec<(usize, RxSample)>> {
+    let mut new = vec![];
+    for index in 0..MAX_SAMPLES {
+        for (cpu, sample) in samples.get(&index, 0)?.iter().enumerate() {
+            if cpu >= last_seq.len() {
+                last_seq.resize(cpu + 1, 0);
+            }
+            if sample.seq > last_seq[cpu] {
+                new.push((cpu, *sample));
+            }
+        }
+    }
+    for (cpu, sample) in &new {
+        last_seq[*cpu] = last_seq[*cpu].max(sample.seq);
+    }
+    new.sort_by_key(|(_, sample)| sample.xdp_timestamp);
+
+    Ok(new)
+}
+
+fn print_sample(cpu: usize, sample: &RxSample) {
+    let provided = |hint: u32| sample.hints & (1 << hint) != 0;
+
+    let hash = if provided(HINT_HASH) {
+        format!(
+            "{:#010x} (rss type: {:#x})",
+            sample.rx_hash, sample.rss_type
+        )
+    } else {
+        "-".to_string()
+    };
+    let timestamp = if provided(HINT_TIMESTAMP) {
+        sample.rx_timestamp.to_string()
+    } else {
+        "-".to_string()
+    };
+    let vlan = if provided(HINT_VLAN) {
+        format!(
+            "{} (proto: {:#06x}, prio: {})",
+            sample.vlan_tci & 0x0FFF,
+            sample.vlan_proto,
+            sample.vlan_tci >> 13
+        )
+    } else {
+        "-".to_string()
+    };
+
+    info!(
+        "cpu: {:>3} seq: {:>8} hash: {} timestamp: {} vlan: {}",
+        cpu, sample.seq, hash, timestamp, vlan
+    );
+}
+
+fn print_samples(
+    samples: &PerCpuArray<MapData, RxSample>,
+    last_seq: &mut Vec<u64>,
+) -> anyhow::Result<()> {
+    let new = new_samples(samples, last_seq)?;
+    let skipped = new.len().saturating_sub(MAX_PRINTED_SAMPLES);
+    if skipped > 0 {
+        info!("... {} more packets", skipped);
+    }
+    for (cpu, sample) in new.iter().skip(skipped) {
+        print_sample(*cpu, sample);
+    }
+
+    Ok(())
+}
+
+// Sends the pings from inside the test environment and displays the hints provided by the device
+// for the received packets.
+//
+// The `testenv` scripts create the namespace with the same name as the interface, with the
+// `veth0` interface inside. The pings are sent to the all nodes multicast address, so that the
+// address of the interface is not needed.
+async fn check_hints(iface: &str, count: u32, hints: u32, maps: &Maps) -> anyhow::Result<()> {
+    let before = received_packets(&maps.seq)?;
+
+    let status = Command::new("ip")
+        .args([
+            "netns", "exec", iface, "ping", "-6", "-q", "-i", "0.2", "-I", "veth0",
+        ])
+        .args(["-c", &count.to_string(), "ff02::1"])
+        .status()
+        .await
+        .context("Failed to run 'ping' in the test environment")?;
+    if !status.success() {
+        warn!("'ping' exited with: {}", status);
+    }
+
+    let received = received_packets(&maps.seq)? - before;
+    info!("Packets received by the program: {}", received);
+    print_samples(&maps.samples, &mut vec![])?;
+    print_hints(&maps.stats, hints)?;
+
+    if received < count as u64 {
+        error!("FAILED: Not all the pings were received by the program.");
+        return Err(anyhow::Error::msg("Hints check failed."));
+    }
+    if hints == 0 {
+        error!("FAILED: The loaded program does not call the RX metadata kfuncs.");
+        return Err(anyhow::Error::msg("Hints check failed."));
+    }
+    info!("PASSED: The hints of all the pings were read.");
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The first variant of the program that the kernel accepts is loaded as a device-bound program of
+// the interface (see `load_program`). The results of the kfuncs, the RX hash distribution and the
+// recent samples are displayed every `--interval` seconds.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let maps = Maps {
+        stats: PerCpuArray::try_from(bpf.take_map("HINT_STATS").unwrap())?,
+        buckets: PerCpuArray::try_from(bpf.take_map("HASH_BUCKETS").unwrap())?,
+        samples: PerCpuArray::try_from(bpf.take_map("RX_SAMPLES").unwrap())?,
+        seq: PerCpuArray::try_from(bpf.take_map("SAMPLE_SEQ").unwrap())?,
+    };
+
+    let ifindex = ifindex_from_name(&opts.iface)?;
+    let loader = dev_bound::DevBoundLoader::new(&bpf_bin, maps.fds(), ifindex)?;
+    let (program_name, hints, program) = load_program(&loader, "{{to_snake_case tutorial_name}}")?;
+    let _link = dev_bound::attach(program.as_fd(), ifindex).context(
+        "Failed to attach the device-bound program to the interface (in the native mode)",
+    )?;
+
+    info!(
+        "XDP Program '{}' attached to '{}'!",
+        program_name, &opts.iface
+    );
+    if hints == 0 {
+        error!(
+            "The RX metadata kfuncs are not supported for '{}' (see the warnings above), no hints are read.",
+            &opts.iface
+        );
+    }
+
+    let read = HINT_NAMES
+        .iter()
+        .enumerate()
+        .filter(|(hint, _)| hints & (1 << hint) != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>();
+    info!("Hints read by the program: {:?}", read);
+
+    if let Some(count) = opts.check {
+        return check_hints(&opts.iface, count, hints, &maps).await;
+    }
+
+    info!("Now waiting for Ctrl-C");
+    let mut last_seq = vec![];
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                print_samples(&maps.samples, &mut last_seq)?;
+                print_hash_buckets(&maps.buckets)?;
+                print_hints(&maps.stats, hints)?;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
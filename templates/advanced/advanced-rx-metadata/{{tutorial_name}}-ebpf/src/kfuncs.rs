@@ -0,0 +1,32 @@
+// Declarations of the XDP RX metadata kfuncs (see `net/core/xdp.c` in the kernel sources).
+//
+// Unlike the helpers, the kfuncs do not have fixed numbers, the calls are resolved by the loader
+// using the BTF of the kernel. For a device-bound program, the verifier replaces the calls with
+// the implementation of the driver of the device, or with the default implementation returning
+// `-EOPNOTSUPP` if the driver does not implement the kfunc. The verifier refuses the calls in a
+// program that is not device-bound.
+
+use aya_ebpf::bindings::xdp_md;
+
+/// Returned by the kfuncs if the device does not have the hint for the packet.
+pub const ENODATA: i32 = 61;
+
+/// Returned by the kfuncs if the driver does not implement the kfunc.
+pub const EOPNOTSUPP: i32 = 95;
+
+extern "C" {
+    /// Reads the RX timestamp (in nanoseconds) of the packet (Linux 6.3 or later).
+    pub fn bpf_xdp_metadata_rx_timestamp(ctx: *const xdp_md, timestamp: *mut u64) -> i32;
+
+    /// Reads the RX hash of the packet and the type of the hash (`enum xdp_rss_hash_type`)
+    /// (Linux 6.3 or later, the `rss_type` argument since Linux 6.5).
+    pub fn bpf_xdp_metadata_rx_hash(ctx: *const xdp_md, hash: *mut u32, rss_type: *mut u32) -> i32;
+
+    /// Reads the VLAN tag stripped by the device, the protocol (in network byte order) and the TCI
+    /// (Linux 6.8 or later).
+    pub fn bpf_xdp_metadata_rx_vlan_tag(
+        ctx: *const xdp_md,
+        vlan_proto: *mut u16,
+        vlan_tci: *mut u16,
+    ) -> i32;
+}
@@ -0,0 +1,166 @@
+#![no_std]
+#![no_main]
+
+mod kfuncs;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::bpf_ktime_get_ns,
+    macros::{map, xdp},
+    maps::PerCpuArray,
+    programs::XdpContext,
+};
+
+use kfuncs::{
+    bpf_xdp_metadata_rx_hash, bpf_xdp_metadata_rx_timestamp, bpf_xdp_metadata_rx_vlan_tag, ENODATA,
+    EOPNOTSUPP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    RxSample, HINT_HASH, HINT_TIMESTAMP, HINT_VLAN, MAX_SAMPLES, NUM_HASH_BUCKETS, NUM_HINTS,
+    NUM_RESULTS, RESULT_ERROR, RESULT_NO_DATA, RESULT_OK, RESULT_UNSUPPORTED,
+};
+
+// Results of the kfuncs, indexed by `hint * NUM_RESULTS + result`.
+#[map]
+static HINT_STATS: PerCpuArray<u64> =
+    PerCpuArray::<u64>::with_max_entries(NUM_HINTS * NUM_RESULTS, 0);
+
+// Distribution of the RX hashes, indexed by `rx_hash % NUM_HASH_BUCKETS`.
+#[map]
+static HASH_BUCKETS: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(NUM_HASH_BUCKETS, 0);
+
+// The last `MAX_SAMPLES` samples of every CPU, the sample with the sequence number `seq` is at the
+// index `seq % MAX_SAMPLES`.
+#[map]
+static RX_SAMPLES: PerCpuArray<RxSample> =
+    PerCpuArray::<RxSample>::with_max_entries(MAX_SAMPLES, 0);
+
+// The sequence number of the last sample of every CPU, also the number of the packets received on
+// the CPU.
+#[map]
+static SAMPLE_SEQ: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(1, 0);
+
+#[inline(always)]
+fn count(stats: &PerCpuArray<u64>, index: u32) {
+    if let Some(count) = stats.get_ptr_mut(index) {
+        unsafe { *count += 1 };
+    }
+}
+
+// Counts the value returned by the kfunc of the `hint` and returns `true` if the kfunc returned
+// the hint.
+//
+// A kfunc that is not implemented by the driver returns `-EOPNOTSUPP`, so the program keeps
+// working (without the hint) on all the devices.
+#[inline(always)]
+fn count_hint(hint: u32, ret: i32) -> bool {
+    let result = match ret {
+        0 => RESULT_OK,
+        ret if ret == -ENODATA => RESULT_NO_DATA,
+        ret if ret == -EOPNOTSUPP => RESULT_UNSUPPORTED,
+        _ => RESULT_ERROR,
+    };
+    count(&HINT_STATS, hint * NUM_RESULTS + result);
+
+    result == RESULT_OK
+}
+
+// Stores the sample in the next entry of the `RX_SAMPLES` map of the CPU.
+#[inline(always)]
+fn store_sample(sample: &mut RxSample) {
+    let Some(seq) = SAMPLE_SEQ.get_ptr_mut(0) else {
+        return;
+    };
+
+    unsafe {
+        *seq += 1;
+        sample.seq = *seq;
+        if let Some(entry) = RX_SAMPLES.get_ptr_mut((*seq % MAX_SAMPLES as u64) as u32) {
+            *entry = *sample;
+        }
+    }
+}
+
+// Reads the RX hash of the packet, and counts it in the `HASH_BUCKETS` map.
+#[inline(always)]
+fn read_hash(ctx: &XdpContext, sample: &mut RxSample) {
+    let ret =
+        unsafe { bpf_xdp_metadata_rx_hash(ctx.ctx, &mut sample.rx_hash, &mut sample.rss_type) };
+    if count_hint(HINT_HASH, ret) {
+        sample.hints |= 1 << HINT_HASH;
+        count(&HASH_BUCKETS, sample.rx_hash % NUM_HASH_BUCKETS);
+    }
+}
+
+#[inline(always)]
+fn read_timestamp(ctx: &XdpContext, sample: &mut RxSample) {
+    let ret = unsafe { bpf_xdp_metadata_rx_timestamp(ctx.ctx, &mut sample.rx_timestamp) };
+    if count_hint(HINT_TIMESTAMP, ret) {
+        sample.hints |= 1 << HINT_TIMESTAMP;
+    }
+}
+
+#[inline(always)]
+fn read_vlan(ctx: &XdpContext, sample: &mut RxSample) {
+    let mut vlan_proto = 0;
+    let ret =
+        unsafe { bpf_xdp_metadata_rx_vlan_tag(ctx.ctx, &mut vlan_proto, &mut sample.vlan_tci) };
+    if count_hint(HINT_VLAN, ret) {
+        sample.hints |= 1 << HINT_VLAN;
+        sample.vlan_proto = u16::from_be(vlan_proto);
+    }
+}
+
+#[inline(always)]
+fn new_sample() -> RxSample {
+    RxSample {
+        xdp_timestamp: unsafe { bpf_ktime_get_ns() },
+        ..Default::default()
+    }
+}
+
+// Reads the RX hash, the RX timestamp and the VLAN tag of the packet from the device using the
+// RX metadata kfuncs, counts the results and stores the hints in the `RX_SAMPLES` map. All the
+// packets are passed.
+//
+// A program calling a kfunc that the kernel does not have can't be loaded, so the variants below
+// leave out the newest kfuncs. The runner loads the first variant that the kernel accepts (see
+// `PROGRAM_VARIANTS` in the runner). The runner loads the programs with its own loader (see
+// `dev_bound.rs`), which does not support the global data, so the program does not use `aya-log`.
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    let mut sample = new_sample();
+    read_hash(&ctx, &mut sample);
+    read_timestamp(&ctx, &mut sample);
+    read_vlan(&ctx, &mut sample);
+    store_sample(&mut sample);
+
+    xdp_action::XDP_PASS
+}
+
+// The same as the program above, without the VLAN tag (`bpf_xdp_metadata_rx_vlan_tag` needs
+// Linux 6.8 or later).
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_no_vlan(ctx: XdpContext) -> u32 {
+    let mut sample = new_sample();
+    read_hash(&ctx, &mut sample);
+    read_timestamp(&ctx, &mut sample);
+    store_sample(&mut sample);
+
+    xdp_action::XDP_PASS
+}
+
+// Stores the samples without any hints, for the kernels or the drivers that do not allow the
+// program to call the RX metadata kfuncs at all.
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_no_kfuncs(_ctx: XdpContext) -> u32 {
+    store_sample(&mut new_sample());
+
+    xdp_action::XDP_PASS
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
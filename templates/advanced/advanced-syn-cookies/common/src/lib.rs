@@ -0,0 +1,39 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of the protected TCP ports.
+pub const MAX_PORTS: u32 = 64;
+
+/// Maximum number of the connections with a validated cookie tracked at a time. When the map is
+/// full, the least recently used connections are evicted.
+pub const MAX_CONNECTIONS: u32 = 65536;
+
+/// Index of the counter of the SYN-ACKs with a cookie sent in reply to the SYNs.
+pub const COOKIES_SENT: u32 = 0;
+
+/// Index of the counter of the ACKs with a valid cookie, the connection is passed to the kernel.
+pub const COOKIES_VALIDATED: u32 = 1;
+
+/// Index of the counter of the ACKs of unknown connections without a valid cookie, dropped.
+pub const COOKIES_REJECTED: u32 = 2;
+
+/// Number of the counters in the `SYNCOOKIE_STATS` map.
+pub const NUM_COUNTERS: u32 = 3;
+
+/// A connection with a validated cookie, the key of the `CONNECTIONS` map. The fields are as in
+/// the packets received from the client, the IPv4 addresses are stored as the IPv4-mapped IPv6
+/// addresses (`::ffff:a.b.c.d`) and the ports are in the host byte order.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "user", derive(Debug))]
+pub struct ConnKey {
+    pub src_addr: [u8; 16],
+    pub dst_addr: [u8; 16],
+    pub src_port: u16,
+    pub dst_port: u16,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for ConnKey {}
@@ -0,0 +1,170 @@
+// Building and parsing the Ethernet + IPv4/IPv6 + TCP packets for the `--test-run` checks.
+
+use std::net::IpAddr;
+
+pub(crate) const TCP_FLAG_SYN: u8 = 0x02;
+pub(crate) const TCP_FLAG_PSH: u8 = 0x08;
+pub(crate) const TCP_FLAG_ACK: u8 = 0x10;
+
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86DD;
+const IPPROTO_TCP: u8 = 6;
+
+const ETH_HLEN: usize = 14;
+
+/// The fields of a TCP packet used by the checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TcpPacket {
+    pub(crate) src_mac: [u8; 6],
+    pub(crate) dst_mac: [u8; 6],
+    pub(crate) src_addr: IpAddr,
+    pub(crate) dst_addr: IpAddr,
+    pub(crate) src_port: u16,
+    pub(crate) dst_port: u16,
+    pub(crate) seq: u32,
+    pub(crate) ack: u32,
+    pub(crate) flags: u8,
+
+    /// The TCP options, padded to a multiple of 4 bytes.
+    pub(crate) options: Vec<u8>,
+    pub(crate) payload: Vec<u8>,
+}
+
+// Sums the 16 bit words (in the network byte order) of the data, without folding.
+fn sum_words(data: &[u8]) -> u32 {
+    data.chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)]) as u32)
+        .sum()
+}
+
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+// Sum of the pseudo header of the TCP checksum.
+fn pseudo_header_sum(src_addr: IpAddr, dst_addr: IpAddr, tcp_len: usize) -> u32 {
+    let addrs = match (src_addr, dst_addr) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => [src.octets().to_vec(), dst.octets().to_vec()],
+        (IpAddr::V6(src), IpAddr::V6(dst)) => [src.octets().to_vec(), dst.octets().to_vec()],
+        _ => panic!("Addresses of different families"),
+    };
+
+    sum_words(&addrs.concat()) + IPPROTO_TCP as u32 + tcp_len as u32
+}
+
+impl TcpPacket {
+    /// Builds the packet, with the valid IPv4 and TCP checksums.
+    pub(crate) fn build(&self) -> Vec<u8> {
+        let tcp_len = 20 + self.options.len() + self.payload.len();
+
+        let mut packet = vec![];
+        packet.extend_from_slice(&self.dst_mac);
+        packet.extend_from_slice(&self.src_mac);
+        match (self.src_addr, self.dst_addr) {
+            (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                packet.extend_from_slice(&ETH_P_IP.to_be_bytes());
+                let mut iph = [0u8; 20];
+                iph[0] = 0x45;
+                iph[2..4].copy_from_slice(&((20 + tcp_len) as u16).to_be_bytes());
+                iph[8] = 64;
+                iph[9] = IPPROTO_TCP;
+                iph[12..16].copy_from_slice(&src.octets());
+                iph[16..20].copy_from_slice(&dst.octets());
+                let check = fold(sum_words(&iph));
+                iph[10..12].copy_from_slice(&check.to_be_bytes());
+                packet.extend_from_slice(&iph);
+            }
+            (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                packet.extend_from_slice(&ETH_P_IPV6.to_be_bytes());
+                packet.extend_from_slice(&[0x60, 0, 0, 0]);
+                packet.extend_from_slice(&(tcp_len as u16).to_be_bytes());
+                packet.extend_from_slice(&[IPPROTO_TCP, 64]);
+                packet.extend_from_slice(&src.octets());
+                packet.extend_from_slice(&dst.octets());
+            }
+            _ => panic!("Addresses of different families"),
+        }
+
+        let mut tcph = vec![];
+        tcph.extend_from_slice(&self.src_port.to_be_bytes());
+        tcph.extend_from_slice(&self.dst_port.to_be_bytes());
+        tcph.extend_from_slice(&self.seq.to_be_bytes());
+        tcph.extend_from_slice(&self.ack.to_be_bytes());
+        tcph.push((((20 + self.options.len()) / 4) as u8) << 4);
+        tcph.push(self.flags);
+        tcph.extend_from_slice(&65535u16.to_be_bytes());
+        tcph.extend_from_slice(&[0, 0, 0, 0]);
+        tcph.extend_from_slice(&self.options);
+        tcph.extend_from_slice(&self.payload);
+        let sum = pseudo_header_sum(self.src_addr, self.dst_addr, tcp_len) + sum_words(&tcph);
+        tcph[16..18].copy_from_slice(&fold(sum).to_be_bytes());
+        packet.extend_from_slice(&tcph);
+
+        packet
+    }
+
+    /// Parses the packet, checking the IPv4 and the TCP checksums.
+    pub(crate) fn parse(packet: &[u8]) -> Result<Self, String> {
+        let short = || "packet too short".to_string();
+        let eth = packet.get(..ETH_HLEN).ok_or_else(short)?;
+
+        let (src_addr, dst_addr, tcp) = match u16::from_be_bytes([eth[12], eth[13]]) {
+            ETH_P_IP => {
+                let iph = packet.get(ETH_HLEN..ETH_HLEN + 20).ok_or_else(short)?;
+                if iph[0] != 0x45 || iph[9] != IPPROTO_TCP {
+                    return Err("not an IPv4 TCP packet without the options".to_string());
+                }
+                if fold(sum_words(iph)) != 0 {
+                    return Err("wrong IPv4 checksum".to_string());
+                }
+                let tot_len = u16::from_be_bytes([iph[2], iph[3]]) as usize;
+                let tcp = packet
+                    .get(ETH_HLEN + 20..ETH_HLEN + tot_len)
+                    .ok_or_else(short)?;
+                let src: [u8; 4] = iph[12..16].try_into().unwrap();
+                let dst: [u8; 4] = iph[16..20].try_into().unwrap();
+                (IpAddr::from(src), IpAddr::from(dst), tcp)
+            }
+            ETH_P_IPV6 => {
+                let ip6h = packet.get(ETH_HLEN..ETH_HLEN + 40).ok_or_else(short)?;
+                if ip6h[0] >> 4 != 6 || ip6h[6] != IPPROTO_TCP {
+                    return Err("not an IPv6 TCP packet".to_string());
+                }
+                let payload_len = u16::from_be_bytes([ip6h[4], ip6h[5]]) as usize;
+                let tcp = packet
+                    .get(ETH_HLEN + 40..ETH_HLEN + 40 + payload_len)
+                    .ok_or_else(short)?;
+                let src: [u8; 16] = ip6h[8..24].try_into().unwrap();
+                let dst: [u8; 16] = ip6h[24..40].try_into().unwrap();
+                (IpAddr::from(src), IpAddr::from(dst), tcp)
+            }
+            _ => return Err("not an IP packet".to_string()),
+        };
+
+        let doff = (*tcp.get(12).ok_or_else(short)? >> 4) as usize * 4;
+        if doff < 20 || doff > tcp.len() {
+            return Err("wrong TCP data offset".to_string());
+        }
+        if fold(pseudo_header_sum(src_addr, dst_addr, tcp.len()) + sum_words(tcp)) != 0 {
+            return Err("wrong TCP checksum".to_string());
+        }
+
+        Ok(Self {
+            dst_mac: eth[0..6].try_into().unwrap(),
+            src_mac: eth[6..12].try_into().unwrap(),
+            src_addr,
+            dst_addr,
+            src_port: u16::from_be_bytes([tcp[0], tcp[1]]),
+            dst_port: u16::from_be_bytes([tcp[2], tcp[3]]),
+            seq: u32::from_be_bytes(tcp[4..8].try_into().unwrap()),
+            ack: u32::from_be_bytes(tcp[8..12].try_into().unwrap()),
+            flags: tcp[13],
+            options: tcp[20..doff].to_vec(),
+            payload: tcp[doff..].to_vec(),
+        })
+    }
+}
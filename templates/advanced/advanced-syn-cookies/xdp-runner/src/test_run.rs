@@ -0,0 +1,60 @@
+// Running an XDP program on a given packet using `BPF_PROG_TEST_RUN`, the same as in the
+// 'packet01-parsing' tutorial (see its README for how the test run works).
+
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+const BPF_PROG_TEST_RUN: libc::c_long = 10;
+
+// The `test` member of the `union bpf_attr` (see `include/uapi/linux/bpf.h`).
+#[repr(C)]
+#[derive(Debug, Default)]
+struct BpfProgTestRunAttr {
+    prog_fd: u32,
+    retval: u32,
+    data_size_in: u32,
+    data_size_out: u32,
+    data_in: u64,
+    data_out: u64,
+    repeat: u32,
+    duration: u32,
+    ctx_size_in: u32,
+    ctx_size_out: u32,
+    ctx_in: u64,
+    ctx_out: u64,
+    flags: u32,
+    cpu: u32,
+    batch_size: u32,
+}
+
+/// Runs the XDP program on the `packet` and returns the XDP action returned by the program and the
+/// packet after the program is run.
+pub(crate) fn test_run_xdp(prog_fd: BorrowedFd<'_>, packet: &[u8]) -> io::Result<(u32, Vec<u8>)> {
+    // The program may grow the packet (eg. by using `bpf_xdp_adjust_head`).
+    let mut data_out = vec![0u8; packet.len() + 256];
+
+    let mut attr = BpfProgTestRunAttr {
+        prog_fd: prog_fd.as_raw_fd() as u32,
+        data_size_in: packet.len() as u32,
+        data_size_out: data_out.len() as u32,
+        data_in: packet.as_ptr() as u64,
+        data_out: data_out.as_mut_ptr() as u64,
+        repeat: 1,
+        ..Default::default()
+    };
+
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            BPF_PROG_TEST_RUN,
+            &mut attr as *mut BpfProgTestRunAttr,
+            std::mem::size_of::<BpfProgTestRunAttr>(),
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    data_out.truncate(attr.data_size_out as usize);
+    Ok((attr.retval, data_out))
+}
@@ -0,0 +1,352 @@
+mod packet;
+mod test_run;
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::time::Duration;
+
+use anyhow::Context;
+
+use aya::maps::{HashMap, MapData, PerCpuArray};
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    COOKIES_REJECTED, COOKIES_SENT, COOKIES_VALIDATED, MAX_PORTS, NUM_COUNTERS,
+};
+
+use packet::{TcpPacket, TCP_FLAG_ACK, TCP_FLAG_PSH, TCP_FLAG_SYN};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// The TCP ports protected by the SYN cookies (comma separated).
+    #[clap(long, value_delimiter = ',', default_value = "80")]
+    ports: Vec<u16>,
+
+    /// Interval (in seconds) between two successive displays of the counters.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Run the program on the packets of a TCP handshake (using `BPF_PROG_TEST_RUN`) and check the
+    /// actions, the SYN-ACKs and the counters, instead of attaching the program to the interface.
+    #[clap(long)]
+    test_run: bool,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+const XDP_DROP: u32 = 1;
+const XDP_PASS: u32 = 2;
+const XDP_TX: u32 = 3;
+
+// The MAC addresses of the test packets.
+const CLIENT_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+const SERVER_MAC: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x02];
+
+// The MSS option of the test SYNs.
+const CLIENT_MSS: u16 = 1460;
+
+type Counters = [u64; NUM_COUNTERS as usize];
+
+// Returns the counters, summed for all the CPUs.
+fn counters(stats: &PerCpuArray<MapData, u64>) -> anyhow::Result<Counters> {
+    let mut counters = [0; NUM_COUNTERS as usize];
+    for (index, counter) in counters.iter_mut().enumerate() {
+        *counter = stats.get(&(index as u32), 0)?.iter().sum();
+    }
+
+    Ok(counters)
+}
+
+fn print_counters(stats: &PerCpuArray<MapData, u64>) -> anyhow::Result<()> {
+    let counters = counters(stats)?;
+    info!(
+        "cookies sent: {:>10} validated: {:>10} rejected: {:>10}",
+        counters[COOKIES_SENT as usize],
+        counters[COOKIES_VALIDATED as usize],
+        counters[COOKIES_REJECTED as usize]
+    );
+
+    Ok(())
+}
+
+// Runs the test packets through the program and keeps the number of the failed checks.
+struct TestRun<'a> {
+    prog_fd: BorrowedFd<'a>,
+    stats: &'a PerCpuArray<MapData, u64>,
+    failed: u32,
+}
+
+impl TestRun<'_> {
+    // Runs the program on the packet and checks the action and the increment of the counter (if
+    // any). Returns the packet after the program is run.
+    fn run(
+        &mut self,
+        name: &str,
+        packet: &TcpPacket,
+        expected_action: u32,
+        expected_counter: Option<u32>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let before = counters(self.stats)?;
+        let (action, out) = test_run::test_run_xdp(self.prog_fd, &packet.build())
+            .context("Failed to run the program using `BPF_PROG_TEST_RUN`")?;
+        let after = counters(self.stats)?;
+
+        let mut expected = before;
+        if let Some(counter) = expected_counter {
+            expected[counter as usize] += 1;
+        }
+        if action == expected_action && after == expected {
+            info!("PASSED: {}", name);
+        } else {
+            error!(
+                "FAILED: {} (expected action: {}, returned action: {}, expected counters: {:?}, counters: {:?})",
+                name, expected_action, action, expected, after
+            );
+            self.failed += 1;
+        }
+
+        Ok(out)
+    }
+
+    fn check(&mut self, name: &str, result: Result<(), String>) {
+        match result {
+            Ok(()) => info!("PASSED: {}", name),
+            Err(e) => {
+                error!("FAILED: {} ({})", name, e);
+                self.failed += 1;
+            }
+        }
+    }
+}
+
+// Checks that the SYN-ACK is the reply to the SYN and has the MSS option.
+fn check_syn_ack(syn: &TcpPacket, syn_ack: &TcpPacket) -> Result<(), String> {
+    if syn_ack.src_mac != syn.dst_mac || syn_ack.dst_mac != syn.src_mac {
+        return Err("MAC addresses not swapped".to_string());
+    }
+    if syn_ack.src_addr != syn.dst_addr || syn_ack.dst_addr != syn.src_addr {
+        return Err("IP addresses not swapped".to_string());
+    }
+    if syn_ack.src_port != syn.dst_port || syn_ack.dst_port != syn.src_port {
+        return Err("ports not swapped".to_string());
+    }
+    if syn_ack.flags != TCP_FLAG_SYN | TCP_FLAG_ACK {
+        return Err(format!("flags: {:#04x}", syn_ack.flags));
+    }
+    if syn_ack.ack != syn.seq.wrapping_add(1) {
+        return Err(format!("acknowledgment number: {}", syn_ack.ack));
+    }
+    let mss = match syn_ack.options[..] {
+        [2, 4, hi, lo, ..] => u16::from_be_bytes([hi, lo]),
+        _ => return Err(format!("no MSS option: {:?}", syn_ack.options)),
+    };
+    if mss == 0 || mss > CLIENT_MSS {
+        return Err(format!("MSS: {mss}"));
+    }
+
+    Ok(())
+}
+
+// Runs a handshake from the client to the protected port of the server, a packet of the validated
+// connection, an ACK with a wrong cookie and a SYN to a port that is not protected.
+fn test_handshake(
+    test: &mut TestRun,
+    client: IpAddr,
+    server: IpAddr,
+    port: u16,
+    unprotected: u16,
+) -> anyhow::Result<()> {
+    let family = if client.is_ipv4() { "IPv4" } else { "IPv6" };
+    let syn = TcpPacket {
+        src_mac: CLIENT_MAC,
+        dst_mac: SERVER_MAC,
+        src_addr: client,
+        dst_addr: server,
+        src_port: 40000,
+        dst_port: port,
+        seq: 1_000_000,
+        ack: 0,
+        flags: TCP_FLAG_SYN,
+        options: [[2, 4], CLIENT_MSS.to_be_bytes()].concat(),
+        payload: vec![],
+    };
+
+    let name = format!("{family}: SYN answered with a SYN-ACK");
+    let out = test.run(&name, &syn, XDP_TX, Some(COOKIES_SENT))?;
+    let syn_ack = match TcpPacket::parse(&out) {
+        Ok(syn_ack) => syn_ack,
+        Err(e) => {
+            test.check(&format!("{family}: SYN-ACK parsed"), Err(e));
+            return Ok(());
+        }
+    };
+    test.check(
+        &format!("{family}: SYN-ACK is the reply to the SYN"),
+        check_syn_ack(&syn, &syn_ack),
+    );
+
+    let ack = TcpPacket {
+        seq: syn.seq.wrapping_add(1),
+        ack: syn_ack.seq.wrapping_add(1),
+        flags: TCP_FLAG_ACK,
+        options: vec![],
+        ..syn.clone()
+    };
+    let name = format!("{family}: ACK with the cookie passed");
+    test.run(&name, &ack, XDP_PASS, Some(COOKIES_VALIDATED))?;
+
+    // The acknowledgment number is no longer the cookie, the packet is passed since the connection
+    // was validated.
+    let data = TcpPacket {
+        ack: ack.ack.wrapping_add(100),
+        flags: TCP_FLAG_ACK | TCP_FLAG_PSH,
+        payload: b"GET / HTTP/1.0\r\n\r\n".to_vec(),
+        ..ack.clone()
+    };
+    let name = format!("{family}: Data of the validated connection passed");
+    test.run(&name, &data, XDP_PASS, None)?;
+
+    let bad_ack = TcpPacket {
+        src_port: syn.src_port + 1,
+        ack: 12345,
+        ..ack.clone()
+    };
+    let name = format!("{family}: ACK without a valid cookie dropped");
+    test.run(&name, &bad_ack, XDP_DROP, Some(COOKIES_REJECTED))?;
+
+    let other_syn = TcpPacket {
+        dst_port: unprotected,
+        ..syn.clone()
+    };
+    let name = format!("{family}: SYN to a port that is not protected passed");
+    test.run(&name, &other_syn, XDP_PASS, None)?;
+
+    Ok(())
+}
+
+fn run_test_packets(
+    xdp: &Xdp,
+    stats: &PerCpuArray<MapData, u64>,
+    ports: &[u16],
+) -> anyhow::Result<()> {
+    let port = ports[0];
+    let unprotected = (1..=u16::MAX).find(|port| !ports.contains(port)).unwrap();
+
+    let mut test = TestRun {
+        prog_fd: xdp.fd()?.as_fd(),
+        stats,
+        failed: 0,
+    };
+    test_handshake(
+        &mut test,
+        IpAddr::V4(Ipv4Addr::new(10, 11, 1, 2)),
+        IpAddr::V4(Ipv4Addr::new(10, 11, 1, 1)),
+        port,
+        unprotected,
+    )?;
+    test_handshake(
+        &mut test,
+        IpAddr::V6(Ipv6Addr::new(0xfc00, 0x42de, 0xcafe, 1, 0, 0, 0, 2)),
+        IpAddr::V6(Ipv6Addr::new(0xfc00, 0x42de, 0xcafe, 1, 0, 0, 0, 1)),
+        port,
+        unprotected,
+    )?;
+
+    if test.failed > 0 {
+        return Err(anyhow::Error::msg(format!(
+            "{} checks failed.",
+            test.failed
+        )));
+    }
+    info!("All the checks passed.");
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The `--ports` are set in the `PORTS` map before the program is attached, the counters of the
+// cookies are displayed every `--interval` seconds. The connections are only established with the
+// netfilter `SYNPROXY` rules for the `--ports` (see the README), which complete the handshakes of
+// the ACKs passed by the program.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+    if opts.ports.is_empty() || opts.ports.len() > MAX_PORTS as usize {
+        return Err(anyhow::Error::msg(format!(
+            "Between 1 and {MAX_PORTS} ports should be protected."
+        )));
+    }
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut ports: HashMap<_, u16, u8> = HashMap::try_from(bpf.take_map("PORTS").unwrap())?;
+    for port in &opts.ports {
+        ports.insert(port, 0, 0)?;
+    }
+    let stats = PerCpuArray::try_from(bpf.take_map("SYNCOOKIE_STATS").unwrap())?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+
+    if opts.test_run {
+        return run_test_packets(xdp, &stats, &opts.ports);
+    }
+
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}', protecting the ports: {:?}",
+        program_name, &opts.iface, opts.ports
+    );
+
+    info!("Now waiting for Ctrl-C");
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                print_counters(&stats)?;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
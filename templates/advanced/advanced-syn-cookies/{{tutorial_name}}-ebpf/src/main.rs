@@ -0,0 +1,356 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use core::mem;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::{
+        bpf_tcp_raw_check_syncookie_ipv4, bpf_tcp_raw_check_syncookie_ipv6,
+        bpf_tcp_raw_gen_syncookie_ipv4, bpf_tcp_raw_gen_syncookie_ipv6, bpf_xdp_adjust_tail,
+    },
+    macros::{map, xdp},
+    maps::{HashMap, LruHashMap, PerCpuArray},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr, parse_ip6hdr, parse_iphdr, parse_tcphdr, ptr_at, EthHdr, HdrCursor, Ipv4Hdr,
+    Ipv6Hdr, TcpHdr, ETH_P_IP, ETH_P_IPV6, IPPROTO_TCP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    ConnKey, COOKIES_REJECTED, COOKIES_SENT, COOKIES_VALIDATED, MAX_CONNECTIONS, MAX_PORTS,
+    NUM_COUNTERS,
+};
+
+// The TCP flags, in the lower bits of `TcpHdr::doff_flags` (in host byte order).
+const TCP_FLAG_SYN: u16 = 0x0002;
+const TCP_FLAG_RST: u16 = 0x0004;
+const TCP_FLAG_ACK: u16 = 0x0010;
+
+// Maximum length of a TCP header with the options.
+const TCP_MAX_HDR_LEN: usize = 60;
+
+const TCPOPT_MSS: u8 = 2;
+
+const SYNACK_TTL: u8 = 64;
+const SYNACK_WINDOW: u16 = 65535;
+
+// The TCP header of a SYN-ACK sent by the program, with only the MSS option.
+#[repr(C)]
+struct SynAckTcpHdr {
+    tcp: TcpHdr,
+    mss_option: [u8; 4],
+}
+
+// The TCP ports (in the host byte order) protected by the program, set by the Userspace program.
+#[map]
+static PORTS: HashMap<u16, u8> = HashMap::<u16, u8>::with_max_entries(MAX_PORTS, 0);
+
+// The connections with a validated cookie, their packets are passed without a check.
+#[map]
+static CONNECTIONS: LruHashMap<ConnKey, u8> =
+    LruHashMap::<ConnKey, u8>::with_max_entries(MAX_CONNECTIONS, 0);
+
+#[map]
+static SYNCOOKIE_STATS: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(NUM_COUNTERS, 0);
+
+// The IP header of a packet.
+#[derive(Clone, Copy)]
+enum IpHdr {
+    V4(*mut Ipv4Hdr),
+    V6(*mut Ipv6Hdr),
+}
+
+// The addresses of a packet, in the network byte order.
+#[derive(Clone, Copy)]
+enum IpAddrs {
+    V4(u32, u32),
+    V6([u8; 16], [u8; 16]),
+}
+
+#[inline(always)]
+fn count(counter: u32) {
+    if let Some(count) = SYNCOOKIE_STATS.get_ptr_mut(counter) {
+        unsafe { *count += 1 };
+    }
+}
+
+// Adds the 16 bit words of the `data` (read in the native byte order) to the checksum `sum`.
+#[inline(always)]
+fn csum_add(mut sum: u32, data: *const u8, len: usize) -> u32 {
+    let words = data as *const u16;
+    for i in 0..len / 2 {
+        sum += unsafe { words.add(i).read_unaligned() } as u32;
+    }
+
+    sum
+}
+
+#[inline(always)]
+fn csum_fold(mut sum: u32) -> u16 {
+    sum = (sum & 0xffff) + (sum >> 16);
+    sum = (sum & 0xffff) + (sum >> 16);
+
+    !(sum as u16)
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Answers the SYNs to the protected ports with a SYN-ACK carrying a cookie, without involving
+// the kernel (or keeping any state), so a SYN flood does not fill the SYN queues of the sockets.
+//
+// The ACK from the client completing the handshake carries the cookie back. If the cookie is
+// valid, the ACK is passed to the kernel, where the netfilter `SYNPROXY` target checks the cookie
+// again (the cookies are generated using the secret of the kernel) and opens the connection to the
+// listening socket on behalf of the client. The TCP stack itself would refuse the cookie, as it
+// only accepts the cookies after its own SYN queue has recently overflowed. The ACKs without a
+// valid cookie (and not of a connection validated earlier) are dropped.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let mut key = ConnKey {
+        src_addr: [0; 16],
+        dst_addr: [0; 16],
+        src_port: 0,
+        dst_port: 0,
+    };
+    let iph = match u16::from_be(unsafe { (*eth).h_proto }) {
+        ETH_P_IP => {
+            let Some(iph) = parse_iphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            if unsafe { (*iph).protocol } != IPPROTO_TCP {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            let (saddr, daddr) = unsafe { ((*iph).saddr, (*iph).daddr) };
+            key.src_addr[10] = 0xff;
+            key.src_addr[11] = 0xff;
+            key.src_addr[12..].copy_from_slice(&saddr.to_ne_bytes());
+            key.dst_addr[10] = 0xff;
+            key.dst_addr[11] = 0xff;
+            key.dst_addr[12..].copy_from_slice(&daddr.to_ne_bytes());
+            IpHdr::V4(iph)
+        }
+        ETH_P_IPV6 => {
+            let Some(ip6h) = parse_ip6hdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            // The extension headers are not parsed, such packets are passed.
+            if unsafe { (*ip6h).nexthdr } != IPPROTO_TCP {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            unsafe {
+                key.src_addr = (*ip6h).saddr;
+                key.dst_addr = (*ip6h).daddr;
+            }
+            IpHdr::V6(ip6h)
+        }
+        _ => return Ok(xdp_action::XDP_PASS),
+    };
+
+    let tcp_pos = cursor.pos;
+    let Some(tcph) = parse_tcphdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    let (sport, dport, doff_flags) = unsafe {
+        (
+            u16::from_be((*tcph).source),
+            u16::from_be((*tcph).dest),
+            u16::from_be((*tcph).doff_flags),
+        )
+    };
+    if unsafe { PORTS.get(&dport) }.is_none() {
+        return Ok(xdp_action::XDP_PASS);
+    }
+    key.src_port = sport;
+    key.dst_port = dport;
+
+    let flags = doff_flags & (TCP_FLAG_SYN | TCP_FLAG_ACK | TCP_FLAG_RST);
+    if flags == TCP_FLAG_SYN {
+        let tcp_len = ((doff_flags >> 12) as usize) * 4;
+        return syn_ack(ctx, iph, tcph, tcp_pos, tcp_len);
+    }
+
+    if unsafe { CONNECTIONS.get(&key) }.is_some() {
+        return Ok(xdp_action::XDP_PASS);
+    }
+    // The other packets (eg. the RSTs) of the unknown connections are left to the kernel.
+    if flags != TCP_FLAG_ACK {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    // Returns `0` if the acknowledgment number is a valid cookie (plus one) for the connection.
+    let ret = unsafe {
+        match iph {
+            IpHdr::V4(iph) => bpf_tcp_raw_check_syncookie_ipv4(iph as *mut _, tcph as *mut _),
+            IpHdr::V6(ip6h) => bpf_tcp_raw_check_syncookie_ipv6(ip6h as *mut _, tcph as *mut _),
+        }
+    };
+    if ret != 0 {
+        count(COOKIES_REJECTED);
+        return Ok(xdp_action::XDP_DROP);
+    }
+
+    let _ = CONNECTIONS.insert(&key, &0, 0);
+    count(COOKIES_VALIDATED);
+    debug!(ctx, "Cookie validated, connection from port: {}", sport);
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+// Turns the SYN into a SYN-ACK with the cookie as the sequence number and sends it back.
+//
+// The headers are rewritten in place: the addresses and the ports are swapped, the options of the
+// SYN (and the IPv4 options) are replaced by the MSS option and the packet is resized to the new
+// headers using `bpf_xdp_adjust_tail`.
+#[inline(always)]
+fn syn_ack(
+    ctx: &XdpContext,
+    iph: IpHdr,
+    tcph: *mut TcpHdr,
+    tcp_pos: usize,
+    tcp_len: usize,
+) -> Result<u32, u32> {
+    // The helper reads the MSS option of the SYN. The verifier does not allow passing the header
+    // of a variable length in the packet, so it is copied to the stack first.
+    let mut th = [0u8; TCP_MAX_HDR_LEN];
+    for (i, byte) in th.iter_mut().enumerate() {
+        if i >= tcp_len {
+            break;
+        }
+        let Some(ptr) = ptr_at::<u8>(ctx, &HdrCursor { pos: tcp_pos + i }) else {
+            return Ok(xdp_action::XDP_PASS);
+        };
+        *byte = unsafe { *ptr };
+    }
+
+    // Returns the cookie in the lower 32 bits and the MSS encoded in the cookie in the next 16
+    // bits, or an error (eg. without `CONFIG_SYN_COOKIES`), then the SYN is left to the kernel.
+    let ret = unsafe {
+        let th = th.as_mut_ptr() as *mut _;
+        match iph {
+            IpHdr::V4(iph) => bpf_tcp_raw_gen_syncookie_ipv4(iph as *mut _, th, tcp_len as u32),
+            IpHdr::V6(ip6h) => bpf_tcp_raw_gen_syncookie_ipv6(ip6h as *mut _, th, tcp_len as u32),
+        }
+    };
+    if ret < 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+    let cookie = ret as u32;
+    let mss = (ret >> 32) as u16;
+
+    // All the pointers to the packet are invalid after `bpf_xdp_adjust_tail`, the fields needed
+    // for the SYN-ACK are saved first.
+    let (sport, dport, seq) = unsafe { ((*tcph).source, (*tcph).dest, (*tcph).seq) };
+    let (addrs, ip_len) = match iph {
+        IpHdr::V4(iph) => unsafe {
+            let addrs = IpAddrs::V4((*iph).saddr, (*iph).daddr);
+            (addrs, mem::size_of::<Ipv4Hdr>())
+        },
+        IpHdr::V6(ip6h) => unsafe {
+            let addrs = IpAddrs::V6((*ip6h).saddr, (*ip6h).daddr);
+            (addrs, mem::size_of::<Ipv6Hdr>())
+        },
+    };
+
+    let len = ctx.data_end() - ctx.data();
+    let new_len = mem::size_of::<EthHdr>() + ip_len + mem::size_of::<SynAckTcpHdr>();
+    if unsafe { bpf_xdp_adjust_tail(ctx.ctx, new_len as i32 - len as i32) } != 0 {
+        return Err(xdp_action::XDP_ABORTED);
+    }
+
+    let mut cursor = HdrCursor::new(ctx);
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    unsafe { mem::swap(&mut (*eth).h_dest, &mut (*eth).h_source) };
+
+    // The new IP header (the TTL is reset), and the sum of the pseudo header for the TCP checksum.
+    let tcp_len = mem::size_of::<SynAckTcpHdr>() as u16;
+    let pseudo = (IPPROTO_TCP as u16).to_be() as u32 + tcp_len.to_be() as u32;
+    let sum = match addrs {
+        IpAddrs::V4(saddr, daddr) => {
+            let Some(iph) = ptr_at::<Ipv4Hdr>(ctx, &cursor) else {
+                return Err(xdp_action::XDP_ABORTED);
+            };
+            unsafe {
+                (*iph).version_ihl = 0x45;
+                (*iph).tos = 0;
+                (*iph).tot_len = (mem::size_of::<Ipv4Hdr>() as u16 + tcp_len).to_be();
+                (*iph).id = 0;
+                (*iph).frag_off = 0x4000u16.to_be();
+                (*iph).ttl = SYNACK_TTL;
+                (*iph).protocol = IPPROTO_TCP;
+                (*iph).check = 0;
+                (*iph).saddr = daddr;
+                (*iph).daddr = saddr;
+                (*iph).check = csum_fold(csum_add(0, iph as *const u8, mem::size_of::<Ipv4Hdr>()));
+            }
+            let addrs = [daddr, saddr];
+            csum_add(pseudo, addrs.as_ptr() as *const u8, 8)
+        }
+        IpAddrs::V6(saddr, daddr) => {
+            let Some(ip6h) = ptr_at::<Ipv6Hdr>(ctx, &cursor) else {
+                return Err(xdp_action::XDP_ABORTED);
+            };
+            unsafe {
+                (*ip6h).priority_version = 0x60;
+                (*ip6h).flow_lbl = [0; 3];
+                (*ip6h).payload_len = tcp_len.to_be();
+                (*ip6h).nexthdr = IPPROTO_TCP;
+                (*ip6h).hop_limit = SYNACK_TTL;
+                (*ip6h).saddr = daddr;
+                (*ip6h).daddr = saddr;
+            }
+            let sum = csum_add(pseudo, daddr.as_ptr(), 16);
+            csum_add(sum, saddr.as_ptr(), 16)
+        }
+    };
+    cursor.pos += ip_len;
+
+    let Some(synack) = ptr_at::<SynAckTcpHdr>(ctx, &cursor) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    unsafe {
+        (*synack).tcp.source = dport;
+        (*synack).tcp.dest = sport;
+        (*synack).tcp.seq = cookie.to_be();
+        (*synack).tcp.ack_seq = u32::from_be(seq).wrapping_add(1).to_be();
+        (*synack).tcp.doff_flags = ((6 << 12) | TCP_FLAG_SYN | TCP_FLAG_ACK).to_be();
+        (*synack).tcp.window = SYNACK_WINDOW.to_be();
+        (*synack).tcp.check = 0;
+        (*synack).tcp.urg_ptr = 0;
+        let mss = mss.to_be_bytes();
+        (*synack).mss_option = [TCPOPT_MSS, 4, mss[0], mss[1]];
+        (*synack).tcp.check = csum_fold(csum_add(
+            sum,
+            synack as *const u8,
+            mem::size_of::<SynAckTcpHdr>(),
+        ));
+    }
+
+    count(COOKIES_SENT);
+    debug!(ctx, "SYN-ACK with a cookie sent, MSS: {}", mss);
+
+    Ok(xdp_action::XDP_TX)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
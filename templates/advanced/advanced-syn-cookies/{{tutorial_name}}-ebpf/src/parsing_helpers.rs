@@ -0,0 +1,141 @@
+// Helpers for parsing the packet headers.
+//
+// These are the helpers from the 'packet03-redirecting' tutorial.
+
+use core::mem;
+
+use aya_ebpf::programs::XdpContext;
+
+pub const ETH_P_IP: u16 = 0x0800;
+pub const ETH_P_IPV6: u16 = 0x86DD;
+
+pub const IPPROTO_TCP: u8 = 6;
+
+/// Ethernet Header.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EthHdr {
+    pub h_dest: [u8; 6],
+    pub h_source: [u8; 6],
+    /// Protocol of the next header (in network byte order).
+    pub h_proto: u16,
+}
+
+/// IPv4 Header (without the options).
+#[repr(C)]
+pub struct Ipv4Hdr {
+    /// Version (upper 4 bits) and the Header length in 32 bit words (lower 4 bits).
+    pub version_ihl: u8,
+    pub tos: u8,
+    pub tot_len: u16,
+    pub id: u16,
+    pub frag_off: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub check: u16,
+    pub saddr: u32,
+    pub daddr: u32,
+}
+
+/// IPv6 Header.
+#[repr(C)]
+pub struct Ipv6Hdr {
+    pub priority_version: u8,
+    pub flow_lbl: [u8; 3],
+    pub payload_len: u16,
+    pub nexthdr: u8,
+    pub hop_limit: u8,
+    pub saddr: [u8; 16],
+    pub daddr: [u8; 16],
+}
+
+/// TCP Header (without the options).
+#[repr(C)]
+pub struct TcpHdr {
+    pub source: u16,
+    pub dest: u16,
+    pub seq: u32,
+    pub ack_seq: u32,
+    /// Data offset (upper 4 bits of the first byte) and the flags.
+    pub doff_flags: u16,
+    pub window: u16,
+    pub check: u16,
+    pub urg_ptr: u16,
+}
+
+/// Keeps track of the current parsing position in the packet.
+///
+/// Every successful `parse_*` call advances the cursor to the start of the next header.
+pub struct HdrCursor {
+    /// Address of the current parsing position in the packet.
+    pub pos: usize,
+}
+
+impl HdrCursor {
+    pub fn new(ctx: &XdpContext) -> Self {
+        Self { pos: ctx.data() }
+    }
+}
+
+/// Returns the pointer to a `T` at the cursor, only if the whole `T` lies within the packet.
+#[inline(always)]
+pub fn ptr_at<T>(ctx: &XdpContext, cursor: &HdrCursor) -> Option<*mut T> {
+    let len = mem::size_of::<T>();
+    if cursor.pos + len > ctx.data_end() {
+        return None;
+    }
+
+    Some(cursor.pos as *mut T)
+}
+
+/// Parses the Ethernet header and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_ethhdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*mut EthHdr> {
+    let eth: *mut EthHdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<EthHdr>();
+
+    Some(eth)
+}
+
+/// Parses the IPv4 header (including the options) and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_iphdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*mut Ipv4Hdr> {
+    let iph: *mut Ipv4Hdr = ptr_at(ctx, cursor)?;
+
+    let hdrsize = ((unsafe { (*iph).version_ihl } & 0x0F) as usize) * 4;
+    if hdrsize < mem::size_of::<Ipv4Hdr>() {
+        return None;
+    }
+    if cursor.pos + hdrsize > ctx.data_end() {
+        return None;
+    }
+    cursor.pos += hdrsize;
+
+    Some(iph)
+}
+
+/// Parses the IPv6 header and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_ip6hdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*mut Ipv6Hdr> {
+    let ip6h: *mut Ipv6Hdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<Ipv6Hdr>();
+
+    Some(ip6h)
+}
+
+/// Parses the TCP header (including the options) and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_tcphdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*mut TcpHdr> {
+    let tcph: *mut TcpHdr = ptr_at(ctx, cursor)?;
+
+    let hdrsize = ((u16::from_be(unsafe { (*tcph).doff_flags }) >> 12) as usize) * 4;
+    if hdrsize < mem::size_of::<TcpHdr>() {
+        return None;
+    }
+    if cursor.pos + hdrsize > ctx.data_end() {
+        return None;
+    }
+    cursor.pos += hdrsize;
+
+    Some(tcph)
+}
@@ -0,0 +1,27 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Index of the TCP stage in the `JUMP_TABLE` and the `STAGE_STATS` maps.
+pub const STAGE_TCP: u32 = 0;
+
+/// Index of the UDP stage in the `JUMP_TABLE` and the `STAGE_STATS` maps.
+pub const STAGE_UDP: u32 = 1;
+
+/// Index of the ICMP (and ICMPv6) stage in the `JUMP_TABLE` and the `STAGE_STATS` maps.
+pub const STAGE_ICMP: u32 = 2;
+
+/// Number of stages.
+pub const NUM_STAGES: u32 = 3;
+
+/// Packets and bytes handled by a stage, in the `STAGE_STATS` map.
+#[repr(C)]
+#[cfg_attr(feature = "user", derive(Copy, Debug, Clone, Default))]
+pub struct StageStats {
+    pub pkt_count: u64,
+    pub bytes_count: u64,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for StageStats {}
@@ -0,0 +1,198 @@
+use anyhow::Context;
+
+use aya::maps::{MapData, PerCpuArray, ProgramArray};
+use aya::programs::{ProgramFd, Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{info, warn};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::signal;
+
+use {{ to_snake_case tutorial_name }}_common::{StageStats, STAGE_ICMP, STAGE_TCP, STAGE_UDP};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Names of the stages, with the index of the stage in the `JUMP_TABLE` and the program of the
+// stage set at the start.
+const STAGES: [(&str, u32, &str); 3] = [
+    ("tcp", STAGE_TCP, "tcp"),
+    ("udp", STAGE_UDP, "udp"),
+    ("icmp", STAGE_ICMP, "icmp"),
+];
+
+// Names of the programs that can be set for a stage, the name of the program in the eBPF binary is
+// `{{to_snake_case tutorial_name}}_<name>`.
+const STAGE_PROGRAMS: [&str; 4] = ["tcp", "udp", "icmp", "drop"];
+
+const HELP: &str = "Commands:
+    set <stage> <program>   Set the program of the stage (stages: tcp, udp, icmp, programs: tcp, udp, icmp, drop)
+    clear <stage>           Remove the program of the stage (the packets are passed)
+    stats                   Display the packets and bytes handled by every stage
+    help                    Display this help";
+
+// The programs of the stages, loaded but not attached. The file descriptors of the programs are
+// used for setting the programs in the `JUMP_TABLE`.
+struct StagePrograms {
+    programs: Vec<(&'static str, ProgramFd)>,
+}
+
+impl StagePrograms {
+    fn load(bpf: &mut Ebpf) -> anyhow::Result<Self> {
+        let mut programs = vec![];
+        for name in STAGE_PROGRAMS {
+            let program_name = format!("{{to_snake_case tutorial_name}}_{name}");
+            let xdp: &mut Xdp = bpf
+                .program_mut(&program_name)
+                .with_context(|| format!("Unable to find the program '{program_name}'"))?
+                .try_into()?;
+            xdp.load()?;
+            programs.push((name, xdp.fd()?.try_clone()?));
+        }
+
+        Ok(Self { programs })
+    }
+
+    fn get(&self, name: &str) -> Option<&ProgramFd> {
+        self.programs
+            .iter()
+            .find(|(program, _)| *program == name)
+            .map(|(_, fd)| fd)
+    }
+}
+
+fn stage_index(stage: &str) -> anyhow::Result<u32> {
+    STAGES
+        .iter()
+        .find(|(name, _, _)| *name == stage)
+        .map(|(_, index, _)| *index)
+        .ok_or_else(|| anyhow::Error::msg(format!("Unknown stage: '{stage}'")))
+}
+
+fn print_stats(stage_stats: &PerCpuArray<MapData, StageStats>) -> anyhow::Result<()> {
+    for (name, index, _) in STAGES {
+        let values = stage_stats.get(&index, 0)?;
+        let total = values
+            .iter()
+            .fold(StageStats::default(), |total, v| StageStats {
+                pkt_count: total.pkt_count + v.pkt_count,
+                bytes_count: total.bytes_count + v.bytes_count,
+            });
+        info!(
+            "stage: {:<5} packets: {:>10} bytes: {:>12}",
+            name, total.pkt_count, total.bytes_count
+        );
+    }
+
+    Ok(())
+}
+
+// Runs a command read from the `stdin`. The programs in the `JUMP_TABLE` are updated while the
+// entry program stays attached, the next packet is handled by the new program of the stage.
+fn run_command(
+    line: &str,
+    jump_table: &mut ProgramArray<MapData>,
+    programs: &StagePrograms,
+    stage_stats: &PerCpuArray<MapData, StageStats>,
+) -> anyhow::Result<()> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    match words.as_slice() {
+        ["set", stage, program] => {
+            let index = stage_index(stage)?;
+            let fd = programs
+                .get(program)
+                .ok_or_else(|| anyhow::Error::msg(format!("Unknown program: '{program}'")))?;
+            jump_table.set(index, fd, 0)?;
+            info!("Stage '{}' set to the program '{}'", stage, program);
+        }
+        ["clear", stage] => {
+            let index = stage_index(stage)?;
+            jump_table.clear_index(&index)?;
+            info!("Stage '{}' cleared", stage);
+        }
+        ["stats"] => print_stats(stage_stats)?,
+        [] => {}
+        _ => println!("{HELP}"),
+    }
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// Only the entry program is attached to the interface, the programs of the stages are loaded and
+// set in the `JUMP_TABLE`. The programs of the stages can then be changed using the commands read
+// from the `stdin`, without detaching the entry program.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut jump_table = ProgramArray::try_from(bpf.take_map("JUMP_TABLE").unwrap())?;
+    let stage_stats = PerCpuArray::try_from(bpf.take_map("STAGE_STATS").unwrap())?;
+
+    let programs = StagePrograms::load(&mut bpf)?;
+    for (_, index, program) in STAGES {
+        jump_table.set(index, programs.get(program).unwrap(), 0)?;
+    }
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' attached to '{}'! Now waiting for commands or Ctrl-C",
+        program_name, &opts.iface
+    );
+    println!("{HELP}");
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        tokio::select! {
+            line = lines.next_line() => {
+                let Some(line) = line? else {
+                    // `stdin` is closed, keep running until Ctrl-C.
+                    signal::ctrl_c().await?;
+                    break;
+                };
+                if let Err(e) = run_command(&line, &mut jump_table, &programs, &stage_stats) {
+                    warn!("{:#}", e);
+                }
+            }
+            _ = signal::ctrl_c() => {
+                break;
+            }
+        }
+    }
+    info!("Exiting...");
+
+    Ok(())
+}
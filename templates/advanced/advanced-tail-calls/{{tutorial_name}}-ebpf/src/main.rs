@@ -0,0 +1,168 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    macros::{map, xdp},
+    maps::{PerCpuArray, ProgramArray},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr_vlan, parse_icmphdr_common, parse_ip6hdr, parse_iphdr, parse_tcphdr,
+    parse_udphdr, HdrCursor, ETH_P_IP, ETH_P_IPV6, IPPROTO_ICMP, IPPROTO_ICMPV6, IPPROTO_TCP,
+    IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    StageStats, NUM_STAGES, STAGE_ICMP, STAGE_TCP, STAGE_UDP,
+};
+
+// Maximum offset of the L4 header from the start of the packet (Ethernet, 2 VLAN headers and an
+// IPv4 header with options). The bound is required by the verifier, since the offset is read from
+// a map in the stages.
+const MAX_L4_OFFSET: u32 = 128;
+
+// Programs of the stages, indexed by the stage. The Userspace program sets (and swaps) the
+// programs in this map.
+#[map]
+static JUMP_TABLE: ProgramArray = ProgramArray::with_max_entries(NUM_STAGES, 0);
+
+// Result of the parsing in the entry program, passed on to the stages. The entry program and the
+// stage run on the same CPU one after the other, so a Per CPU map is safe to use.
+#[repr(C)]
+struct ParseState {
+    stage: u32,
+    l4_offset: u32,
+}
+
+#[map]
+static PARSE_STATE: PerCpuArray<ParseState> = PerCpuArray::<ParseState>::with_max_entries(1, 0);
+
+#[map]
+static STAGE_STATS: PerCpuArray<StageStats> =
+    PerCpuArray::<StageStats>::with_max_entries(NUM_STAGES, 0);
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// The entry program, parses the Ethernet and the IP headers and tail calls the program of the
+// stage for the L4 protocol.
+//
+// A successful tail call does not return, the action returned by the stage is the action for the
+// packet. If there is no program for the stage in the `JUMP_TABLE`, the tail call fails and the
+// packet is passed.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some((_, eth_type)) = parse_ethhdr_vlan(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let ip_type = match eth_type {
+        ETH_P_IP => parse_iphdr(ctx, &mut cursor).map(|iph| unsafe { (*iph).protocol }),
+        ETH_P_IPV6 => parse_ip6hdr(ctx, &mut cursor).map(|ip6h| unsafe { (*ip6h).nexthdr }),
+        _ => None,
+    };
+
+    let stage = match ip_type {
+        Some(IPPROTO_TCP) => STAGE_TCP,
+        Some(IPPROTO_UDP) => STAGE_UDP,
+        Some(IPPROTO_ICMP) | Some(IPPROTO_ICMPV6) => STAGE_ICMP,
+        _ => return Ok(xdp_action::XDP_PASS),
+    };
+
+    let Some(state) = PARSE_STATE.get_ptr_mut(0) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+    unsafe {
+        (*state).stage = stage;
+        (*state).l4_offset = (cursor.pos - ctx.data()) as u32;
+    }
+
+    let _ = unsafe { JUMP_TABLE.tail_call(ctx, stage) };
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+// Returns the cursor at the L4 header, from the state saved by the entry program, and counts the
+// packet for the stage.
+#[inline(always)]
+fn stage_cursor(ctx: &XdpContext) -> Option<HdrCursor> {
+    let state = PARSE_STATE.get(0)?;
+    if state.l4_offset > MAX_L4_OFFSET {
+        return None;
+    }
+
+    if let Some(stats) = STAGE_STATS.get_ptr_mut(state.stage) {
+        unsafe {
+            (*stats).pkt_count += 1;
+            (*stats).bytes_count += (ctx.data_end() - ctx.data()) as u64;
+        }
+    }
+
+    Some(HdrCursor {
+        pos: ctx.data() + state.l4_offset as usize,
+    })
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_tcp(ctx: XdpContext) -> u32 {
+    let Some(mut cursor) = stage_cursor(&ctx) else {
+        return xdp_action::XDP_PASS;
+    };
+    if let Some(tcph) = parse_tcphdr(&ctx, &mut cursor) {
+        let (source, dest) = unsafe { (u16::from_be((*tcph).source), u16::from_be((*tcph).dest)) };
+        debug!(&ctx, "TCP stage: {} -> {}", source, dest);
+    }
+
+    xdp_action::XDP_PASS
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_udp(ctx: XdpContext) -> u32 {
+    let Some(mut cursor) = stage_cursor(&ctx) else {
+        return xdp_action::XDP_PASS;
+    };
+    if let Some(udph) = parse_udphdr(&ctx, &mut cursor) {
+        let (source, dest) = unsafe { (u16::from_be((*udph).source), u16::from_be((*udph).dest)) };
+        debug!(&ctx, "UDP stage: {} -> {}", source, dest);
+    }
+
+    xdp_action::XDP_PASS
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_icmp(ctx: XdpContext) -> u32 {
+    let Some(mut cursor) = stage_cursor(&ctx) else {
+        return xdp_action::XDP_PASS;
+    };
+    if let Some(icmph) = parse_icmphdr_common(&ctx, &mut cursor) {
+        let (type_, code) = unsafe { ((*icmph).type_, (*icmph).code) };
+        debug!(&ctx, "ICMP stage: type: {}, code: {}", type_, code);
+    }
+
+    xdp_action::XDP_PASS
+}
+
+// A stage that drops all the packets, the Userspace program can put this program in place of any
+// of the stages above.
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_drop(ctx: XdpContext) -> u32 {
+    let _ = stage_cursor(&ctx);
+
+    xdp_action::XDP_DROP
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,28 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Packets that are not classified (XDP) or that have no metadata (TC).
+pub const CLASS_NONE: u32 = 0;
+
+/// TCP packets.
+pub const CLASS_TCP: u32 = 1;
+
+/// UDP packets.
+pub const CLASS_UDP: u32 = 2;
+
+/// ICMP (and ICMPv6) packets.
+pub const CLASS_ICMP: u32 = 3;
+
+/// Number of classes, the size of the `XDP_CLASS_STATS` and the `TC_MARK_STATS` maps.
+pub const NUM_CLASSES: u32 = 4;
+
+/// Metadata put in front of the packet by the XDP program and read by the TC program.
+///
+/// The size of the metadata must be a multiple of 4 bytes.
+#[repr(C)]
+pub struct MetaInfo {
+    /// The class of the packet, which is set as the `skb->mark` by the TC program.
+    pub mark: u32,
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use anyhow::Context;
+
+use aya::maps::{MapData, PerCpuArray};
+use aya::programs::{tc, SchedClassifier, TcAttachType, Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::process::Command;
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::{CLASS_ICMP, NUM_CLASSES};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Interval (in seconds) between two successive displays of the counters.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Check that the marks are set, by sending `CHECK` pings from inside the test environment of
+    /// the interface (created using the `testenv` scripts) and exit.
+    #[clap(long)]
+    check: Option<u32>,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Names of the classes, the index in this array is the class (and the mark set by the TC program).
+const CLASS_NAMES: [&str; NUM_CLASSES as usize] = ["none", "tcp", "udp", "icmp"];
+
+// Returns the counters for all the classes, summed for all the CPUs.
+fn class_counts(stats: &PerCpuArray<MapData, u64>) -> anyhow::Result<Vec<u64>> {
+    let mut counts = vec![];
+    for class in 0..NUM_CLASSES {
+        counts.push(stats.get(&class, 0)?.iter().sum());
+    }
+
+    Ok(counts)
+}
+
+fn print_counts(
+    xdp_stats: &PerCpuArray<MapData, u64>,
+    tc_stats: &PerCpuArray<MapData, u64>,
+) -> anyhow::Result<()> {
+    let xdp_counts = class_counts(xdp_stats)?;
+    let tc_counts = class_counts(tc_stats)?;
+    for (class, name) in CLASS_NAMES.iter().enumerate() {
+        info!(
+            "class: {:<5} XDP classified: {:>10} TC marked: {:>10}",
+            name, xdp_counts[class], tc_counts[class]
+        );
+    }
+
+    Ok(())
+}
+
+// Sends the pings from inside the test environment and checks that the ICMP packets classified
+// by the XDP program are all marked by the TC program.
+//
+// The `testenv` scripts create the namespace with the same name as the interface, with the
+// `veth0` interface inside. The pings are sent to the all nodes multicast address, so that the
+// address of the interface is not needed.
+async fn check_marks(
+    iface: &str,
+    count: u32,
+    xdp_stats: &PerCpuArray<MapData, u64>,
+    tc_stats: &PerCpuArray<MapData, u64>,
+) -> anyhow::Result<()> {
+    let xdp_before = class_counts(xdp_stats)?[CLASS_ICMP as usize];
+    let tc_before = class_counts(tc_stats)?[CLASS_ICMP as usize];
+
+    let status = Command::new("ip")
+        .args([
+            "netns", "exec", iface, "ping", "-6", "-q", "-i", "0.2", "-I", "veth0",
+        ])
+        .args(["-c", &count.to_string(), "ff02::1"])
+        .status()
+        .await
+        .context("Failed to run 'ping' in the test environment")?;
+    if !status.success() {
+        warn!("'ping' exited with: {}", status);
+    }
+    // Give the last packets the time to reach the TC program.
+    time::sleep(Duration::from_millis(500)).await;
+
+    let xdp_icmp = class_counts(xdp_stats)?[CLASS_ICMP as usize] - xdp_before;
+    let tc_icmp = class_counts(tc_stats)?[CLASS_ICMP as usize] - tc_before;
+    info!(
+        "ICMP packets classified by XDP: {}, marked by TC: {}",
+        xdp_icmp, tc_icmp
+    );
+
+    if tc_icmp < count as u64 || tc_icmp != xdp_icmp {
+        error!("FAILED: Not all the ICMP packets were marked.");
+        return Err(anyhow::Error::msg("Mark check failed."));
+    }
+    info!("PASSED: All the ICMP packets were marked.");
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the eBPF binary in the kernel
+// and attaching both the XDP program and the TC classifier to a particular network interface.
+//
+// The TC classifier is attached to the ingress of the `clsact` qdisc of the interface, which is
+// added if it does not exist. The counters of the classified and the marked packets are displayed
+// every `--interval` seconds.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let xdp_stats = PerCpuArray::try_from(bpf.take_map("XDP_CLASS_STATS").unwrap())?;
+    let tc_stats = PerCpuArray::try_from(bpf.take_map("TC_MARK_STATS").unwrap())?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    // The error is ignored, since the qdisc may already exist.
+    if let Err(e) = tc::qdisc_add_clsact(&opts.iface) {
+        warn!(
+            "Failed to add the 'clsact' qdisc (it may already exist): {}",
+            e
+        );
+    }
+    let tc_program_name = "{{to_snake_case tutorial_name}}_tc";
+    let classifier: &mut SchedClassifier = bpf
+        .program_mut(tc_program_name)
+        .with_context(|| format!("Unable to find the program '{tc_program_name}'"))?
+        .try_into()?;
+    classifier.load()?;
+    let _tc_linkid = classifier
+        .attach(&opts.iface, TcAttachType::Ingress)
+        .context("Failed to attach the TC classifier to the ingress of the interface")?;
+
+    if let Err(e) = EbpfLogger::init(&mut bpf) {
+        // This can happen if you remove all log statements from your eBPF program.
+        warn!("failed to initialize eBPF logger: {}", e);
+    }
+
+    info!(
+        "XDP Program '{}' and TC Program '{}' attached to '{}'!",
+        program_name, tc_program_name, &opts.iface
+    );
+
+    if let Some(count) = opts.check {
+        return check_marks(&opts.iface, count, &xdp_stats, &tc_stats).await;
+    }
+
+    info!("Now waiting for Ctrl-C");
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                print_counts(&xdp_stats, &tc_stats)?;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
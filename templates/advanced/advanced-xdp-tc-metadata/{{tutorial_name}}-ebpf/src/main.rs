@@ -0,0 +1,126 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+
+use core::mem;
+
+use aya_ebpf::{
+    bindings::{xdp_action, TC_ACT_OK},
+    helpers::bpf_xdp_adjust_meta,
+    macros::{classifier, map, xdp},
+    maps::PerCpuArray,
+    programs::{TcContext, XdpContext},
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr_vlan, parse_ip6hdr, parse_iphdr, HdrCursor, ETH_P_IP, ETH_P_IPV6, IPPROTO_ICMP,
+    IPPROTO_ICMPV6, IPPROTO_TCP, IPPROTO_UDP,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{
+    MetaInfo, CLASS_ICMP, CLASS_NONE, CLASS_TCP, CLASS_UDP, NUM_CLASSES,
+};
+
+// Packets classified by the XDP program, indexed by the class.
+#[map]
+static XDP_CLASS_STATS: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(NUM_CLASSES, 0);
+
+// Packets marked by the TC program, indexed by the mark (`CLASS_NONE` for the packets without the
+// metadata).
+#[map]
+static TC_MARK_STATS: PerCpuArray<u64> = PerCpuArray::<u64>::with_max_entries(NUM_CLASSES, 0);
+
+#[inline(always)]
+fn count(stats: &PerCpuArray<u64>, index: u32) {
+    if let Some(count) = stats.get_ptr_mut(index) {
+        unsafe { *count += 1 };
+    }
+}
+
+// Returns the class of the packet from the L4 protocol.
+#[inline(always)]
+fn classify(ctx: &XdpContext) -> u32 {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some((_, eth_type)) = parse_ethhdr_vlan(ctx, &mut cursor) else {
+        return CLASS_NONE;
+    };
+
+    let ip_type = match eth_type {
+        ETH_P_IP => parse_iphdr(ctx, &mut cursor).map(|iph| unsafe { (*iph).protocol }),
+        ETH_P_IPV6 => parse_ip6hdr(ctx, &mut cursor).map(|ip6h| unsafe { (*ip6h).nexthdr }),
+        _ => None,
+    };
+
+    match ip_type {
+        Some(IPPROTO_TCP) => CLASS_TCP,
+        Some(IPPROTO_UDP) => CLASS_UDP,
+        Some(IPPROTO_ICMP) | Some(IPPROTO_ICMPV6) => CLASS_ICMP,
+        _ => CLASS_NONE,
+    }
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Classifies the packet and puts the class in the metadata area in front of the packet, using
+// `bpf_xdp_adjust_meta`. The metadata is kept by the kernel when the `sk_buff` is built for the
+// packet, so that it can be read by the TC program.
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let class = classify(ctx);
+    if class == CLASS_NONE {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    // A negative delta grows the metadata area. This fails if the driver does not support the
+    // metadata.
+    if unsafe { bpf_xdp_adjust_meta(ctx.ctx, -(mem::size_of::<MetaInfo>() as i32)) } != 0 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    // After adjusting the metadata, the pointers have to be read again and the bounds checking of
+    // the metadata is done against the start of the packet (`data`).
+    let meta = ctx.metadata();
+    if meta + mem::size_of::<MetaInfo>() > ctx.data() {
+        return Ok(xdp_action::XDP_PASS);
+    }
+    unsafe { (*(meta as *mut MetaInfo)).mark = class };
+    count(&XDP_CLASS_STATS, class);
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+#[classifier]
+pub fn {{to_snake_case tutorial_name}}_tc(ctx: TcContext) -> i32 {
+    try_{{to_snake_case tutorial_name}}_tc(&ctx).unwrap_or(TC_ACT_OK as i32)
+}
+
+// Reads the metadata put in front of the packet by the XDP program and sets the class as the
+// `skb->mark` of the packet.
+fn try_{{to_snake_case tutorial_name}}_tc(ctx: &TcContext) -> Result<i32, i32> {
+    let skb = ctx.skb.skb;
+    let (meta, data) = unsafe { ((*skb).data_meta as usize, (*skb).data as usize) };
+    if meta + mem::size_of::<MetaInfo>() > data {
+        count(&TC_MARK_STATS, CLASS_NONE);
+        return Ok(TC_ACT_OK as i32);
+    }
+
+    let mark = unsafe { (*(meta as *const MetaInfo)).mark };
+    unsafe { (*skb).mark = mark };
+    count(&TC_MARK_STATS, mark);
+    debug!(ctx, "Packet marked: {}", mark);
+
+    Ok(TC_ACT_OK as i32)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
 
@@ -8,6 +8,7 @@ use aya::Ebpf;
 use aya_log::EbpfLogger;
 
 use clap::{ValueEnum, Parser};
+use serde::Serialize;
 use tokio::{signal, time};
 
 use {{ to_snake_case tutorial_name }}_common::StatsRecord;
@@ -25,6 +26,23 @@ struct Opt {
 
     #[clap(long)]
     release: bool,
+
+    /// Format in which the stats are displayed.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum OutputFormat {
+    #[default]
+    /// Log lines (on `stderr`)
+    Text,
+
+    /// One JSON object for every sample (on `stdout`)
+    Json,
+
+    /// Comma separated values, one line for every sample (on `stdout`)
+    Csv,
 }
 
 #[derive(Debug, Clone, Default, ValueEnum)]
@@ -43,9 +61,81 @@ fn action_info_from_opts(opts_action: &XdpAction) -> (&str, u32, &str) {
         XdpAction::Drop => ("drop", 1u32, "{{to_snake_case tutorial_name}}_drop_packet_stats"),
     }
 }
-async fn print_stats(stats_array: &Array<&MapData, StatsRecord>, action: u32, action_name: &str) {
-    let stats = stats_array.get(&action, 0);
-    log::info!("Packet Count for action '{}' : {}", action_name, stats.unwrap().pkt_count);
+
+// Counters for an action.
+#[derive(Debug, Serialize)]
+struct StatsCounters {
+    packets: u64,
+    bytes: u64,
+}
+
+// Stats of an action in a sample. This is the record that is emitted for the `json` and `csv`
+// output formats. The `Array` is shared by all the CPUs, so there are only 'total' counters.
+#[derive(Debug, Serialize)]
+struct ActionStats<'a> {
+    /// Sequence number of the sample, starting at 1.
+    sample: u64,
+    /// Time of the sample as seconds since the UNIX Epoch.
+    timestamp: f64,
+    iface: &'a str,
+    action: &'a str,
+    total: StatsCounters,
+}
+
+const CSV_HEADER: &str = "sample,timestamp,iface,action,cpu,packets,bytes";
+
+// Displays the stats in the given output format. The `text` format uses the logger (`stderr`),
+// while the `json` and `csv` formats are written to the `stdout`, so that the output can be
+// consumed by other tools without having to parse the log lines.
+async fn print_stats(
+    stats_array: &Array<&MapData, StatsRecord>,
+    action: u32,
+    action_name: &str,
+    iface: &str,
+    sample: u64,
+    output: OutputFormat,
+) -> anyhow::Result<()> {
+    let stats = stats_array.get(&action, 0)?;
+    let action_stats = ActionStats {
+        sample,
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64(),
+        iface,
+        action: action_name,
+        total: StatsCounters {
+            packets: stats.pkt_count,
+            bytes: stats.bytes_count,
+        },
+    };
+
+    match output {
+        OutputFormat::Text => {
+            log::info!(
+                "Action '{}': Packet Count: {}, Bytes Count: {}",
+                action_stats.action,
+                action_stats.total.packets,
+                action_stats.total.bytes
+            );
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string(&action_stats)?);
+        }
+        OutputFormat::Csv => {
+            println!(
+                "{},{:.6},{},{},total,{},{}",
+                action_stats.sample,
+                action_stats.timestamp,
+                action_stats.iface,
+                action_stats.action,
+                action_stats.total.packets,
+                action_stats.total.bytes
+            );
+        }
+    }
+
+    Ok(())
 }
 
 // This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
@@ -91,13 +181,18 @@ async fn main() -> Result<(), anyhow::Error> {
 
         let mut stats_poller_interval = time::interval(Duration::from_secs(2));
 
+        if let OutputFormat::Csv = opts.output {
+            println!("{CSV_HEADER}");
+        }
 
+        let mut sample = 0;
         loop {
             tokio::select! {
                 _ = stats_poller_interval.tick() => {
                     log::info!("tick!");
+                    sample += 1;
                     let stats_array = Array::try_from(bpf.map("STATS_ARRAY").unwrap()).unwrap();
-                    print_stats(&stats_array, action, action_name).await;
+                    print_stats(&stats_array, action, action_name, &opts.iface, sample, opts.output).await?;
                 }
                 _ = signal::ctrl_c() => {
                     log::info!("Exiting...");
@@ -39,10 +39,15 @@ pub fn {{to_snake_case tutorial_name}}_drop_packet_stats(ctx: XdpContext) -> u32
 #[inline(always)]
 fn try_{{to_snake_case tutorial_name}}_packet_stats(ctx: XdpContext, action: u32) -> Result<u32, u32> {
     debug!(&ctx, "Received a packet.");
+
+    // `data` and `data_end` are the start and the end of the packet in the memory.
+    let bytes = (ctx.data_end() - ctx.data()) as u64;
+
     let record = STATS_ARRAY.get_ptr_mut(action);
     if let Some(record) = record {
         let _ = unsafe {
             atomic_xadd_acquire(&mut (*record).pkt_count, 1);
+            atomic_xadd_acquire(&mut (*record).bytes_count, bytes);
         };
         Ok(action)
     } else {
@@ -5,12 +5,16 @@
 
 /// Structure that maintains the Packet Statistics.
 ///
-/// This structure will be shared by the Userspace and eBPF code.
+/// This structure will be shared by the Userspace and eBPF code, hence the `#[repr(C)]`, so that
+/// the layout of the structure is same for both.
+#[repr(C)]
 #[cfg_attr(feature = "user", derive(Copy, Debug, Clone))]
 pub struct StatsRecord {
     /// Number of Packets for a given `xdp_action`.
-    pub pkt_count: u32,
-    // TODO: Add bytes_count
+    pub pkt_count: u64,
+
+    /// Number of Bytes in the Packets for a given `xdp_action`.
+    pub bytes_count: u64,
 }
 
 #[cfg(feature = "user")]
@@ -1,4 +1,6 @@
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Context;
 
@@ -7,6 +9,14 @@ use aya::programs::{links::FdLink, Xdp, XdpFlags};
 use aya::EbpfLoader;
 
 use clap::{Parser, ValueEnum};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::widgets::{Block, Row, Sparkline, Table, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::{signal, time};
 
 use {{to_snake_case tutorial_name}}_common::StatsRecord;
@@ -19,6 +29,12 @@ enum CliCommand {
     /// Stats: Loads a given Map and
     Stats(StatsOptions),
 
+    /// Export: Serves the stats from the pinned Map as Prometheus metrics
+    Export(ExportOptions),
+
+    /// Top: Displays the stats from the pinned Maps in a terminal dashboard
+    Top(TopOptions),
+
     // TODO: Add List and Unpin Commands
 }
 
@@ -138,10 +154,6 @@ fn pin_program_and_maps(opts: PinOptions) -> Result<(), anyhow::Error> {
 // Handling of the 'stats' command.
 #[derive(Debug, Parser)]
 struct StatsOptions {
-    /// Action for which the program is attached.
-    #[clap(short, long)]
-    action: XdpAction,
-
     /// Interface name to which the program is attached.
     #[clap(short, long, default_value = "lo")]
     iface: String,
@@ -149,15 +161,70 @@ struct StatsOptions {
     /// Name of the 'tutorial' to search Pinned Maps in `/sys/fs/bpf`
     #[clap(short, long, default_value = "{{tutorial_name}}")]
     name: String,
+
+    /// Interval (in seconds) between two successive samples of the stats.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    /// Display the stats for individual CPUs in addition to the totals.
+    #[clap(long)]
+    per_cpu: bool,
+
+    /// Format in which the stats are displayed.
+    #[clap(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
 }
 
-async fn stats(opts: StatsOptions) -> anyhow::Result<()> {
-    let mut stats_poller_interval = time::interval(Duration::from_secs(2));
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum OutputFormat {
+    #[default]
+    /// Log lines for every action (on `stderr`)
+    Text,
 
-    let map_pin_path = format!(
-        "/sys/fs/bpf/{}/{}/maps/PINNED_PERCPU_ARRAY",
-        opts.iface, opts.name
-    );
+    /// One JSON object per action for every sample (on `stdout`)
+    Json,
+
+    /// Comma separated values, one line per CPU and the total for every action (on `stdout`)
+    Csv,
+}
+
+// Names of the XDP Actions. The index in this array is the value of the action, which is also the
+// key used for the action in the stats map.
+const XDP_ACTION_NAMES: [&str; 5] = ["aborted", "drop", "pass", "tx", "redirect"];
+
+// Values of all the per CPU records for all the actions read from the map at a given instant.
+//
+// The values in the map are cumulative counters, so to compute the rates we need two such
+// samples and the time elapsed between them.
+struct StatsSample {
+    timestamp: Instant,
+    time: SystemTime,
+    records: Vec<Vec<StatsRecord>>,
+}
+
+fn collect_stats(stats_array: &PerCpuArray<MapData, StatsRecord>) -> anyhow::Result<StatsSample> {
+    let mut records = vec![];
+    for action in 0..XDP_ACTION_NAMES.len() as u32 {
+        let values = stats_array
+            .get(&action, 0)
+            .with_context(|| format!("Failed to read stats for action: {action}"))?;
+        records.push(values.iter().copied().collect());
+    }
+
+    Ok(StatsSample {
+        timestamp: Instant::now(),
+        time: SystemTime::now(),
+        records,
+    })
+}
+
+// Opens the `PINNED_PERCPU_ARRAY` map pinned by the 'pin' command for the given interface and
+// tutorial.
+fn open_pinned_stats_array(
+    iface: &str,
+    name: &str,
+) -> anyhow::Result<PerCpuArray<MapData, StatsRecord>> {
+    let map_pin_path = format!("/sys/fs/bpf/{}/{}/maps/PINNED_PERCPU_ARRAY", iface, name);
 
     if !std::path::Path::new(&map_pin_path).exists() {
         return Err(anyhow::Error::msg(
@@ -165,19 +232,39 @@ async fn stats(opts: StatsOptions) -> anyhow::Result<()> {
                 ));
     }
 
-
-    let map_data = MapData::from_pin(map_pin_path).unwrap();
+    let map_data = MapData::from_pin(map_pin_path)?;
     let map = Map::PerCpuArray(map_data);
-    let stats_array = map.try_into().unwrap();
 
-    let (action_name, action_val, _) = action_info_from_opts(&opts.action);
+    Ok(map.try_into()?)
+}
+
+async fn stats(opts: StatsOptions) -> anyhow::Result<()> {
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Stats interval should be at least 1 second."));
+    }
+
+    let stats_array = open_pinned_stats_array(&opts.iface, &opts.name)?;
+
+    // The first tick of a `tokio::time::Interval` completes immediately, we start the interval
+    // after one period, so that there is always a previous sample to compute the rates.
+    let period = Duration::from_secs(opts.interval);
+    let mut stats_poller_interval = time::interval_at(time::Instant::now() + period, period);
+
+    if let OutputFormat::Csv = opts.output {
+        println!("{CSV_HEADER}");
+    }
+
+    let mut prev = collect_stats(&stats_array)?;
+    let mut sample = 0;
 
     loop {
         tokio::select! {
             _ = stats_poller_interval.tick() => {
-                log::info!("tick!");
-                // TODO: Right now just using XDP_PASS, make it a proper action
-                print_stats_for_action(&stats_array, action_val, action_name).await;
+                let curr = collect_stats(&stats_array)?;
+                sample += 1;
+                let stats = action_stats(&prev, &curr, &opts.iface, sample);
+                print_stats(&stats, opts.per_cpu, opts.output)?;
+                prev = curr;
             }
             _ = signal::ctrl_c() => {
                 log::info!("Exiting...");
@@ -189,23 +276,573 @@ async fn stats(opts: StatsOptions) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn print_stats_for_action(
+// Counters (and the rates computed from them) for an action, either for a single CPU or the
+// total across all the CPUs.
+#[derive(Debug, Default, Serialize)]
+struct StatsCounters {
+    packets: u64,
+    bytes: u64,
+    packets_per_sec: f64,
+    bytes_per_sec: f64,
+}
+
+// Stats of a single action in a sample. This is the record that is emitted for the `json` and
+// `csv` output formats, the records of all the actions in a sample have the same `sample` and
+// `timestamp`.
+#[derive(Debug, Serialize)]
+struct ActionStats<'a> {
+    /// Sequence number of the sample, starting at 1.
+    sample: u64,
+    /// Time of the sample as seconds since the UNIX Epoch.
+    timestamp: f64,
+    iface: &'a str,
+    action: &'a str,
+    period: f64,
+    cpus: Vec<StatsCounters>,
+    total: StatsCounters,
+}
+
+// Computes the stats for all the actions, rates are computed from the difference between the
+// counters in the current and the previous sample.
+fn action_stats<'a>(
+    prev: &StatsSample,
+    curr: &StatsSample,
+    iface: &'a str,
+    sample: u64,
+) -> Vec<ActionStats<'a>> {
+    let period = curr
+        .timestamp
+        .duration_since(prev.timestamp)
+        .as_secs_f64();
+    let timestamp = curr
+        .time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    let mut stats = vec![];
+    for (action, action_name) in XDP_ACTION_NAMES.iter().enumerate() {
+        let (prev_values, curr_values) = (&prev.records[action], &curr.records[action]);
+
+        let mut cpus = vec![];
+        let mut total = StatsCounters::default();
+        for (p, c) in prev_values.iter().zip(curr_values.iter()) {
+            // Counters are cumulative and may wrap around.
+            let packets_delta = c.pkt_count.wrapping_sub(p.pkt_count);
+            let bytes_delta = c.bytes_count.wrapping_sub(p.bytes_count);
+            let counters = StatsCounters {
+                packets: c.pkt_count,
+                bytes: c.bytes_count,
+                packets_per_sec: packets_delta as f64 / period,
+                bytes_per_sec: bytes_delta as f64 / period,
+            };
+
+            total.packets += counters.packets;
+            total.bytes += counters.bytes;
+            total.packets_per_sec += counters.packets_per_sec;
+            total.bytes_per_sec += counters.bytes_per_sec;
+            cpus.push(counters);
+        }
+
+        stats.push(ActionStats {
+            sample,
+            timestamp,
+            iface,
+            action: action_name,
+            period,
+            cpus,
+            total,
+        });
+    }
+
+    stats
+}
+
+const CSV_HEADER: &str =
+    "sample,timestamp,iface,action,cpu,packets,bytes,packets_per_sec,bytes_per_sec";
+
+fn csv_counters(counters: &StatsCounters) -> String {
+    format!(
+        "{},{},{:.2},{:.2}",
+        counters.packets, counters.bytes, counters.packets_per_sec, counters.bytes_per_sec
+    )
+}
+
+// Displays the stats in the given output format. The `text` format uses the logger (`stderr`),
+// while the `json` and `csv` formats are written to the `stdout`, so that the output can be
+// consumed by other tools without having to parse the log lines.
+fn print_stats(stats: &[ActionStats], per_cpu: bool, output: OutputFormat) -> anyhow::Result<()> {
+    for action_stats in stats {
+        match output {
+            OutputFormat::Text => {
+                if per_cpu {
+                    for (cpu, counters) in action_stats.cpus.iter().enumerate() {
+                        log::info!(
+                            "    CPU: {:>3}, Action: {:<8}, Packets: {:>12} ({:>12.2} pkts/s), Bytes: {:>14} ({:>14.2} bytes/s)",
+                            cpu,
+                            action_stats.action,
+                            counters.packets,
+                            counters.packets_per_sec,
+                            counters.bytes,
+                            counters.bytes_per_sec,
+                        );
+                    }
+                }
+                log::info!(
+                    "Action: {:<8}, Packets: {:>12} ({:>12.2} pkts/s), Bytes: {:>14} ({:>14.2} bytes/s), period: {:.3}s",
+                    action_stats.action,
+                    action_stats.total.packets,
+                    action_stats.total.packets_per_sec,
+                    action_stats.total.bytes,
+                    action_stats.total.bytes_per_sec,
+                    action_stats.period,
+                );
+            }
+            OutputFormat::Json => {
+                println!("{}", serde_json::to_string(action_stats)?);
+            }
+            OutputFormat::Csv => {
+                let prefix = format!(
+                    "{},{:.6},{},{}",
+                    action_stats.sample,
+                    action_stats.timestamp,
+                    action_stats.iface,
+                    action_stats.action
+                );
+                if per_cpu {
+                    for (cpu, counters) in action_stats.cpus.iter().enumerate() {
+                        println!("{},{},{}", prefix, cpu, csv_counters(counters));
+                    }
+                }
+                println!("{},total,{}", prefix, csv_counters(&action_stats.total));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Handling of the 'export' command.
+#[derive(Debug, Parser)]
+struct ExportOptions {
+    /// Interface name to which the program is attached.
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    /// Name of the 'tutorial' to search Pinned Maps in `/sys/fs/bpf`
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    name: String,
+
+    /// Address on which the `/metrics` endpoint is served.
+    #[clap(short, long, default_value = "127.0.0.1:9435")]
+    listen: String,
+}
+
+// Labels that identify the XDP program whose stats are exported.
+struct MetricLabels {
+    iface: String,
+    tutorial: String,
+    program: String,
+}
+
+// The name of the program is the name of the link pinned by the 'pin' command in the `programs`
+// directory.
+fn pinned_program_name(iface: &str, name: &str) -> String {
+    let program_pin_path = format!("/sys/fs/bpf/{}/{}/programs", iface, name);
+
+    std::fs::read_dir(program_pin_path)
+        .ok()
+        .and_then(|mut entries| entries.next())
+        .and_then(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// Returns the value of a counter from a record.
+type MetricValue = fn(&StatsRecord) -> u64;
+
+// Escapes a label value as required by the Prometheus text exposition format, the backslash, the
+// double quote and the line feed are escaped with a backslash.
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+// Renders the counters in a sample in the Prometheus text exposition format.
+//
+// The counters are exported as is (per CPU and per action), rates are computed by Prometheus
+// (eg. using the `rate` function) from the successive scrapes.
+fn render_metrics(sample: &StatsSample, labels: &MetricLabels) -> String {
+    let metrics: [(&str, &str, MetricValue); 2] = [
+        (
+            "xdp_packets_total",
+            "Number of packets processed by the XDP program for an action.",
+            |r| r.pkt_count,
+        ),
+        (
+            "xdp_bytes_total",
+            "Number of bytes processed by the XDP program for an action.",
+            |r| r.bytes_count,
+        ),
+    ];
+
+    let iface = escape_label_value(&labels.iface);
+    let tutorial = escape_label_value(&labels.tutorial);
+    let program = escape_label_value(&labels.program);
+
+    let mut body = String::new();
+    for (metric, help, value) in metrics {
+        body.push_str(&format!("# HELP {metric} {help}\n"));
+        body.push_str(&format!("# TYPE {metric} counter\n"));
+        for (action, action_name) in XDP_ACTION_NAMES.iter().enumerate() {
+            for (cpu, record) in sample.records[action].iter().enumerate() {
+                body.push_str(&format!(
+                    "{metric}\{{iface=\"{}\",tutorial=\"{}\",program=\"{}\",action=\"{}\",cpu=\"{}\"}} {}\n",
+                    iface,
+                    tutorial,
+                    program,
+                    action_name,
+                    cpu,
+                    value(record),
+                ));
+            }
+        }
+    }
+
+    body
+}
+
+// Time allowed for a client to send the request and read the response, so that a client that
+// never completes the request does not hold the connection forever.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A minimal HTTP handler, that is just enough for serving the metrics to Prometheus. Only
+// `GET /metrics` is supported, for everything else `404 Not Found` is returned.
+async fn handle_scrape(
+    mut stream: TcpStream,
     stats_array: &PerCpuArray<MapData, StatsRecord>,
-    action: u32,
-    action_name: &str,
+    labels: &MetricLabels,
+) -> anyhow::Result<()> {
+    let mut request = vec![];
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+        if request.len() > 8192 {
+            break;
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let request_line = request.lines().next().unwrap_or_default();
+    log::debug!("Received request: '{request_line}'");
+
+    let response = if request_line.starts_with("GET /metrics ") {
+        let body = render_metrics(&collect_stats(stats_array)?, labels);
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+async fn export(opts: ExportOptions) -> anyhow::Result<()> {
+    let stats_array = open_pinned_stats_array(&opts.iface, &opts.name)?;
+
+    let labels = MetricLabels {
+        program: pinned_program_name(&opts.iface, &opts.name),
+        iface: opts.iface,
+        tutorial: opts.name,
+    };
+
+    // Every connection is served by its own task, so that a slow client does not hold up the
+    // other scrapes (or the handling of Ctrl-C).
+    let stats_array = Arc::new(stats_array);
+    let labels = Arc::new(labels);
+
+    let listener = TcpListener::bind(&opts.listen)
+        .await
+        .with_context(|| format!("Failed to listen on '{}'", opts.listen))?;
+    log::info!("Serving metrics at 'http://{}/metrics'", listener.local_addr()?);
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Errors like running out of the file descriptors (`EMFILE`) are
+                        // transient, back off a little and keep serving.
+                        log::warn!("Failed to accept a connection: {e}");
+                        time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                log::debug!("Accepted connection from '{peer}'");
+
+                let stats_array = Arc::clone(&stats_array);
+                let labels = Arc::clone(&labels);
+                tokio::spawn(async move {
+                    let scrape = handle_scrape(stream, &stats_array, &labels);
+                    match time::timeout(SCRAPE_TIMEOUT, scrape).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => log::warn!("Error serving metrics to '{peer}': {e}"),
+                        Err(_) => log::warn!("Timed out serving metrics to '{peer}'"),
+                    }
+                });
+            }
+            _ = signal::ctrl_c() => {
+                log::info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Handling of the 'top' command.
+#[derive(Debug, Parser)]
+struct TopOptions {
+    /// Interface name of the pinned maps to display first.
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    /// Name of the 'tutorial' of the pinned maps to display first.
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    name: String,
+
+    /// Interval (in seconds) between two successive refreshes of the stats.
+    #[clap(long, default_value_t = 1)]
+    interval: u64,
+}
+
+// Number of samples of the rates that are kept for the sparklines.
+const SPARKLINE_HISTORY: usize = 512;
+
+// An interface and a tutorial for which `PINNED_PERCPU_ARRAY` map is pinned in `/sys/fs/bpf`.
+#[derive(Debug, Clone, PartialEq)]
+struct PinnedTarget {
+    iface: String,
+    name: String,
+}
+
+// The 'pin' command pins the map at `/sys/fs/bpf/<iface>/<tutorial>/maps/PINNED_PERCPU_ARRAY`,
+// so we just look for the maps at this path.
+fn discover_pinned_targets() -> anyhow::Result<Vec<PinnedTarget>> {
+    let mut targets = vec![];
+    for iface in std::fs::read_dir("/sys/fs/bpf")? {
+        let iface = iface?;
+        if !iface.file_type()?.is_dir() {
+            continue;
+        }
+        for name in std::fs::read_dir(iface.path())? {
+            let name = name?;
+            if name.path().join("maps/PINNED_PERCPU_ARRAY").exists() {
+                targets.push(PinnedTarget {
+                    iface: iface.file_name().to_string_lossy().into_owned(),
+                    name: name.file_name().to_string_lossy().into_owned(),
+                });
+            }
+        }
+    }
+    targets.sort_by(|a, b| (&a.iface, &a.name).cmp(&(&b.iface, &b.name)));
+
+    Ok(targets)
+}
+
+fn top(opts: TopOptions) -> anyhow::Result<()> {
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Refresh interval should be at least 1 second."));
+    }
+
+    let mut targets = discover_pinned_targets()?;
+    if targets.is_empty() {
+        return Err(anyhow::Error::msg(
+            "No pinned PINNED_PERCPU_ARRAY maps found in '/sys/fs/bpf'. Please run 'pin --action <action>' to pin the map."
+        ));
+    }
+    let selected = targets
+        .iter()
+        .position(|t| t.iface == opts.iface && t.name == opts.name)
+        .unwrap_or(0);
+
+    // `ratatui::init` switches the terminal to the 'raw mode' and the alternate screen, this has to
+    // be restored (even on errors) before we exit.
+    let mut terminal = ratatui::init();
+    let result = run_top(
+        &mut terminal,
+        &mut targets,
+        selected,
+        Duration::from_secs(opts.interval),
+    );
+    ratatui::restore();
+
+    result
+}
+
+// The main loop of the 'top' command, run using `spawn_blocking`. The terminal events are polled
+// with a timeout (the time until the next sample), so that the stats are refreshed even without
+// any key presses. Note: In the 'raw mode' `Ctrl-C` does not generate the `SIGINT`, but is received
+// as a key event instead.
+fn run_top(
+    terminal: &mut DefaultTerminal,
+    targets: &mut Vec<PinnedTarget>,
+    mut selected: usize,
+    period: Duration,
+) -> anyhow::Result<()> {
+    'targets: loop {
+        let target = targets[selected].clone();
+        let stats_array = open_pinned_stats_array(&target.iface, &target.name)?;
+
+        let mut prev = collect_stats(&stats_array)?;
+        let mut sample = 0;
+        let mut stats = vec![];
+        let mut history = vec![VecDeque::new(); XDP_ACTION_NAMES.len()];
+        let mut next_sample = Instant::now() + period;
+
+        loop {
+            terminal.draw(|frame| draw_top(frame, targets, selected, &stats, &history))?;
+
+            let timeout = next_sample.saturating_duration_since(Instant::now());
+            if event::poll(timeout)? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            return Ok(())
+                        }
+                        KeyCode::Tab | KeyCode::Char('n') => {
+                            selected = (selected + 1) % targets.len();
+                            continue 'targets;
+                        }
+                        KeyCode::BackTab | KeyCode::Char('p') => {
+                            selected = (selected + targets.len() - 1) % targets.len();
+                            continue 'targets;
+                        }
+                        KeyCode::Char('r') => {
+                            let rescanned = discover_pinned_targets()?;
+                            if rescanned.is_empty() {
+                                return Err(anyhow::Error::msg(
+                                    "No pinned PINNED_PERCPU_ARRAY maps found in '/sys/fs/bpf'.",
+                                ));
+                            }
+                            *targets = rescanned;
+                            selected = targets.iter().position(|t| *t == target).unwrap_or(0);
+                            continue 'targets;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if Instant::now() >= next_sample {
+                let curr = collect_stats(&stats_array)?;
+                sample += 1;
+                stats = action_stats(&prev, &curr, &target.iface, sample);
+                for (h, s) in history.iter_mut().zip(stats.iter()) {
+                    h.push_back(s.total.packets_per_sec.round() as u64);
+                    if h.len() > SPARKLINE_HISTORY {
+                        h.pop_front();
+                    }
+                }
+                prev = curr;
+                next_sample += period;
+            }
+        }
+    }
+}
+
+fn draw_top(
+    frame: &mut Frame,
+    targets: &[PinnedTarget],
+    selected: usize,
+    stats: &[ActionStats],
+    history: &[VecDeque<u64>],
 ) {
-    let mut total_packets = 0;
-    let values = stats_array.get(&action, 0).unwrap();
-    for (i, value) in values.iter().enumerate() {
-        log::info!(
-            "CPU: {}, Action: {}, Packet Count: {}",
-            i,
-            action_name,
-            value.pkt_count
-        );
-        total_packets += value.pkt_count;
+    let [tabs_area, table_area, sparklines_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(XDP_ACTION_NAMES.len() as u16 + 3),
+        Constraint::Min(0),
+    ])
+    .areas(frame.area());
+
+    let tabs = Tabs::new(targets.iter().map(|t| format!("{}/{}", t.iface, t.name)))
+        .select(selected)
+        .highlight_style(Style::new().bold().reversed())
+        .block(Block::bordered().title(
+            " Pinned Stats (Tab/n: next, Shift-Tab/p: previous, r: rescan, q: quit) ",
+        ));
+    frame.render_widget(tabs, tabs_area);
+
+    let cpus = stats.first().map(|s| s.cpus.len()).unwrap_or(0);
+    let header = ["Action".to_string()]
+        .into_iter()
+        .chain((0..cpus).map(|cpu| format!("CPU {cpu}")))
+        .chain(["Packets", "pkts/s", "Bytes", "bytes/s"].map(String::from));
+    let rows = stats.iter().map(|s| {
+        [s.action.to_string()]
+            .into_iter()
+            .chain(
+                s.cpus
+                    .iter()
+                    .map(|c| format!("{} ({:.0}/s)", c.packets, c.packets_per_sec)),
+            )
+            .chain([
+                s.total.packets.to_string(),
+                format!("{:.2}", s.total.packets_per_sec),
+                s.total.bytes.to_string(),
+                format!("{:.2}", s.total.bytes_per_sec),
+            ])
+            .collect::<Row>()
+    });
+    let widths = [Constraint::Length(10)]
+        .into_iter()
+        .chain((0..cpus).map(|_| Constraint::Fill(1)))
+        .chain([Constraint::Length(14); 4]);
+    let table = Table::new(rows, widths)
+        .header(header.collect::<Row>().bold())
+        .block(Block::bordered().title(" Actions × CPUs "));
+    frame.render_widget(table, table_area);
+
+    let areas = Layout::vertical(vec![Constraint::Fill(1); XDP_ACTION_NAMES.len()])
+        .split(sparklines_area);
+    for ((action_name, h), area) in XDP_ACTION_NAMES.iter().zip(history).zip(areas.iter()) {
+        // Only the most recent samples that fit in the area are displayed.
+        let width = area.width.saturating_sub(2) as usize;
+        let data = h
+            .iter()
+            .skip(h.len().saturating_sub(width))
+            .copied()
+            .collect::<Vec<_>>();
+        let sparkline = Sparkline::default()
+            .data(&data)
+            .block(Block::bordered().title(format!(" {action_name} (pkts/s) ")));
+        frame.render_widget(sparkline, *area);
     }
-    log::info!("Action: {}, Total  Packets: {}", action_name, total_packets);
 }
 
 #[tokio::main]
@@ -217,6 +854,83 @@ async fn main() -> Result<(), anyhow::Error> {
     match cli {
         CliCommand::Pin(opts) => pin_program_and_maps(opts),
         CliCommand::Stats(opts) => stats(opts).await,
+        CliCommand::Export(opts) => export(opts).await,
+        // The terminal dashboard blocks on the terminal events and the drawing, so it runs on a
+        // thread for the blocking tasks and not on one of the async workers.
+        CliCommand::Top(opts) => tokio::task::spawn_blocking(move || top(opts)).await?,
         // TODO : Add List and Unpin commands
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pkt_count: u64, bytes_count: u64) -> StatsRecord {
+        StatsRecord {
+            pkt_count,
+            bytes_count,
+        }
+    }
+
+    // A sample with two CPUs, with the counters only for the 'pass' action.
+    fn sample() -> StatsSample {
+        let mut records = vec![vec![record(0, 0); 2]; XDP_ACTION_NAMES.len()];
+        records[2] = vec![record(3, 180), record(4, 240)];
+
+        StatsSample {
+            timestamp: Instant::now(),
+            time: SystemTime::now(),
+            records,
+        }
+    }
+
+    fn labels(iface: &str) -> MetricLabels {
+        MetricLabels {
+            iface: iface.to_string(),
+            tutorial: "{{tutorial_name}}".to_string(),
+            program: "{{to_snake_case tutorial_name}}_action_pass".to_string(),
+        }
+    }
+
+    #[test]
+    fn render_metrics_exports_every_action_and_cpu() {
+        let body = render_metrics(&sample(), &labels("lo"));
+        let lines = body.lines().collect::<Vec<_>>();
+
+        // `# HELP`, `# TYPE` and a line for every action and CPU, for both the metrics.
+        assert_eq!(lines.len(), 2 * (2 + XDP_ACTION_NAMES.len() * 2));
+        assert_eq!(lines[0], "# HELP xdp_packets_total Number of packets processed by the XDP program for an action.");
+        assert_eq!(lines[1], "# TYPE xdp_packets_total counter");
+
+        let labels = "iface=\"lo\",tutorial=\"{{tutorial_name}}\",program=\"{{to_snake_case tutorial_name}}_action_pass\"";
+        for line in [
+            format!("xdp_packets_total\{{{labels},action=\"pass\",cpu=\"0\"}} 3"),
+            format!("xdp_packets_total\{{{labels},action=\"pass\",cpu=\"1\"}} 4"),
+            format!("xdp_packets_total\{{{labels},action=\"drop\",cpu=\"1\"}} 0"),
+            format!("xdp_bytes_total\{{{labels},action=\"pass\",cpu=\"0\"}} 180"),
+            format!("xdp_bytes_total\{{{labels},action=\"pass\",cpu=\"1\"}} 240"),
+        ] {
+            assert!(lines.contains(&line.as_str()), "missing: {line}\n{body}");
+        }
+        assert!(body.ends_with('\n'));
+    }
+
+    #[test]
+    fn render_metrics_escapes_label_values() {
+        let body = render_metrics(&sample(), &labels("we\"ird\\if\nname"));
+
+        assert!(body.contains("iface=\"we\\\"ird\\\\if\\nname\""), "{body}");
+        // The line feed in the label value must not break the line of the sample.
+        assert!(body
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .all(|line| line.starts_with("xdp_")));
+    }
+
+    #[test]
+    fn escape_label_value_leaves_plain_values() {
+        assert_eq!(escape_label_value("eth0"), "eth0");
+        assert_eq!(escape_label_value("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+    }
+}
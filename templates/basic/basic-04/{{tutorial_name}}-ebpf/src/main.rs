@@ -36,9 +36,16 @@ pub fn {{ to_snake_case tutorial_name }}_action_drop(ctx: XdpContext) -> u32 {
 #[inline(always)]
 fn try_{{ to_snake_case tutorial_name }}_packet_stats(ctx: XdpContext, action: u32) -> Result<u32, u32> {
     debug!(&ctx, "Received a packet.");
+
+    // `data` and `data_end` are the start and the end of the packet in the memory.
+    let bytes = (ctx.data_end() - ctx.data()) as u64;
+
     let record = PINNED_PERCPU_ARRAY.get_ptr_mut(action);
     if let Some(record) = record {
-        let _ = unsafe { (*record).pkt_count += 1 };
+        unsafe {
+            (*record).pkt_count += 1;
+            (*record).bytes_count += bytes;
+        }
         Ok(action)
     } else {
         error!(&ctx, "Entry for the action not found in the map!");
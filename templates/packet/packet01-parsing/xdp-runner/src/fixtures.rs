@@ -0,0 +1,311 @@
+// Fixture packets for testing the parser program using `BPF_PROG_TEST_RUN`.
+//
+// The checksums in the packets are not computed, as the parser does not verify them.
+
+const ETH_P_IP: u16 = 0x0800;
+const ETH_P_IPV6: u16 = 0x86DD;
+const ETH_P_8021Q: u16 = 0x8100;
+const ETH_P_8021AD: u16 = 0x88A8;
+
+const IPPROTO_HOPOPTS: u8 = 0;
+const IPPROTO_ROUTING: u8 = 43;
+const IPPROTO_FRAGMENT: u8 = 44;
+const IPPROTO_ICMPV6: u8 = 58;
+const IPPROTO_DSTOPTS: u8 = 60;
+
+const XDP_DROP: u32 = 1;
+const XDP_PASS: u32 = 2;
+
+// VLAN IDs used by the `testenv` scripts (see `VLAN_IDS` in `testenv/config.sh`).
+const VLAN_IDS: [u16; 2] = [1, 2];
+
+// Maximum number of the IPv6 extension headers skipped by the parser (see `IPV6_EXT_MAX_CHAIN` in
+// the `parsing_helpers.rs` of the eBPF program).
+const IPV6_EXT_MAX_CHAIN: usize = 8;
+
+/// A packet and the XDP action expected for the packet from the parser.
+pub(crate) struct Fixture {
+    pub(crate) name: &'static str,
+    pub(crate) packet: Vec<u8>,
+    pub(crate) expected: u32,
+}
+
+// Ethernet header followed by the VLAN headers, `vlans` are `(TPID, VLAN ID)` pairs.
+fn ethhdr(vlans: &[(u16, u16)], h_proto: u16) -> Vec<u8> {
+    let mut hdr = vec![];
+    hdr.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x02]);
+    hdr.extend_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+    for (tpid, vlan_id) in vlans {
+        hdr.extend_from_slice(&tpid.to_be_bytes());
+        hdr.extend_from_slice(&vlan_id.to_be_bytes());
+    }
+    hdr.extend_from_slice(&h_proto.to_be_bytes());
+
+    hdr
+}
+
+/// An IPv6 extension header.
+#[derive(Clone, Copy)]
+enum ExtHdr {
+    /// Hop-by-Hop Options header of `(hdrlen + 1) * 8` bytes, padded with a PadN option.
+    HopByHop(u8),
+    /// Destination Options header of `(hdrlen + 1) * 8` bytes, padded with a PadN option.
+    DestOpts(u8),
+    /// Routing header (of the type 0, with no segments left).
+    Routing,
+    /// Fragment header with the fragment offset (in 8 byte units) and the 'more fragments' flag.
+    Fragment(u16, bool),
+}
+
+impl ExtHdr {
+    fn proto(&self) -> u8 {
+        match self {
+            ExtHdr::HopByHop(_) => IPPROTO_HOPOPTS,
+            ExtHdr::DestOpts(_) => IPPROTO_DSTOPTS,
+            ExtHdr::Routing => IPPROTO_ROUTING,
+            ExtHdr::Fragment(_, _) => IPPROTO_FRAGMENT,
+        }
+    }
+
+    fn build(&self, nexthdr: u8) -> Vec<u8> {
+        match *self {
+            ExtHdr::HopByHop(hdrlen) | ExtHdr::DestOpts(hdrlen) => {
+                let len = (hdrlen as usize + 1) * 8;
+                let mut hdr = vec![nexthdr, hdrlen, 1, (len - 4) as u8];
+                hdr.resize(len, 0);
+                hdr
+            }
+            ExtHdr::Routing => vec![nexthdr, 0, 0, 0, 0, 0, 0, 0],
+            ExtHdr::Fragment(offset, more) => {
+                let frag_off = (offset << 3) | more as u16;
+                let mut hdr = vec![nexthdr, 0];
+                hdr.extend_from_slice(&frag_off.to_be_bytes());
+                hdr.extend_from_slice(&0xcafeu32.to_be_bytes());
+                hdr
+            }
+        }
+    }
+}
+
+// IPv6 packet with the extension headers (in the given order) followed by an ICMPv6 Echo Request.
+fn ipv6_ext_icmp6_echo_request(exthdrs: &[ExtHdr], sequence: u16) -> Vec<u8> {
+    let mut payload = vec![];
+    for (i, exthdr) in exthdrs.iter().enumerate() {
+        let nexthdr = exthdrs.get(i + 1).map_or(IPPROTO_ICMPV6, ExtHdr::proto);
+        payload.extend(exthdr.build(nexthdr));
+    }
+    payload.extend_from_slice(&[128, 0, 0, 0]);
+    payload.extend_from_slice(&0x1234u16.to_be_bytes());
+    payload.extend_from_slice(&sequence.to_be_bytes());
+
+    let mut pkt = vec![0x60, 0, 0, 0];
+    pkt.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    pkt.push(exthdrs.first().map_or(IPPROTO_ICMPV6, ExtHdr::proto));
+    pkt.push(64);
+    pkt.extend_from_slice(&[0xfc, 0x00, 0x42, 0xde, 0xca, 0xfe, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2]);
+    pkt.extend_from_slice(&[0xfc, 0x00, 0x42, 0xde, 0xca, 0xfe, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+    pkt.extend(payload);
+
+    pkt
+}
+
+fn ipv6_icmp6_echo_request(sequence: u16) -> Vec<u8> {
+    ipv6_ext_icmp6_echo_request(&[], sequence)
+}
+
+fn ipv4_icmp_echo_request(sequence: u16) -> Vec<u8> {
+    let mut icmp = vec![8, 0, 0, 0];
+    icmp.extend_from_slice(&0x1234u16.to_be_bytes());
+    icmp.extend_from_slice(&sequence.to_be_bytes());
+
+    let mut pkt = vec![0x45, 0];
+    pkt.extend_from_slice(&(20 + icmp.len() as u16).to_be_bytes());
+    pkt.extend_from_slice(&[0, 0, 0x40, 0, 64, 1, 0, 0]);
+    pkt.extend_from_slice(&[10, 11, 1, 2]);
+    pkt.extend_from_slice(&[10, 11, 1, 1]);
+    pkt.extend_from_slice(&icmp);
+
+    pkt
+}
+
+fn packet(vlans: &[(u16, u16)], h_proto: u16, payload: Vec<u8>) -> Vec<u8> {
+    let mut pkt = ethhdr(vlans, h_proto);
+    pkt.extend(payload);
+
+    pkt
+}
+
+/// Returns the fixture packets. The expected actions are those of the solution of the exercises,
+/// ie. VLAN tagged packets, IPv4 packets and IPv6 packets with extension headers are also handled.
+pub(crate) fn fixtures() -> Vec<Fixture> {
+    let dot1q = |vlan_id| (ETH_P_8021Q, vlan_id);
+    let dot1ad = |vlan_id| (ETH_P_8021AD, vlan_id);
+    let ext_chain = [
+        ExtHdr::HopByHop(0),
+        ExtHdr::DestOpts(0),
+        ExtHdr::Routing,
+        ExtHdr::Fragment(0, true),
+    ];
+
+    vec![
+        Fixture {
+            name: "IPv6 Echo Request, odd sequence",
+            packet: packet(&[], ETH_P_IPV6, ipv6_icmp6_echo_request(1)),
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "IPv6 Echo Request, even sequence",
+            packet: packet(&[], ETH_P_IPV6, ipv6_icmp6_echo_request(2)),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "Truncated IPv6 Echo Request",
+            packet: {
+                let mut pkt = packet(&[], ETH_P_IPV6, ipv6_icmp6_echo_request(2));
+                pkt.truncate(pkt.len() - 4);
+                pkt
+            },
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "802.1Q IPv6 Echo Request, odd sequence",
+            packet: packet(&[dot1q(VLAN_IDS[0])], ETH_P_IPV6, ipv6_icmp6_echo_request(3)),
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "802.1Q IPv6 Echo Request, even sequence",
+            packet: packet(&[dot1q(VLAN_IDS[0])], ETH_P_IPV6, ipv6_icmp6_echo_request(4)),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "802.1ad (QinQ) IPv6 Echo Request, even sequence",
+            packet: packet(
+                &[dot1ad(VLAN_IDS[0]), dot1q(VLAN_IDS[1])],
+                ETH_P_IPV6,
+                ipv6_icmp6_echo_request(6),
+            ),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "Three VLAN headers (more than VLAN_MAX_DEPTH), even sequence",
+            packet: packet(
+                &[dot1ad(VLAN_IDS[0]), dot1q(VLAN_IDS[1]), dot1q(VLAN_IDS[0])],
+                ETH_P_IPV6,
+                ipv6_icmp6_echo_request(8),
+            ),
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "Truncated 802.1Q header",
+            packet: {
+                let mut pkt = ethhdr(&[], ETH_P_8021Q);
+                pkt.extend_from_slice(&VLAN_IDS[0].to_be_bytes());
+                pkt
+            },
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "IPv4 Echo Request, odd sequence",
+            packet: packet(&[], ETH_P_IP, ipv4_icmp_echo_request(1)),
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "IPv4 Echo Request, even sequence",
+            packet: packet(&[], ETH_P_IP, ipv4_icmp_echo_request(2)),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "IPv4 Echo Request with invalid header length",
+            packet: {
+                let mut pkt = packet(&[], ETH_P_IP, ipv4_icmp_echo_request(2));
+                // IHL of 4 (16 bytes) is less than the minimum IPv4 header length.
+                pkt[14] = 0x44;
+                pkt
+            },
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "802.1Q IPv4 Echo Request, even sequence",
+            packet: packet(&[dot1q(VLAN_IDS[1])], ETH_P_IP, ipv4_icmp_echo_request(10)),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "IPv6 Hop-by-Hop Options, Echo Request, even sequence",
+            packet: packet(
+                &[],
+                ETH_P_IPV6,
+                ipv6_ext_icmp6_echo_request(&[ExtHdr::HopByHop(0)], 12),
+            ),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "IPv6 Hop-by-Hop Options (16 bytes), Echo Request, even sequence",
+            packet: packet(
+                &[],
+                ETH_P_IPV6,
+                ipv6_ext_icmp6_echo_request(&[ExtHdr::HopByHop(1)], 14),
+            ),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "IPv6 Hop-by-Hop, Destination, Routing and Fragment headers, even sequence",
+            packet: packet(&[], ETH_P_IPV6, ipv6_ext_icmp6_echo_request(&ext_chain, 16)),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "IPv6 Hop-by-Hop, Destination, Routing and Fragment headers, odd sequence",
+            packet: packet(&[], ETH_P_IPV6, ipv6_ext_icmp6_echo_request(&ext_chain, 17)),
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "802.1Q IPv6 Destination Options, Echo Request, even sequence",
+            packet: packet(
+                &[dot1q(VLAN_IDS[0])],
+                ETH_P_IPV6,
+                ipv6_ext_icmp6_echo_request(&[ExtHdr::DestOpts(2)], 18),
+            ),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "IPV6_EXT_MAX_CHAIN extension headers, even sequence",
+            packet: packet(
+                &[],
+                ETH_P_IPV6,
+                ipv6_ext_icmp6_echo_request(&[ExtHdr::DestOpts(0); IPV6_EXT_MAX_CHAIN], 20),
+            ),
+            expected: XDP_DROP,
+        },
+        Fixture {
+            name: "More than IPV6_EXT_MAX_CHAIN extension headers, even sequence",
+            packet: packet(
+                &[],
+                ETH_P_IPV6,
+                ipv6_ext_icmp6_echo_request(&[ExtHdr::DestOpts(0); IPV6_EXT_MAX_CHAIN + 1], 22),
+            ),
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "Truncated IPv6 Hop-by-Hop Options header",
+            packet: {
+                let mut pkt = packet(
+                    &[],
+                    ETH_P_IPV6,
+                    ipv6_ext_icmp6_echo_request(&[ExtHdr::HopByHop(1)], 24),
+                );
+                // Ethernet and IPv6 headers, followed by 10 of the 16 bytes of the header.
+                pkt.truncate(14 + 40 + 10);
+                pkt
+            },
+            expected: XDP_PASS,
+        },
+        Fixture {
+            name: "IPv6 non-first Fragment, even sequence",
+            packet: packet(
+                &[],
+                ETH_P_IPV6,
+                ipv6_ext_icmp6_echo_request(&[ExtHdr::Fragment(185, false)], 26),
+            ),
+            expected: XDP_PASS,
+        },
+    ]
+}
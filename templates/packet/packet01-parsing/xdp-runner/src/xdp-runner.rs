@@ -0,0 +1,153 @@
+mod fixtures;
+mod test_run;
+
+use std::os::fd::AsFd;
+
+use anyhow::Context;
+
+use aya::programs::{Xdp, XdpFlags};
+use aya::util::KernelVersion;
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::Parser;
+use log::{error, info, warn};
+use tokio::signal;
+
+#[derive(Debug, Parser)]
+struct Opt {
+    #[clap(short, long, default_value = "{{to_snake_case tutorial_name}}_parser")]
+    program: String,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+
+    /// Run the program on the fixture packets (using `BPF_PROG_TEST_RUN`) and check the actions
+    /// returned, instead of attaching the program to the interface.
+    #[clap(long)]
+    test_run: bool,
+}
+
+// Runs the program on all the fixture packets and reports the fixtures for which the action
+// returned by the program is not the expected action.
+fn run_fixtures(xdp: &Xdp) -> Result<(), anyhow::Error> {
+    let prog_fd = xdp.fd()?.as_fd();
+
+    let mut failed = 0;
+    let fixtures = fixtures::fixtures();
+    for fixture in &fixtures {
+        let (action, _) = test_run::test_run_xdp(prog_fd, &fixture.packet)
+            .context("Failed to run the program using `BPF_PROG_TEST_RUN`")?;
+        if action == fixture.expected {
+            info!("PASSED: {}", fixture.name);
+        } else {
+            error!(
+                "FAILED: {} (expected action: {}, returned action: {})",
+                fixture.name, fixture.expected, action
+            );
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(anyhow::Error::msg(format!(
+            "{} of {} fixtures failed.",
+            failed,
+            fixtures.len()
+        )));
+    }
+    info!("All {} fixtures passed.", fixtures.len());
+
+    Ok(())
+}
+
+// The `bpf_loop` helper is available since the kernel 5.17, a program using it can't be loaded on
+// the older kernels. If the file has a `<program>_unrolled` variant of the program (walking the
+// IPv6 extension headers using a bounded loop instead), it is selected on such kernels.
+fn select_program(bpf: &Ebpf, program: &str) -> String {
+    let unrolled = format!("{}_unrolled", program);
+    if bpf.program(&unrolled).is_none() {
+        return program.to_string();
+    }
+
+    match KernelVersion::current() {
+        Ok(version) if version < KernelVersion::new(5, 17, 0) => {
+            warn!(
+                "The kernel {} doesn't support `bpf_loop`, using the program '{}' instead",
+                version, unrolled
+            );
+            unrolled
+        }
+        Ok(_) => program.to_string(),
+        Err(e) => {
+            warn!("failed to detect the kernel version: {}", e);
+            program.to_string()
+        }
+    }
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// This program will be run when you are running `cargo xtask run`. The actual packet processing
+// logic is implemented in the `<tutorial_name>-ebpf` package, whose generated output is passed
+// as an argument to this program as `--file`. In a given file there may be more than one 'prgrams'
+// in the `xdp` section, which program is to be attached is specified by the `--program` argument.
+// Optionally, we can also give the interface to which the program is to be attached by specifying
+// the `--iface` flag (default being `lo`).
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+    let program = select_program(&bpf, &opts.program);
+    let xdp_program = bpf.program_mut(&program);
+
+    if let Some(xdp_program) = xdp_program {
+        let xdp: &mut Xdp = xdp_program.try_into()?;
+        xdp.load()?;
+
+        if opts.test_run {
+            return run_fixtures(xdp);
+        }
+
+        let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+        if let Err(e) = EbpfLogger::init(&mut bpf) {
+            // This can happen if you remove all log statements from your eBPF program.
+            warn!("failed to initialize eBPF logger: {}", e);
+        }
+
+        info!(
+            "XDP Program '{}' attached to '{}'! Now waiting for Ctrl-C",
+            &program, &opts.iface
+        );
+        signal::ctrl_c().await?;
+        info!("Exiting...");
+
+        Ok(())
+    } else {
+        let mut progs = vec![];
+        for (name, _program_type) in bpf.programs() {
+            progs.push(name);
+        }
+        Err(anyhow::Error::msg(format!(
+            "Unable to find the program '{}' in the loaded file '{}'. Available programs are: {}",
+            program,
+            opts.file,
+            progs.join(", "),
+        )))
+    }
+}
@@ -0,0 +1,98 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+mod solution;
+
+use aya_ebpf::{bindings::xdp_action, macros::xdp, programs::XdpContext};
+use aya_log_ebpf::{debug, info};
+
+use solution::Ip6ExtWalk;
+
+use parsing_helpers::{
+    parse_ethhdr, parse_icmp6hdr, parse_ip6hdr, HdrCursor, ETH_P_IPV6, ICMPV6_ECHO_REQUEST,
+    IPPROTO_ICMPV6,
+};
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_parser(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_parser(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Parses the Ethernet, IPv6 and ICMPv6 headers and drops the ICMPv6 Echo Requests with even
+// sequence numbers. All the other packets (including the ones that we fail to parse) are passed.
+fn try_{{to_snake_case tutorial_name}}_parser(ctx: &XdpContext) -> Result<u32, u32> {
+    // The cursor starts at the beginning of the packet and is advanced by every header parsed.
+    let mut cursor = HdrCursor::new(ctx);
+
+    // Exercise 1: Packets with VLAN headers are passed without looking at the IPv6 header. Write
+    // a `parse_ethhdr_vlan` function that also parses upto `VLAN_MAX_DEPTH` VLAN headers (see
+    // `VlanHdr` and `proto_is_vlan` in `parsing_helpers.rs`) after the Ethernet header and use it
+    // instead of `parse_ethhdr`.
+    let Some(eth_type) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    // Exercise 2: Only IPv6 packets are handled. Write `parse_iphdr` and `parse_icmphdr` functions
+    // (see `Ipv4Hdr` and `IcmpHdr` in `parsing_helpers.rs`) and use them to apply the same logic
+    // to the IPv4 ICMP Echo Requests. Note: IPv4 header is of variable length.
+    if eth_type != ETH_P_IPV6 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    // Exercise 3: The IPv6 packets with the extension headers (eg. Hop-by-Hop Options) before the
+    // ICMPv6 header are passed. Skip upto `IPV6_EXT_MAX_CHAIN` extension headers (see `Ipv6OptHdr`,
+    // `FragHdr` and `proto_is_ip6ext` in `parsing_helpers.rs`) using the `bpf_loop` helper, along
+    // with a bounded loop for the kernels without `bpf_loop`.
+    let Some(ip_type) = parse_ip6hdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if ip_type != IPPROTO_ICMPV6 {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    let Some(icmp6h) = parse_icmp6hdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let (icmp6_type, sequence) =
+        unsafe { ((*icmp6h).icmp6_type, u16::from_be((*icmp6h).icmp6_sequence)) };
+    if icmp6_type != ICMPV6_ECHO_REQUEST {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    if sequence % 2 == 0 {
+        info!(ctx, "Dropping ICMPv6 Echo Request with sequence: {}", sequence);
+        Ok(xdp_action::XDP_DROP)
+    } else {
+        debug!(ctx, "Passing ICMPv6 Echo Request with sequence: {}", sequence);
+        Ok(xdp_action::XDP_PASS)
+    }
+}
+
+// Solution for the exercises, can be run with `--program {{to_snake_case tutorial_name}}_parser_solution`.
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_parser_solution(ctx: XdpContext) -> u32 {
+    match solution::try_{{to_snake_case tutorial_name}}_parser_solution(&ctx, Ip6ExtWalk::BpfLoop) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Same as the solution above, but skips the IPv6 extension headers with a bounded loop instead of
+// `bpf_loop`. The runner loads this program instead, on the kernels without `bpf_loop`.
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_parser_solution_unrolled(ctx: XdpContext) -> u32 {
+    match solution::try_{{to_snake_case tutorial_name}}_parser_solution(&ctx, Ip6ExtWalk::Unrolled) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,197 @@
+// Helpers for parsing the packet headers.
+//
+// These are similar to the helpers in `parsing_helpers.h` in the original XDP tutorial. Every
+// access to the packet data has to be 'bounds checked' against the `data_end` of the
+// `XdpContext`, without this check the eBPF verifier will refuse to load the program. All the
+// `parse_*` functions below make use of `ptr_at` for this and return `None` if the header does
+// not fit in the packet.
+
+use core::mem;
+
+use aya_ebpf::programs::XdpContext;
+
+pub const ETH_P_IP: u16 = 0x0800;
+pub const ETH_P_IPV6: u16 = 0x86DD;
+pub const ETH_P_8021Q: u16 = 0x8100;
+pub const ETH_P_8021AD: u16 = 0x88A8;
+
+pub const IPPROTO_ICMP: u8 = 1;
+pub const IPPROTO_ICMPV6: u8 = 58;
+
+pub const IPPROTO_HOPOPTS: u8 = 0;
+pub const IPPROTO_ROUTING: u8 = 43;
+pub const IPPROTO_FRAGMENT: u8 = 44;
+pub const IPPROTO_DSTOPTS: u8 = 60;
+
+pub const ICMP_ECHO: u8 = 8;
+pub const ICMPV6_ECHO_REQUEST: u8 = 128;
+
+/// Maximum number of stacked VLAN headers that are parsed.
+///
+/// The eBPF verifier only accepts loops that are 'bounded', hence we parse at the most these many
+/// VLAN headers (two for QinQ).
+pub const VLAN_MAX_DEPTH: usize = 2;
+
+/// Maximum number of the IPv6 extension headers that are skipped to find the upper-layer header.
+pub const IPV6_EXT_MAX_CHAIN: u32 = 8;
+
+/// Mask of the fragment offset in the `frag_off` field (in host byte order) of the `FragHdr`.
+pub const IP6_OFFSET_MASK: u16 = 0xFFF8;
+
+/// Ethernet Header.
+#[repr(C)]
+pub struct EthHdr {
+    pub h_dest: [u8; 6],
+    pub h_source: [u8; 6],
+    /// Protocol of the next header (in network byte order).
+    pub h_proto: u16,
+}
+
+/// VLAN Header (802.1Q and 802.1ad), follows the Ethernet Header.
+#[repr(C)]
+pub struct VlanHdr {
+    /// Tag Control Information, lower 12 bits are the VLAN ID (in network byte order).
+    pub h_vlan_tci: u16,
+    /// Protocol of the next header (in network byte order).
+    pub h_vlan_encapsulated_proto: u16,
+}
+
+/// IPv4 Header (without the options).
+#[repr(C)]
+pub struct Ipv4Hdr {
+    /// Version (upper 4 bits) and the Header length in 32 bit words (lower 4 bits).
+    pub version_ihl: u8,
+    pub tos: u8,
+    pub tot_len: u16,
+    pub id: u16,
+    pub frag_off: u16,
+    pub ttl: u8,
+    pub protocol: u8,
+    pub check: u16,
+    pub saddr: u32,
+    pub daddr: u32,
+}
+
+/// ICMP Header (with the fields of the 'echo' messages).
+#[repr(C)]
+pub struct IcmpHdr {
+    pub icmp_type: u8,
+    pub code: u8,
+    pub checksum: u16,
+    pub identifier: u16,
+    /// Sequence number of the echo message (in network byte order).
+    pub sequence: u16,
+}
+
+/// IPv6 Header.
+#[repr(C)]
+pub struct Ipv6Hdr {
+    pub priority_version: u8,
+    pub flow_lbl: [u8; 3],
+    pub payload_len: u16,
+    pub nexthdr: u8,
+    pub hop_limit: u8,
+    pub saddr: [u8; 16],
+    pub daddr: [u8; 16],
+}
+
+/// The first two bytes of the IPv6 extension headers of a variable length (Hop-by-Hop Options,
+/// Routing and Destination Options).
+#[repr(C)]
+pub struct Ipv6OptHdr {
+    /// Protocol of the next header.
+    pub nexthdr: u8,
+    /// Length of the header in 8 byte units, not including the first 8 bytes.
+    pub hdrlen: u8,
+}
+
+/// IPv6 Fragment Header (of a fixed length).
+#[repr(C)]
+pub struct FragHdr {
+    /// Protocol of the next header.
+    pub nexthdr: u8,
+    pub reserved: u8,
+    /// Fragment offset in 8 byte units (upper 13 bits) and the 'more fragments' flag (lowest bit),
+    /// in network byte order.
+    pub frag_off: u16,
+    pub identification: u32,
+}
+
+/// ICMPv6 Header (with the fields of the 'echo' messages).
+#[repr(C)]
+pub struct Icmp6Hdr {
+    pub icmp6_type: u8,
+    pub icmp6_code: u8,
+    pub icmp6_cksum: u16,
+    pub icmp6_identifier: u16,
+    /// Sequence number of the echo message (in network byte order).
+    pub icmp6_sequence: u16,
+}
+
+/// Keeps track of the current parsing position in the packet.
+///
+/// Every successful `parse_*` call advances the cursor to the start of the next header.
+pub struct HdrCursor {
+    /// Address of the current parsing position in the packet.
+    pub pos: usize,
+}
+
+impl HdrCursor {
+    pub fn new(ctx: &XdpContext) -> Self {
+        Self { pos: ctx.data() }
+    }
+}
+
+/// Returns the pointer to a `T` at the cursor, only if the whole `T` lies within the packet.
+#[inline(always)]
+pub fn ptr_at<T>(ctx: &XdpContext, cursor: &HdrCursor) -> Option<*const T> {
+    let len = mem::size_of::<T>();
+    if cursor.pos + len > ctx.data_end() {
+        return None;
+    }
+
+    Some(cursor.pos as *const T)
+}
+
+/// Parses the Ethernet header and returns the protocol of the next header (in host byte order).
+#[inline(always)]
+pub fn parse_ethhdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u16> {
+    let eth: *const EthHdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<EthHdr>();
+
+    Some(u16::from_be(unsafe { (*eth).h_proto }))
+}
+
+/// Returns `true` if the `h_proto` (in host byte order) is that of a VLAN header.
+#[inline(always)]
+pub fn proto_is_vlan(h_proto: u16) -> bool {
+    h_proto == ETH_P_8021Q || h_proto == ETH_P_8021AD
+}
+
+/// Returns `true` if the `nexthdr` is that of an IPv6 extension header, which is followed by
+/// another extension header or the upper-layer header (eg. ICMPv6).
+#[inline(always)]
+pub fn proto_is_ip6ext(nexthdr: u8) -> bool {
+    matches!(
+        nexthdr,
+        IPPROTO_HOPOPTS | IPPROTO_ROUTING | IPPROTO_FRAGMENT | IPPROTO_DSTOPTS
+    )
+}
+
+/// Parses the IPv6 header and returns the protocol of the next header.
+#[inline(always)]
+pub fn parse_ip6hdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u8> {
+    let ip6h: *const Ipv6Hdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<Ipv6Hdr>();
+
+    Some(unsafe { (*ip6h).nexthdr })
+}
+
+/// Parses the ICMPv6 header and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_icmp6hdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*const Icmp6Hdr> {
+    let icmp6h: *const Icmp6Hdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<Icmp6Hdr>();
+
+    Some(icmp6h)
+}
@@ -0,0 +1,283 @@
+// Solutions to the exercises of this tutorial.
+//
+// Try to solve the exercises in `main.rs` before looking at these solutions.
+
+use core::{ffi::c_void, mem};
+
+use aya_ebpf::{
+    bindings::{xdp_action, xdp_md},
+    helpers::bpf_loop,
+    programs::XdpContext,
+};
+use aya_log_ebpf::{debug, info};
+
+use crate::parsing_helpers::{
+    parse_ethhdr, parse_icmp6hdr, parse_ip6hdr, proto_is_ip6ext, proto_is_vlan, ptr_at, FragHdr,
+    HdrCursor, IcmpHdr, Ipv4Hdr, Ipv6OptHdr, VlanHdr, ETH_P_IP, ETH_P_IPV6, ICMPV6_ECHO_REQUEST,
+    ICMP_ECHO, IP6_OFFSET_MASK, IPPROTO_FRAGMENT, IPPROTO_ICMP, IPPROTO_ICMPV6, IPV6_EXT_MAX_CHAIN,
+    VLAN_MAX_DEPTH,
+};
+
+/// Maximum offset (from the start of the packet) of an IPv6 extension header that is parsed.
+///
+/// The offset is passed to the `bpf_loop` callback in the memory, so the verifier does not know
+/// its bounds, it has to be checked before it is added to the start of the packet.
+const IPV6_EXT_MAX_OFFSET: usize = 4096;
+
+/// How the IPv6 extension headers are skipped by the solution.
+#[derive(Clone, Copy)]
+pub enum Ip6ExtWalk {
+    /// Using the `bpf_loop` helper (Linux 5.17 or later).
+    BpfLoop,
+    /// Using a loop with a constant bound, unrolled by the compiler.
+    Unrolled,
+}
+
+/// Parses the Ethernet header and up to `VLAN_MAX_DEPTH` VLAN headers following it. Returns the
+/// protocol of the next header (in host byte order) and the VLAN IDs of the parsed VLAN headers
+/// (outermost first).
+///
+/// If there are more than `VLAN_MAX_DEPTH` VLAN headers, the returned protocol is that of a VLAN
+/// header, so that the caller does not mistake the remaining VLAN headers for the L3 header.
+#[inline(always)]
+pub fn parse_ethhdr_vlan(
+    ctx: &XdpContext,
+    cursor: &mut HdrCursor,
+) -> Option<(u16, [Option<u16>; VLAN_MAX_DEPTH])> {
+    let mut h_proto = parse_ethhdr(ctx, cursor)?;
+    let mut vlans = [None; VLAN_MAX_DEPTH];
+
+    // A 'bounded' loop, since `VLAN_MAX_DEPTH` is a constant the compiler unrolls this loop.
+    for vlan in vlans.iter_mut() {
+        if !proto_is_vlan(h_proto) {
+            break;
+        }
+        let vlh: *const VlanHdr = ptr_at(ctx, cursor)?;
+        cursor.pos += mem::size_of::<VlanHdr>();
+
+        unsafe {
+            *vlan = Some(u16::from_be((*vlh).h_vlan_tci) & 0x0FFF);
+            h_proto = u16::from_be((*vlh).h_vlan_encapsulated_proto);
+        }
+    }
+
+    Some((h_proto, vlans))
+}
+
+/// Parses the IPv4 header (including the options) and returns the protocol of the next header.
+#[inline(always)]
+pub fn parse_iphdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u8> {
+    let iph: *const Ipv4Hdr = ptr_at(ctx, cursor)?;
+
+    // The header length is variable (because of the options), hence it has to be checked that
+    // the header length is sane and that the whole header lies within the packet.
+    let hdrsize = ((unsafe { (*iph).version_ihl } & 0x0F) as usize) * 4;
+    if hdrsize < mem::size_of::<Ipv4Hdr>() {
+        return None;
+    }
+    if cursor.pos + hdrsize > ctx.data_end() {
+        return None;
+    }
+    cursor.pos += hdrsize;
+
+    Some(unsafe { (*iph).protocol })
+}
+
+/// Parses the ICMP header and returns the pointer to the header.
+#[inline(always)]
+pub fn parse_icmphdr(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<*const IcmpHdr> {
+    let icmph: *const IcmpHdr = ptr_at(ctx, cursor)?;
+    cursor.pos += mem::size_of::<IcmpHdr>();
+
+    Some(icmph)
+}
+
+/// Parses the IPv6 extension header of the type `nexthdr` and returns the protocol of the next
+/// header.
+///
+/// Only the first fragment of a packet has the upper-layer header, `None` is returned for the
+/// other fragments.
+#[inline(always)]
+pub fn parse_ip6hdrext(ctx: &XdpContext, cursor: &mut HdrCursor, nexthdr: u8) -> Option<u8> {
+    if nexthdr == IPPROTO_FRAGMENT {
+        let fragh: *const FragHdr = ptr_at(ctx, cursor)?;
+        if u16::from_be(unsafe { (*fragh).frag_off }) & IP6_OFFSET_MASK != 0 {
+            return None;
+        }
+        cursor.pos += mem::size_of::<FragHdr>();
+
+        return Some(unsafe { (*fragh).nexthdr });
+    }
+
+    let opth: *const Ipv6OptHdr = ptr_at(ctx, cursor)?;
+    let hdrsize = (unsafe { (*opth).hdrlen } as usize + 1) * 8;
+    if cursor.pos + hdrsize > ctx.data_end() {
+        return None;
+    }
+    cursor.pos += hdrsize;
+
+    Some(unsafe { (*opth).nexthdr })
+}
+
+/// Skips up to `IPV6_EXT_MAX_CHAIN` IPv6 extension headers, starting with the header of the type
+/// `nexthdr` at the cursor. Returns the protocol of the upper-layer header and advances the cursor
+/// to it.
+///
+/// A 'bounded' loop, like the VLAN headers, which works on all the kernels. The compiler unrolls
+/// the loop, so the size of the program (and the work of the verifier) grows with the bound.
+#[inline(always)]
+pub fn skip_ip6hdrext_unrolled(
+    ctx: &XdpContext,
+    cursor: &mut HdrCursor,
+    mut nexthdr: u8,
+) -> Option<u8> {
+    for _ in 0..IPV6_EXT_MAX_CHAIN {
+        if !proto_is_ip6ext(nexthdr) {
+            return Some(nexthdr);
+        }
+        nexthdr = parse_ip6hdrext(ctx, cursor, nexthdr)?;
+    }
+
+    // More than `IPV6_EXT_MAX_CHAIN` extension headers.
+    if proto_is_ip6ext(nexthdr) {
+        return None;
+    }
+
+    Some(nexthdr)
+}
+
+// The state of `skip_ip6hdrext_loop`, shared with the `bpf_loop` callback.
+#[repr(C)]
+struct Ip6ExtState {
+    ctx: *mut xdp_md,
+    // Offset of the current header from the start of the packet.
+    offset: usize,
+    // Type of the current header.
+    nexthdr: u8,
+}
+
+// Called by `bpf_loop` for every header. Returns `1` to stop the loop (the current header is the
+// upper-layer header, or the extension header could not be parsed), `0` to continue.
+//
+// The verifier does not know how many times the callback is called, so the offset is checked and
+// the pointers to the packet are derived again from the `xdp_md` on every call.
+#[inline(never)]
+extern "C" fn skip_ip6hdrext_callback(_index: u64, state: *mut c_void) -> i64 {
+    let state = unsafe { &mut *(state as *mut Ip6ExtState) };
+    if !proto_is_ip6ext(state.nexthdr) || state.offset > IPV6_EXT_MAX_OFFSET {
+        return 1;
+    }
+
+    let ctx = XdpContext::new(state.ctx);
+    let mut cursor = HdrCursor {
+        pos: ctx.data() + state.offset,
+    };
+    let Some(nexthdr) = parse_ip6hdrext(&ctx, &mut cursor, state.nexthdr) else {
+        return 1;
+    };
+    state.offset = cursor.pos - ctx.data();
+    state.nexthdr = nexthdr;
+
+    0
+}
+
+/// Same as `skip_ip6hdrext_unrolled`, but the loop is run by the `bpf_loop` helper (Linux 5.17
+/// or later), which calls the callback up to the given number of times. The size of the program
+/// does not depend on the number of the iterations.
+#[inline(always)]
+pub fn skip_ip6hdrext_loop(ctx: &XdpContext, cursor: &mut HdrCursor, nexthdr: u8) -> Option<u8> {
+    let mut state = Ip6ExtState {
+        ctx: ctx.ctx,
+        offset: cursor.pos - ctx.data(),
+        nexthdr,
+    };
+
+    // One more call than the number of the headers, to check the header after the last one.
+    let ret = unsafe {
+        bpf_loop(
+            IPV6_EXT_MAX_CHAIN + 1,
+            skip_ip6hdrext_callback as *mut c_void,
+            &mut state as *mut Ip6ExtState as *mut c_void,
+            0,
+        )
+    };
+    if ret < 0 || proto_is_ip6ext(state.nexthdr) || state.offset > IPV6_EXT_MAX_OFFSET {
+        return None;
+    }
+    cursor.pos = ctx.data() + state.offset;
+
+    Some(state.nexthdr)
+}
+
+// Returns the sequence number of the ICMPv6 Echo Request following the IPv6 header and the
+// extension headers (if any).
+#[inline(always)]
+fn icmpv6_echo_sequence(ctx: &XdpContext, cursor: &mut HdrCursor, walk: Ip6ExtWalk) -> Option<u16> {
+    let nexthdr = parse_ip6hdr(ctx, cursor)?;
+    let nexthdr = match walk {
+        Ip6ExtWalk::BpfLoop => skip_ip6hdrext_loop(ctx, cursor, nexthdr)?,
+        Ip6ExtWalk::Unrolled => skip_ip6hdrext_unrolled(ctx, cursor, nexthdr)?,
+    };
+    if nexthdr != IPPROTO_ICMPV6 {
+        return None;
+    }
+
+    let icmp6h = parse_icmp6hdr(ctx, cursor)?;
+    unsafe {
+        if (*icmp6h).icmp6_type != ICMPV6_ECHO_REQUEST {
+            return None;
+        }
+        Some(u16::from_be((*icmp6h).icmp6_sequence))
+    }
+}
+
+// Returns the sequence number of the ICMP Echo Request following the IPv4 header.
+#[inline(always)]
+fn icmp_echo_sequence(ctx: &XdpContext, cursor: &mut HdrCursor) -> Option<u16> {
+    if parse_iphdr(ctx, cursor)? != IPPROTO_ICMP {
+        return None;
+    }
+
+    let icmph = parse_icmphdr(ctx, cursor)?;
+    unsafe {
+        if (*icmph).icmp_type != ICMP_ECHO {
+            return None;
+        }
+        Some(u16::from_be((*icmph).sequence))
+    }
+}
+
+// Same as the `try_{{to_snake_case tutorial_name}}_parser`, but also handles the packets with
+// (upto `VLAN_MAX_DEPTH`) VLAN headers, the IPv4 ICMP Echo Requests and the IPv6 packets with
+// (upto `IPV6_EXT_MAX_CHAIN`) extension headers, skipped as given by `walk`.
+#[inline(always)]
+pub fn try_{{to_snake_case tutorial_name}}_parser_solution(
+    ctx: &XdpContext,
+    walk: Ip6ExtWalk,
+) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some((eth_type, vlans)) = parse_ethhdr_vlan(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if let Some(vlan_id) = vlans[0] {
+        debug!(ctx, "Received a packet on VLAN: {}", vlan_id);
+    }
+
+    let sequence = match eth_type {
+        ETH_P_IPV6 => icmpv6_echo_sequence(ctx, &mut cursor, walk),
+        ETH_P_IP => icmp_echo_sequence(ctx, &mut cursor),
+        _ => None,
+    };
+    let Some(sequence) = sequence else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    if sequence % 2 == 0 {
+        info!(ctx, "Dropping Echo Request with sequence: {}", sequence);
+        Ok(xdp_action::XDP_DROP)
+    } else {
+        debug!(ctx, "Passing Echo Request with sequence: {}", sequence);
+        Ok(xdp_action::XDP_PASS)
+    }
+}
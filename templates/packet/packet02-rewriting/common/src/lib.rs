@@ -0,0 +1,26 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Configuration of the packet rewriting, set by the Userspace program in the `REWRITE_CONFIG`
+/// map and read by the eBPF programs.
+///
+/// All the fields are in the host byte order.
+#[repr(C)]
+#[cfg_attr(feature = "user", derive(Copy, Debug, Clone))]
+pub struct RewriteConfig {
+    /// Destination port (UDP or TCP) of the packets to be rewritten, `0` matches all the ports.
+    pub match_port: u16,
+
+    /// New destination port of the rewritten packets.
+    pub new_port: u16,
+
+    /// VLAN ID of the tag to be pushed or of the tag to be popped (`0` pops any tag).
+    pub vlan_id: u16,
+
+    pub _pad: u16,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for RewriteConfig {}
@@ -0,0 +1,142 @@
+use anyhow::Context;
+
+use aya::maps::Array;
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
+use tokio::signal;
+
+use {{ to_snake_case tutorial_name }}_common::RewriteConfig;
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Rewriting to be performed on the received packets.
+    #[clap(short, long)]
+    mode: RewriteMode,
+
+    /// Destination port (UDP or TCP) of the packets to be rewritten (default: all ports).
+    #[clap(long, default_value_t = 0)]
+    match_port: u16,
+
+    /// New destination port of the rewritten packets (required for 'port-rewrite').
+    #[clap(long, required_if_eq("mode", "port-rewrite"))]
+    new_port: Option<u16>,
+
+    /// VLAN ID of the tag to be pushed ('vlan-push') or popped ('vlan-pop', default: any tag).
+    #[clap(long)]
+    vlan_id: Option<u16>,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum RewriteMode {
+    /// Rewrite the destination port of the UDP and TCP packets
+    PortRewrite,
+
+    /// Push a VLAN tag on the packets without a VLAN tag
+    VlanPush,
+
+    /// Pop the outermost VLAN tag of the packets
+    VlanPop,
+}
+
+fn program_from_mode(mode: &RewriteMode) -> &str {
+    match mode {
+        RewriteMode::PortRewrite => "{{to_snake_case tutorial_name}}_port_rewrite",
+        RewriteMode::VlanPush => "{{to_snake_case tutorial_name}}_vlan_push",
+        RewriteMode::VlanPop => "{{to_snake_case tutorial_name}}_vlan_pop",
+    }
+}
+
+fn rewrite_config_from_opts(opts: &Opt) -> Result<RewriteConfig, anyhow::Error> {
+    let vlan_id = match (&opts.mode, opts.vlan_id) {
+        // The first VLAN ID used by the `testenv` scripts.
+        (RewriteMode::VlanPush, None) => 1,
+        (_, vlan_id) => vlan_id.unwrap_or_default(),
+    };
+    if vlan_id > 4094 {
+        return Err(anyhow::Error::msg(format!(
+            "Invalid VLAN ID: {vlan_id}, VLAN ID should be between 1 and 4094."
+        )));
+    }
+    if let (RewriteMode::VlanPush, 0) = (&opts.mode, vlan_id) {
+        return Err(anyhow::Error::msg("VLAN ID of the tag to be pushed can't be 0."));
+    }
+
+    Ok(RewriteConfig {
+        match_port: opts.match_port,
+        new_port: opts.new_port.unwrap_or_default(),
+        vlan_id,
+        _pad: 0,
+    })
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// Every rewrite mode is implemented by a separate program in the eBPF binary, the parameters for
+// the rewriting (ports and VLAN ID) are set in the `REWRITE_CONFIG` map before the program is
+// attached.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let config = rewrite_config_from_opts(&opts)?;
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut rewrite_config = Array::try_from(bpf.map_mut("REWRITE_CONFIG").unwrap())?;
+    rewrite_config.set(0, config, 0)?;
+    info!("Rewrite configuration: {:?}", config);
+
+    let program_name = program_from_mode(&opts.mode);
+    let xdp_program = bpf.program_mut(program_name);
+
+    if let Some(xdp_program) = xdp_program {
+        let xdp: &mut Xdp = xdp_program.try_into()?;
+        xdp.load()?;
+        let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+        if let Err(e) = EbpfLogger::init(&mut bpf) {
+            // This can happen if you remove all log statements from your eBPF program.
+            warn!("failed to initialize eBPF logger: {}", e);
+        }
+
+        info!(
+            "XDP Program '{}' attached to '{}'! Now waiting for Ctrl-C",
+            program_name, &opts.iface
+        );
+        signal::ctrl_c().await?;
+        info!("Exiting...");
+
+        Ok(())
+    } else {
+        let mut progs = vec![];
+        for (name, _program_type) in bpf.programs() {
+            progs.push(name);
+        }
+        Err(anyhow::Error::msg(format!(
+            "Unable to find the program '{}' in the loaded file '{}'. Available programs are: {}",
+            program_name,
+            opts.file,
+            progs.join(", "),
+        )))
+    }
+}
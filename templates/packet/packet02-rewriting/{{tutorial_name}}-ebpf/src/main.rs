@@ -0,0 +1,187 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+mod rewrite_helpers;
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    macros::{map, xdp},
+    maps::Array,
+    programs::XdpContext,
+};
+use aya_log_ebpf::{debug, error};
+
+use parsing_helpers::{
+    parse_ethhdr, parse_ethhdr_vlan, parse_ip6hdr, parse_iphdr, parse_tcphdr, parse_udphdr,
+    proto_is_vlan, ptr_at, HdrCursor, VlanHdr, ETH_P_IP, ETH_P_IPV6, IPPROTO_TCP, IPPROTO_UDP,
+};
+use rewrite_helpers::{csum_replace2, vlan_tag_pop, vlan_tag_push};
+
+use {{ to_snake_case tutorial_name }}_common::RewriteConfig;
+
+// The configuration is set by the Userspace program before the program is attached.
+#[map]
+static REWRITE_CONFIG: Array<RewriteConfig> = Array::<RewriteConfig>::with_max_entries(1, 0);
+
+#[inline(always)]
+fn rewrite_config(ctx: &XdpContext) -> Result<&'static RewriteConfig, u32> {
+    match REWRITE_CONFIG.get(0) {
+        Some(config) => Ok(config),
+        None => {
+            error!(ctx, "Rewrite configuration not found in the map!");
+            Err(xdp_action::XDP_ABORTED)
+        }
+    }
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_port_rewrite(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_port_rewrite(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Rewrites the destination port of the UDP and TCP packets matching the `match_port` to the
+// `new_port`. The checksum in the UDP/TCP header is updated 'incrementally' for the changed port,
+// instead of computing the checksum over the whole packet again.
+fn try_{{to_snake_case tutorial_name}}_port_rewrite(ctx: &XdpContext) -> Result<u32, u32> {
+    let config = rewrite_config(ctx)?;
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some((_, eth_type)) = parse_ethhdr_vlan(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let ip_type = match eth_type {
+        ETH_P_IP => {
+            let Some(iph) = parse_iphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            // Only the first fragment has the UDP/TCP header, the bytes at the 'port' in the
+            // other fragments are the payload (the fragment offset is in the lower 13 bits).
+            if u16::from_be(unsafe { (*iph).frag_off }) & 0x1fff != 0 {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            Some(unsafe { (*iph).protocol })
+        }
+        ETH_P_IPV6 => parse_ip6hdr(ctx, &mut cursor).map(|ip6h| unsafe { (*ip6h).nexthdr }),
+        _ => None,
+    };
+
+    let new_port = config.new_port.to_be();
+    match ip_type {
+        Some(IPPROTO_UDP) => {
+            let Some(udph) = parse_udphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            let old_port = unsafe { (*udph).dest };
+            if config.match_port != 0 && u16::from_be(old_port) != config.match_port {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            unsafe {
+                (*udph).dest = new_port;
+                // A zero checksum means 'no checksum' for UDP over IPv4, which is left as it is.
+                // A computed checksum of zero is transmitted as all ones.
+                if (*udph).check != 0 {
+                    let check = csum_replace2((*udph).check, old_port, new_port);
+                    (*udph).check = if check == 0 { 0xFFFF } else { check };
+                }
+            }
+            debug!(ctx, "UDP destination port rewritten to: {}", config.new_port);
+        }
+        Some(IPPROTO_TCP) => {
+            let Some(tcph) = parse_tcphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            let old_port = unsafe { (*tcph).dest };
+            if config.match_port != 0 && u16::from_be(old_port) != config.match_port {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            unsafe {
+                (*tcph).dest = new_port;
+                (*tcph).check = csum_replace2((*tcph).check, old_port, new_port);
+            }
+            debug!(ctx, "TCP destination port rewritten to: {}", config.new_port);
+        }
+        _ => {}
+    }
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_vlan_push(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_vlan_push(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Pushes a VLAN tag with the configured VLAN ID on the packets without a VLAN tag.
+fn try_{{to_snake_case tutorial_name}}_vlan_push(ctx: &XdpContext) -> Result<u32, u32> {
+    let config = rewrite_config(ctx)?;
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if proto_is_vlan(u16::from_be(unsafe { (*eth).h_proto })) {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    match vlan_tag_push(ctx, eth, config.vlan_id) {
+        Some(()) => {
+            debug!(ctx, "Pushed VLAN tag: {}", config.vlan_id);
+            Ok(xdp_action::XDP_PASS)
+        }
+        None => {
+            error!(ctx, "Failed to push VLAN tag: {}", config.vlan_id);
+            Err(xdp_action::XDP_ABORTED)
+        }
+    }
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_vlan_pop(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_vlan_pop(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Pops the outermost VLAN tag, if the VLAN ID of the tag is the configured VLAN ID (or the
+// configured VLAN ID is `0`).
+fn try_{{to_snake_case tutorial_name}}_vlan_pop(ctx: &XdpContext) -> Result<u32, u32> {
+    let config = rewrite_config(ctx)?;
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    if !proto_is_vlan(u16::from_be(unsafe { (*eth).h_proto })) {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    // The VLAN ID is checked before popping the tag, since the tag can't be put back.
+    if config.vlan_id != 0 {
+        let Some(vlh) = ptr_at::<VlanHdr>(ctx, &cursor) else {
+            return Ok(xdp_action::XDP_PASS);
+        };
+        if u16::from_be(unsafe { (*vlh).h_vlan_tci }) & 0x0FFF != config.vlan_id {
+            return Ok(xdp_action::XDP_PASS);
+        }
+    }
+
+    if let Some(vlan_id) = vlan_tag_pop(ctx, eth) {
+        debug!(ctx, "Popped VLAN tag: {}", vlan_id);
+    }
+
+    Ok(xdp_action::XDP_PASS)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
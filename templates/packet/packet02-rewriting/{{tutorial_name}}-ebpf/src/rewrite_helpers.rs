@@ -0,0 +1,94 @@
+// Helpers for rewriting the packets.
+//
+// These are similar to the helpers in `rewrite_helpers.h` in the original XDP tutorial.
+
+use core::mem;
+
+use aya_ebpf::{helpers::bpf_xdp_adjust_head, programs::XdpContext};
+
+use crate::parsing_helpers::{proto_is_vlan, EthHdr, VlanHdr, ETH_P_8021Q};
+
+/// Updates the Internet checksum `check` when a 16 bit word in the checksummed data is changed
+/// from `old` to `new` (See RFC 1624).
+///
+/// All the values are in the network byte order, the one's complement sum does not depend on the
+/// byte order, as long as all the values are in the same byte order.
+#[inline(always)]
+pub fn csum_replace2(check: u16, old: u16, new: u16) -> u16 {
+    let mut sum = (!check as u32) + (!old as u32) + (new as u32);
+    sum = (sum & 0xFFFF) + (sum >> 16);
+    sum = (sum & 0xFFFF) + (sum >> 16);
+
+    !(sum as u16)
+}
+
+/// Removes the outermost VLAN tag from the packet and returns the VLAN ID of the removed tag.
+///
+/// The Ethernet header is copied, the start of the packet is moved forward by the size of the
+/// VLAN header using `bpf_xdp_adjust_head` and the copy of the Ethernet header is written back at
+/// the new start of the packet.
+#[inline(always)]
+pub fn vlan_tag_pop(ctx: &XdpContext, eth: *mut EthHdr) -> Option<u16> {
+    let h_proto = u16::from_be(unsafe { (*eth).h_proto });
+    if !proto_is_vlan(h_proto) {
+        return None;
+    }
+
+    let vlh = (eth as usize + mem::size_of::<EthHdr>()) as *const VlanHdr;
+    // Still need to do the bounds checking.
+    if vlh as usize + mem::size_of::<VlanHdr>() > ctx.data_end() {
+        return None;
+    }
+
+    let (vlan_id, encapsulated_proto, mut eth_copy) = unsafe {
+        (
+            u16::from_be((*vlh).h_vlan_tci) & 0x0FFF,
+            (*vlh).h_vlan_encapsulated_proto,
+            *eth,
+        )
+    };
+
+    if unsafe { bpf_xdp_adjust_head(ctx.ctx, mem::size_of::<VlanHdr>() as i32) } != 0 {
+        return None;
+    }
+
+    // After adjusting the head, all the pointers to the packet are invalid. `data` and `data_end`
+    // have to be read again and the bounds checking has to be done again.
+    let eth = ctx.data() as *mut EthHdr;
+    if ctx.data() + mem::size_of::<EthHdr>() > ctx.data_end() {
+        return None;
+    }
+
+    eth_copy.h_proto = encapsulated_proto;
+    unsafe { *eth = eth_copy };
+
+    Some(vlan_id)
+}
+
+/// Adds an 802.1Q VLAN tag with the given VLAN ID as the outermost tag of the packet.
+///
+/// This is the reverse of `vlan_tag_pop`, the start of the packet is moved backwards by the size
+/// of the VLAN header, to make room for the VLAN header.
+#[inline(always)]
+pub fn vlan_tag_push(ctx: &XdpContext, eth: *mut EthHdr, vlan_id: u16) -> Option<()> {
+    let mut eth_copy = unsafe { *eth };
+
+    if unsafe { bpf_xdp_adjust_head(ctx.ctx, -(mem::size_of::<VlanHdr>() as i32)) } != 0 {
+        return None;
+    }
+
+    let eth = ctx.data() as *mut EthHdr;
+    let vlh = (ctx.data() + mem::size_of::<EthHdr>()) as *mut VlanHdr;
+    if ctx.data() + mem::size_of::<EthHdr>() + mem::size_of::<VlanHdr>() > ctx.data_end() {
+        return None;
+    }
+
+    unsafe {
+        (*vlh).h_vlan_tci = (vlan_id & 0x0FFF).to_be();
+        (*vlh).h_vlan_encapsulated_proto = eth_copy.h_proto;
+    }
+    eth_copy.h_proto = ETH_P_8021Q.to_be();
+    unsafe { *eth = eth_copy };
+
+    Some(())
+}
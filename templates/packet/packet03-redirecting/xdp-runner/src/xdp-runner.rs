@@ -0,0 +1,183 @@
+use std::ffi::CString;
+
+use anyhow::Context;
+
+use aya::maps::{DevMap, HashMap};
+use aya::programs::{Xdp, XdpFlags};
+use aya::Ebpf;
+use aya_log::EbpfLogger;
+
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
+use tokio::signal;
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Action to be performed on the received packets.
+    #[clap(short, long)]
+    mode: RedirectMode,
+
+    /// Interface to which the packets are redirected ('redirect'). In the 'router' mode, the
+    /// program is also attached to this interface, so that the packets are routed both ways.
+    #[clap(short, long, required_if_eq("mode", "redirect"))]
+    redirect_iface: Option<String>,
+
+    /// Source MAC address of the packets to be redirected (required for 'redirect').
+    #[clap(long, required_if_eq("mode", "redirect"))]
+    src_mac: Option<String>,
+
+    /// Destination MAC address of the redirected packets (required for 'redirect').
+    #[clap(long, required_if_eq("mode", "redirect"))]
+    dest_mac: Option<String>,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum RedirectMode {
+    /// Respond to the ICMP echo requests using `XDP_TX`
+    IcmpEcho,
+
+    /// Redirect the packets to another interface using the `TX_PORT` map
+    Redirect,
+
+    /// Forward the packets using the kernel routing table (`bpf_fib_lookup`)
+    Router,
+}
+
+fn program_from_mode(mode: &RedirectMode) -> &str {
+    match mode {
+        RedirectMode::IcmpEcho => "{{to_snake_case tutorial_name}}_icmp_echo",
+        RedirectMode::Redirect => "{{to_snake_case tutorial_name}}_redirect",
+        RedirectMode::Router => "{{to_snake_case tutorial_name}}_router",
+    }
+}
+
+// Parses a MAC address in the usual `aa:bb:cc:dd:ee:ff` format.
+fn parse_mac(mac: &str) -> Result<[u8; 6], anyhow::Error> {
+    let mut addr = [0u8; 6];
+    let mut octets = mac.split(':');
+    for octet in addr.iter_mut() {
+        let value = octets
+            .next()
+            .ok_or_else(|| anyhow::Error::msg(format!("Invalid MAC address: '{mac}'")))?;
+        *octet = u8::from_str_radix(value, 16)
+            .with_context(|| format!("Invalid MAC address: '{mac}'"))?;
+    }
+    if octets.next().is_some() {
+        return Err(anyhow::Error::msg(format!("Invalid MAC address: '{mac}'")));
+    }
+
+    Ok(addr)
+}
+
+// Returns the interface index of the interface with the given name.
+fn ifindex_from_name(iface: &str) -> Result<u32, anyhow::Error> {
+    let name = CString::new(iface)?;
+    let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+    if ifindex == 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Interface '{iface}' not found"));
+    }
+
+    Ok(ifindex)
+}
+
+// Sets the interface to which the packets are redirected in the `TX_PORT` map and the source and
+// destination MAC addresses in the `REDIRECT_PARAMS` map.
+fn populate_redirect_maps(bpf: &mut Ebpf, opts: &Opt) -> Result<(), anyhow::Error> {
+    // These are `Some` for the 'redirect' mode, ensured by the `clap` parser.
+    let redirect_iface = opts.redirect_iface.as_deref().unwrap();
+    let src_mac = parse_mac(opts.src_mac.as_deref().unwrap())?;
+    let dest_mac = parse_mac(opts.dest_mac.as_deref().unwrap())?;
+
+    let ifindex = ifindex_from_name(redirect_iface)?;
+    let mut tx_port = DevMap::try_from(bpf.map_mut("TX_PORT").unwrap())?;
+    tx_port.set(0, ifindex, None, 0)?;
+
+    let mut redirect_params: HashMap<_, [u8; 6], [u8; 6]> =
+        HashMap::try_from(bpf.map_mut("REDIRECT_PARAMS").unwrap())?;
+    redirect_params.insert(src_mac, dest_mac, 0)?;
+
+    info!(
+        "Redirecting packets from '{}' to '{}' (ifindex: {}) with destination MAC: {}",
+        opts.src_mac.as_deref().unwrap(),
+        redirect_iface,
+        ifindex,
+        opts.dest_mac.as_deref().unwrap(),
+    );
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// Every mode is implemented by a separate program in the eBPF binary. For the 'redirect' mode,
+// the maps used by the program are populated from the interface name and the MAC addresses given
+// on the command line before the program is attached.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    if let RedirectMode::Redirect = opts.mode {
+        populate_redirect_maps(&mut bpf, &opts)?;
+    }
+
+    let mut ifaces = vec![opts.iface.as_str()];
+    if let (RedirectMode::Router, Some(redirect_iface)) = (&opts.mode, &opts.redirect_iface) {
+        ifaces.push(redirect_iface);
+    }
+
+    let program_name = program_from_mode(&opts.mode);
+    let xdp_program = bpf.program_mut(program_name);
+
+    if let Some(xdp_program) = xdp_program {
+        let xdp: &mut Xdp = xdp_program.try_into()?;
+        xdp.load()?;
+        for iface in &ifaces {
+            let _linkid = xdp
+            .attach(iface, XdpFlags::default())
+            .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+        }
+
+        if let Err(e) = EbpfLogger::init(&mut bpf) {
+            // This can happen if you remove all log statements from your eBPF program.
+            warn!("failed to initialize eBPF logger: {}", e);
+        }
+
+        info!(
+            "XDP Program '{}' attached to '{}'! Now waiting for Ctrl-C",
+            program_name,
+            ifaces.join("', '")
+        );
+        signal::ctrl_c().await?;
+        info!("Exiting...");
+
+        Ok(())
+    } else {
+        let mut progs = vec![];
+        for (name, _program_type) in bpf.programs() {
+            progs.push(name);
+        }
+        Err(anyhow::Error::msg(format!(
+            "Unable to find the program '{}' in the loaded file '{}'. Available programs are: {}",
+            program_name,
+            opts.file,
+            progs.join(", "),
+        )))
+    }
+}
@@ -0,0 +1,281 @@
+#![no_std]
+#![no_main]
+
+mod parsing_helpers;
+mod rewrite_helpers;
+
+use core::{ffi::c_void, mem};
+
+use aya_ebpf::{
+    bindings::{bpf_fib_lookup as bpf_fib_lookup_t, xdp_action},
+    helpers::{bpf_fib_lookup, bpf_redirect},
+    macros::{map, xdp},
+    maps::{DevMap, HashMap},
+    programs::XdpContext,
+};
+use aya_log_ebpf::debug;
+
+use parsing_helpers::{
+    parse_ethhdr, parse_icmphdr_common, parse_ip6hdr, parse_iphdr, HdrCursor, ETH_P_IP,
+    ETH_P_IPV6, ICMPV6_ECHO_REPLY, ICMPV6_ECHO_REQUEST, ICMP_ECHO, ICMP_ECHOREPLY, IPPROTO_ICMP,
+    IPPROTO_ICMPV6,
+};
+use rewrite_helpers::{
+    csum_replace2, ip_decrease_ttl, swap_src_dst_ipv4, swap_src_dst_ipv6, swap_src_dst_mac,
+};
+
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+
+// Return values of the `bpf_fib_lookup` helper.
+const BPF_FIB_LKUP_RET_SUCCESS: i64 = 0;
+const BPF_FIB_LKUP_RET_BLACKHOLE: i64 = 1;
+const BPF_FIB_LKUP_RET_UNREACHABLE: i64 = 2;
+const BPF_FIB_LKUP_RET_PROHIBIT: i64 = 3;
+
+// The interface to which the packets are redirected by the `redirect` program, set by the
+// Userspace program at index `0`.
+#[map]
+static TX_PORT: DevMap = DevMap::with_max_entries(1, 0);
+
+// Source MAC address of the received packets to the destination MAC address of the redirected
+// packets, set by the Userspace program.
+#[map]
+static REDIRECT_PARAMS: HashMap<[u8; 6], [u8; 6]> = HashMap::with_max_entries(1, 0);
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_icmp_echo(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_icmp_echo(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Responds to the ICMP and ICMPv6 echo requests, by turning the request into a reply and sending
+// it back on the same interface using `XDP_TX`.
+//
+// The source and the destination addresses (MAC and IP) are swapped and the ICMP type is changed
+// to the echo reply. Only the ICMP checksum has to be updated for the changed type, swapping the
+// addresses does not change the IPv4 header checksum or the ICMPv6 pseudo-header checksum.
+fn try_{{to_snake_case tutorial_name}}_icmp_echo(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let (mut iph, mut ip6h) = (None, None);
+    let (echo_request, echo_reply) = match u16::from_be(unsafe { (*eth).h_proto }) {
+        ETH_P_IP => {
+            let Some(ip) = parse_iphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            if unsafe { (*ip).protocol } != IPPROTO_ICMP {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            iph = Some(ip);
+            (ICMP_ECHO, ICMP_ECHOREPLY)
+        }
+        ETH_P_IPV6 => {
+            let Some(ip6) = parse_ip6hdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            if unsafe { (*ip6).nexthdr } != IPPROTO_ICMPV6 {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            ip6h = Some(ip6);
+            (ICMPV6_ECHO_REQUEST, ICMPV6_ECHO_REPLY)
+        }
+        _ => return Ok(xdp_action::XDP_PASS),
+    };
+
+    let Some(icmph) = parse_icmphdr_common(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    let (type_, code) = unsafe { ((*icmph).type_, (*icmph).code) };
+    if type_ != echo_request {
+        return Ok(xdp_action::XDP_PASS);
+    }
+
+    if let Some(iph) = iph {
+        swap_src_dst_ipv4(iph);
+    }
+    if let Some(ip6h) = ip6h {
+        swap_src_dst_ipv6(ip6h);
+    }
+    swap_src_dst_mac(eth);
+
+    // The type and the code together make up the first 16 bit word of the ICMP header.
+    let old = u16::from_ne_bytes([type_, code]);
+    let new = u16::from_ne_bytes([echo_reply, code]);
+    unsafe {
+        (*icmph).type_ = echo_reply;
+        (*icmph).cksum = csum_replace2((*icmph).cksum, old, new);
+    }
+    debug!(
+        ctx,
+        "Echo reply sent, sequence: {}",
+        u16::from_be(unsafe { (*icmph).sequence })
+    );
+
+    Ok(xdp_action::XDP_TX)
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_redirect(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_redirect(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Redirects the packets from the source MAC address in the `REDIRECT_PARAMS` map to the interface
+// in the `TX_PORT` map, after rewriting the destination MAC address.
+fn try_{{to_snake_case tutorial_name}}_redirect(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let h_source = unsafe { (*eth).h_source };
+    let Some(dest_mac) = (unsafe { REDIRECT_PARAMS.get(&h_source) }) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+    unsafe { (*eth).h_dest = *dest_mac };
+
+    // `XDP_ABORTED` is returned as the action if the interface is not set in the map.
+    Ok(TX_PORT.redirect(0, 0).unwrap_or(xdp_action::XDP_ABORTED))
+}
+
+/// Parameters for the `bpf_fib_lookup` helper.
+///
+/// This has the same layout as the `struct bpf_fib_lookup` in the kernel (64 bytes), with the
+/// anonymous unions replaced by the fields used in this program. The addresses are in the network
+/// byte order, IPv4 addresses use the first element of the `src` and `dst` arrays.
+#[repr(C)]
+#[derive(Default)]
+struct FibLookup {
+    family: u8,
+    l4_protocol: u8,
+    sport: u16,
+    dport: u16,
+    tot_len: u16,
+    /// Input: Interface the packet is received on, Output: Interface to forward the packet to.
+    ifindex: u32,
+    /// IPv4 TOS (first byte) or IPv6 Flow Info.
+    tos_flowinfo: [u8; 4],
+    src: [u32; 4],
+    dst: [u32; 4],
+    h_vlan_proto: u16,
+    h_vlan_tci: u16,
+    smac: [u8; 6],
+    dmac: [u8; 6],
+}
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}_router(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}_router(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Forwards the IPv4 and IPv6 packets using the routing table (and the neighbour table) of the
+// kernel, looked up using the `bpf_fib_lookup` helper.
+//
+// The packets for which the lookup does not succeed (eg. the packets for the local host or the
+// packets for which the neighbour is not yet resolved) are passed to the kernel network stack.
+fn try_{{to_snake_case tutorial_name}}_router(ctx: &XdpContext) -> Result<u32, u32> {
+    let mut cursor = HdrCursor::new(ctx);
+
+    let Some(eth) = parse_ethhdr(ctx, &mut cursor) else {
+        return Ok(xdp_action::XDP_PASS);
+    };
+
+    let mut params = FibLookup::default();
+    let (mut iph, mut ip6h) = (None, None);
+    match u16::from_be(unsafe { (*eth).h_proto }) {
+        ETH_P_IP => {
+            let Some(ip) = parse_iphdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            // Packets with an expiring TTL are left for the kernel, to send the ICMP error.
+            if unsafe { (*ip).ttl } <= 1 {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            unsafe {
+                params.family = AF_INET;
+                params.tos_flowinfo[0] = (*ip).tos;
+                params.l4_protocol = (*ip).protocol;
+                params.tot_len = u16::from_be((*ip).tot_len);
+                params.src[0] = (*ip).saddr;
+                params.dst[0] = (*ip).daddr;
+            }
+            iph = Some(ip);
+        }
+        ETH_P_IPV6 => {
+            let Some(ip6) = parse_ip6hdr(ctx, &mut cursor) else {
+                return Ok(xdp_action::XDP_PASS);
+            };
+            if unsafe { (*ip6).hop_limit } <= 1 {
+                return Ok(xdp_action::XDP_PASS);
+            }
+            unsafe {
+                params.family = AF_INET6;
+                // Traffic class and flow label, without the version.
+                params.tos_flowinfo = [
+                    (*ip6).priority_version & 0x0F,
+                    (*ip6).flow_lbl[0],
+                    (*ip6).flow_lbl[1],
+                    (*ip6).flow_lbl[2],
+                ];
+                params.l4_protocol = (*ip6).nexthdr;
+                params.tot_len = u16::from_be((*ip6).payload_len);
+                params.src = mem::transmute::<[u8; 16], [u32; 4]>((*ip6).saddr);
+                params.dst = mem::transmute::<[u8; 16], [u32; 4]>((*ip6).daddr);
+            }
+            ip6h = Some(ip6);
+        }
+        _ => return Ok(xdp_action::XDP_PASS),
+    }
+    params.ifindex = unsafe { (*ctx.ctx).ingress_ifindex };
+
+    let rc = unsafe {
+        bpf_fib_lookup(
+            ctx.ctx as *mut c_void,
+            &mut params as *mut FibLookup as *mut bpf_fib_lookup_t,
+            mem::size_of::<FibLookup>() as i32,
+            0,
+        )
+    };
+    match rc {
+        BPF_FIB_LKUP_RET_SUCCESS => {}
+        BPF_FIB_LKUP_RET_BLACKHOLE | BPF_FIB_LKUP_RET_UNREACHABLE | BPF_FIB_LKUP_RET_PROHIBIT => {
+            return Ok(xdp_action::XDP_DROP);
+        }
+        // Not forwarded, forwarding disabled, neighbour not resolved, fragmentation needed etc.
+        _ => return Ok(xdp_action::XDP_PASS),
+    }
+
+    // The packet is forwarded, decrement the TTL (or the hop limit) and rewrite the MAC addresses
+    // with the ones from the lookup.
+    if let Some(iph) = iph {
+        ip_decrease_ttl(iph);
+    }
+    if let Some(ip6h) = ip6h {
+        unsafe { (*ip6h).hop_limit -= 1 };
+    }
+    unsafe {
+        (*eth).h_dest = params.dmac;
+        (*eth).h_source = params.smac;
+    }
+    debug!(ctx, "Packet forwarded to interface: {}", params.ifindex);
+
+    Ok(unsafe { bpf_redirect(params.ifindex, 0) } as u32)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
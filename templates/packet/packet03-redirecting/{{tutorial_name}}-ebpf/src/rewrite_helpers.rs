@@ -0,0 +1,58 @@
+// Helpers for rewriting the packets.
+//
+// `csum_replace2` is from the 'packet02-rewriting' tutorial, the helpers for swapping the
+// addresses are similar to the ones in `rewrite_helpers.h` in the original XDP tutorial.
+
+use core::mem;
+
+use crate::parsing_helpers::{EthHdr, Ipv4Hdr, Ipv6Hdr};
+
+/// Updates the Internet checksum `check` when a 16 bit word in the checksummed data is changed
+/// from `old` to `new` (See RFC 1624).
+///
+/// All the values are in the network byte order, the one's complement sum does not depend on the
+/// byte order, as long as all the values are in the same byte order.
+#[inline(always)]
+pub fn csum_replace2(check: u16, old: u16, new: u16) -> u16 {
+    let mut sum = (!check as u32) + (!old as u32) + (new as u32);
+    sum = (sum & 0xFFFF) + (sum >> 16);
+    sum = (sum & 0xFFFF) + (sum >> 16);
+
+    !(sum as u16)
+}
+
+/// Swaps the source and the destination MAC addresses in the Ethernet header.
+#[inline(always)]
+pub fn swap_src_dst_mac(eth: *mut EthHdr) {
+    unsafe { mem::swap(&mut (*eth).h_source, &mut (*eth).h_dest) };
+}
+
+/// Swaps the source and the destination addresses in the IPv4 header.
+///
+/// The IPv4 header checksum does not change, since the sum of the header is the same.
+#[inline(always)]
+pub fn swap_src_dst_ipv4(iph: *mut Ipv4Hdr) {
+    unsafe { mem::swap(&mut (*iph).saddr, &mut (*iph).daddr) };
+}
+
+/// Swaps the source and the destination addresses in the IPv6 header.
+#[inline(always)]
+pub fn swap_src_dst_ipv6(ip6h: *mut Ipv6Hdr) {
+    unsafe { mem::swap(&mut (*ip6h).saddr, &mut (*ip6h).daddr) };
+}
+
+/// Decrements the TTL in the IPv4 header and updates the header checksum. Returns the new TTL.
+///
+/// This is the same as `ip_decrease_ttl` in the kernel, the TTL is the upper byte of the 16 bit
+/// word it shares with the protocol, hence `0x0100` is added to the checksum.
+#[inline(always)]
+pub fn ip_decrease_ttl(iph: *mut Ipv4Hdr) -> u8 {
+    unsafe {
+        let mut check = (*iph).check as u32;
+        check += 0x0100u16.to_be() as u32;
+        (*iph).check = (check + (check >= 0xFFFF) as u32) as u16;
+        (*iph).ttl -= 1;
+
+        (*iph).ttl
+    }
+}
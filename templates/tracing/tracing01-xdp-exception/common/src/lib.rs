@@ -0,0 +1,24 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Key of the `XDP_EXCEPTIONS` map, the exceptions are counted per interface and action.
+///
+/// This structure will be shared by the Userspace and eBPF code, hence the `#[repr(C)]`, so that
+/// the layout of the structure is same for both.
+#[repr(C)]
+#[cfg_attr(
+    feature = "user",
+    derive(Copy, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)
+)]
+pub struct ExceptionKey {
+    /// Index of the interface on which the exception occurred.
+    pub ifindex: u32,
+
+    /// The `xdp_action` returned by the program (or attempted by the driver).
+    pub action: u32,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for ExceptionKey {}
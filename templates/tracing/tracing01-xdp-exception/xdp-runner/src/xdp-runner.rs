@@ -0,0 +1,139 @@
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::time::Duration;
+
+use anyhow::Context;
+
+use aya::maps::{MapData, PerCpuHashMap};
+use aya::programs::TracePoint;
+use aya::Ebpf;
+
+use clap::Parser;
+use log::info;
+use tokio::{signal, time};
+
+use {{ to_snake_case tutorial_name }}_common::ExceptionKey;
+
+#[derive(Debug, Parser)]
+struct Opt {
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    /// Interval (in seconds) between two successive reads of the counters.
+    #[clap(long, default_value_t = 2)]
+    interval: u64,
+
+    #[clap(long)]
+    release: bool,
+}
+
+// Names of the XDP Actions. The index in this array is the value of the action.
+const XDP_ACTION_NAMES: [&str; 5] = ["aborted", "drop", "pass", "tx", "redirect"];
+
+fn action_name(action: u32) -> String {
+    match XDP_ACTION_NAMES.get(action as usize) {
+        Some(name) => name.to_string(),
+        None => format!("unknown({action})"),
+    }
+}
+
+// Returns the name of the interface with the given index. The interface may have been removed
+// since the exception was counted, in which case the index is returned as the name.
+fn iface_name(ifindex: u32) -> String {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let name = unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) };
+    if name.is_null() {
+        return format!("ifindex({ifindex})");
+    }
+
+    unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Reads the counters from the map, summing the values for all the CPUs.
+fn collect_exceptions(
+    exceptions: &PerCpuHashMap<MapData, ExceptionKey, u64>,
+) -> anyhow::Result<BTreeMap<ExceptionKey, u64>> {
+    let mut counts = BTreeMap::new();
+    for entry in exceptions.iter() {
+        let (key, values) = entry.context("Failed to read the exceptions map")?;
+        counts.insert(key, values.iter().sum());
+    }
+
+    Ok(counts)
+}
+
+fn print_exceptions(prev: &BTreeMap<ExceptionKey, u64>, curr: &BTreeMap<ExceptionKey, u64>) {
+    if curr.is_empty() {
+        info!("No XDP exceptions so far.");
+        return;
+    }
+
+    for (key, count) in curr {
+        let new = count - prev.get(key).copied().unwrap_or_default();
+        info!(
+            "iface: {:<16} action: {:<12} exceptions: {:>10} (+{})",
+            iface_name(key.ifindex),
+            action_name(key.action),
+            count,
+            new
+        );
+    }
+}
+
+// This is a Userspace program that is responsible for 'installing' the eBPF binary in the kernel
+// and attaching the tracepoint program to the `xdp:xdp_exception` tracepoint.
+//
+// Unlike the XDP programs in the previous tutorials, the tracepoint program is not attached to an
+// interface, it sees the exceptions of all the XDP programs on all the interfaces. The counters are
+// read from the `XDP_EXCEPTIONS` map and displayed every `--interval` seconds.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    if opts.interval == 0 {
+        return Err(anyhow::Error::msg("Interval should be at least 1 second."));
+    }
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let program: &mut TracePoint = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    program.load()?;
+    program
+        .attach("xdp", "xdp_exception")
+        .context("Failed to attach the program to the `xdp:xdp_exception` tracepoint")?;
+
+    let exceptions: PerCpuHashMap<_, ExceptionKey, u64> =
+        PerCpuHashMap::try_from(bpf.take_map("XDP_EXCEPTIONS").unwrap())?;
+
+    info!("Counting the XDP exceptions! Now waiting for Ctrl-C");
+
+    let period = Duration::from_secs(opts.interval);
+    let mut poller_interval = time::interval_at(time::Instant::now() + period, period);
+    let mut prev = BTreeMap::new();
+
+    loop {
+        tokio::select! {
+            _ = poller_interval.tick() => {
+                let curr = collect_exceptions(&exceptions)?;
+                print_exceptions(&prev, &curr);
+                prev = curr;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
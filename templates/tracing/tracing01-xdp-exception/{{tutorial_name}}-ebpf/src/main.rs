@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+
+use aya_ebpf::{
+    macros::{map, tracepoint},
+    maps::PerCpuHashMap,
+    programs::TracePointContext,
+};
+
+use {{ to_snake_case tutorial_name }}_common::ExceptionKey;
+
+// Offsets of the fields in the `xdp:xdp_exception` tracepoint record, these can be found in
+// `/sys/kernel/tracing/events/xdp/xdp_exception/format`. The first 8 bytes are the common fields
+// of all the tracepoints.
+const ACT_OFFSET: usize = 12;
+const IFINDEX_OFFSET: usize = 16;
+
+// Number of exceptions for every interface and action. The Userspace program sums the values for
+// all the CPUs.
+#[map]
+static XDP_EXCEPTIONS: PerCpuHashMap<ExceptionKey, u64> =
+    PerCpuHashMap::<ExceptionKey, u64>::with_max_entries(1024, 0);
+
+#[tracepoint]
+pub fn {{to_snake_case tutorial_name}}(ctx: TracePointContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => 1,
+    }
+}
+
+// The `xdp:xdp_exception` tracepoint is hit whenever an XDP program returns `XDP_ABORTED` (or an
+// invalid action), or when the driver fails to perform the action returned by the program (for
+// example, when `XDP_TX` fails).
+fn try_{{to_snake_case tutorial_name}}(ctx: &TracePointContext) -> Result<u32, i64> {
+    let action: u32 = unsafe { ctx.read_at(ACT_OFFSET)? };
+    let ifindex: i32 = unsafe { ctx.read_at(IFINDEX_OFFSET)? };
+
+    let key = ExceptionKey {
+        ifindex: ifindex as u32,
+        action,
+    };
+    match XDP_EXCEPTIONS.get_ptr_mut(&key) {
+        // This is a Per CPU value, no other CPU updates it.
+        Some(count) => unsafe { *count += 1 },
+        None => XDP_EXCEPTIONS.insert(&key, &1, 0)?,
+    }
+
+    Ok(0)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -0,0 +1,53 @@
+// The following `no_std` is required for compiling for the eBPF target. That also means, care
+// should be taken that the code here needs to use `core::*` definitions and not `std::*`
+// definitions.
+#![no_std]
+
+/// Maximum number of bytes of a packet that are sampled.
+pub const MAX_SNAPLEN: u32 = 65535;
+
+/// Configuration of the sampling, set by the Userspace program in the `SAMPLING_CONFIG` map and
+/// read by the eBPF program.
+#[repr(C)]
+#[cfg_attr(feature = "user", derive(Copy, Debug, Clone))]
+pub struct SamplingConfig {
+    /// The `xdp_action` returned by the program for all the packets.
+    pub action: u32,
+
+    /// One in every `sample_rate` packets is sampled (on average), `0` and `1` sample all packets.
+    pub sample_rate: u32,
+
+    /// Maximum number of bytes of a packet that are sampled.
+    pub snaplen: u32,
+
+    pub _pad: u32,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for SamplingConfig {}
+
+/// Metadata of a sampled packet.
+///
+/// Every sample in the `SAMPLES` map starts with this structure, followed by the first
+/// `cap_len` bytes of the packet.
+#[repr(C)]
+#[cfg_attr(feature = "user", derive(Copy, Debug, Clone))]
+pub struct SampleMeta {
+    /// Time the packet was sampled, from `bpf_ktime_get_ns` (`CLOCK_MONOTONIC`).
+    pub timestamp_ns: u64,
+
+    /// Index of the interface on which the packet was received.
+    pub ifindex: u32,
+
+    /// The `xdp_action` returned by the program for the packet.
+    pub action: u32,
+
+    /// Length of the packet.
+    pub pkt_len: u32,
+
+    /// Number of bytes of the packet following the metadata in the sample.
+    pub cap_len: u32,
+}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for SampleMeta {}
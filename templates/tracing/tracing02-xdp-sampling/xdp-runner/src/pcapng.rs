@@ -0,0 +1,284 @@
+// A minimal writer for the `pcapng` file format, that can be read by Wireshark and `tcpdump`.
+//
+// Only the blocks needed for writing the sampled packets are supported - the Section Header Block,
+// an Interface Description Block for every interface and an Enhanced Packet Block for every
+// packet. All the blocks are written in the host byte order, the readers detect the byte order
+// from the Section Header Block. See https://www.ietf.org/archive/id/draft-ietf-opsawg-pcapng-01.html
+//
+// This module does not depend on the rest of the runner and can be copied as it is to the other
+// tutorials.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x0000_0001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x0000_0006;
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+const LINKTYPE_ETHERNET: u16 = 1;
+
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_COMMENT: u16 = 1;
+const IF_NAME: u16 = 2;
+const IF_TSRESOL: u16 = 9;
+
+// Resolution of the timestamps, 10^-9 seconds.
+const TSRESOL_NSEC: u8 = 9;
+
+/// A packet to be written to the `pcapng` file.
+pub struct Packet<'a> {
+    /// Name of the interface on which the packet was captured.
+    pub iface: &'a str,
+
+    /// Time of the capture as nanoseconds since the UNIX Epoch.
+    pub timestamp_ns: u64,
+
+    /// Length of the packet on the wire.
+    pub orig_len: u32,
+
+    /// The captured bytes of the packet.
+    pub data: &'a [u8],
+
+    /// Optional comment attached to the packet (shown by Wireshark as 'Packet comments').
+    pub comment: Option<&'a str>,
+}
+
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+    snaplen: u32,
+    // Interface names to the Interface IDs (the order of the Interface Description Blocks).
+    interfaces: HashMap<String, u32>,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Creates a new writer and writes the Section Header Block.
+    pub fn new(writer: W, snaplen: u32) -> io::Result<Self> {
+        let mut pcapng = Self {
+            writer,
+            snaplen,
+            interfaces: HashMap::new(),
+        };
+
+        let mut body = vec![];
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_ne_bytes());
+        body.extend_from_slice(&1u16.to_ne_bytes()); // Major Version
+        body.extend_from_slice(&0u16.to_ne_bytes()); // Minor Version
+        body.extend_from_slice(&(-1i64).to_ne_bytes()); // Section Length (not specified)
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        pcapng.write_block(SECTION_HEADER_BLOCK, &body)?;
+
+        Ok(pcapng)
+    }
+
+    /// Writes the packet, an Interface Description Block is written first if this is the first
+    /// packet on the interface.
+    pub fn write_packet(&mut self, packet: &Packet) -> io::Result<()> {
+        let interface_id = self.interface_id(packet.iface)?;
+
+        let mut body = vec![];
+        body.extend_from_slice(&interface_id.to_ne_bytes());
+        body.extend_from_slice(&((packet.timestamp_ns >> 32) as u32).to_ne_bytes());
+        body.extend_from_slice(&(packet.timestamp_ns as u32).to_ne_bytes());
+        body.extend_from_slice(&(packet.data.len() as u32).to_ne_bytes());
+        body.extend_from_slice(&packet.orig_len.to_ne_bytes());
+        body.extend_from_slice(packet.data);
+        body.resize(padded_len(body.len()), 0);
+        if let Some(comment) = packet.comment {
+            push_option(&mut body, OPT_COMMENT, comment.as_bytes());
+            push_option(&mut body, OPT_ENDOFOPT, &[]);
+        }
+
+        self.write_block(ENHANCED_PACKET_BLOCK, &body)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn interface_id(&mut self, iface: &str) -> io::Result<u32> {
+        if let Some(id) = self.interfaces.get(iface) {
+            return Ok(*id);
+        }
+
+        let mut body = vec![];
+        body.extend_from_slice(&LINKTYPE_ETHERNET.to_ne_bytes());
+        body.extend_from_slice(&0u16.to_ne_bytes()); // Reserved
+        body.extend_from_slice(&self.snaplen.to_ne_bytes());
+        push_option(&mut body, IF_NAME, iface.as_bytes());
+        push_option(&mut body, IF_TSRESOL, &[TSRESOL_NSEC]);
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        self.write_block(INTERFACE_DESCRIPTION_BLOCK, &body)?;
+
+        let id = self.interfaces.len() as u32;
+        self.interfaces.insert(iface.to_string(), id);
+
+        Ok(id)
+    }
+
+    // Every block starts with the block type and the total length of the block and ends with the
+    // total length again. The `body` is always a multiple of 4 bytes.
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> io::Result<()> {
+        let total_len = (body.len() + 12) as u32;
+        self.writer.write_all(&block_type.to_ne_bytes())?;
+        self.writer.write_all(&total_len.to_ne_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&total_len.to_ne_bytes())
+    }
+}
+
+// Length rounded up to a multiple of 4 bytes.
+fn padded_len(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+// Options are a code, the length of the value and the value padded to a multiple of 4 bytes.
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_ne_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_ne_bytes());
+    body.extend_from_slice(value);
+    body.resize(padded_len(body.len()), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_ne_bytes([b[at], b[at + 1]])
+    }
+
+    fn u32_at(b: &[u8], at: usize) -> u32 {
+        u32::from_ne_bytes(b[at..at + 4].try_into().unwrap())
+    }
+
+    // Splits the file into the blocks (type, total length and body), checking that every block
+    // ends with its total length, which is a multiple of 4 bytes.
+    fn blocks(mut data: &[u8]) -> Vec<(u32, usize, &[u8])> {
+        let mut blocks = vec![];
+        while !data.is_empty() {
+            let total_len = u32_at(data, 4) as usize;
+            assert_eq!(total_len % 4, 0);
+            assert_eq!(u32_at(data, total_len - 4) as usize, total_len);
+            blocks.push((u32_at(data, 0), total_len, &data[8..total_len - 4]));
+            data = &data[total_len..];
+        }
+
+        blocks
+    }
+
+    fn packet<'a>(iface: &'a str, data: &'a [u8], comment: Option<&'a str>) -> Packet<'a> {
+        Packet {
+            iface,
+            timestamp_ns: 0x0000_0001_0000_0002,
+            orig_len: 1500,
+            data,
+            comment,
+        }
+    }
+
+    fn write(snaplen: u32, packets: &[Packet]) -> Vec<u8> {
+        let mut pcapng = PcapngWriter::new(vec![], snaplen).unwrap();
+        for packet in packets {
+            pcapng.write_packet(packet).unwrap();
+        }
+
+        pcapng.writer
+    }
+
+    #[test]
+    fn section_header_block() {
+        let data = write(256, &[]);
+        let blocks = blocks(&data);
+        assert_eq!(blocks.len(), 1);
+
+        let (block_type, total_len, body) = blocks[0];
+        assert_eq!(block_type, SECTION_HEADER_BLOCK);
+        assert_eq!(total_len, 32);
+        assert_eq!(u32_at(body, 0), BYTE_ORDER_MAGIC);
+        assert_eq!((u16_at(body, 4), u16_at(body, 6)), (1, 0));
+        assert_eq!(&body[8..16], &(-1i64).to_ne_bytes());
+        assert_eq!(&body[16..], &[0; 4]);
+    }
+
+    #[test]
+    fn interface_description_block_for_every_interface() {
+        let data = write(
+            256,
+            &[
+                packet("eth0", &[0; 4], None),
+                packet("eth0", &[0; 4], None),
+                packet("veth1", &[0; 4], None),
+            ],
+        );
+        let blocks = blocks(&data);
+        let types: Vec<_> = blocks
+            .iter()
+            .map(|(block_type, _, _)| *block_type)
+            .collect();
+        assert_eq!(
+            types,
+            [
+                SECTION_HEADER_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK,
+                ENHANCED_PACKET_BLOCK,
+                ENHANCED_PACKET_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK,
+                ENHANCED_PACKET_BLOCK,
+            ]
+        );
+
+        // Link type, reserved, snaplen, `if_name` (4 bytes), `if_tsresol` (1 byte padded to 4)
+        // and the end of the options.
+        let (_, total_len, body) = blocks[1];
+        assert_eq!(total_len, 12 + 8 + 8 + 8 + 4);
+        assert_eq!(u16_at(body, 0), LINKTYPE_ETHERNET);
+        assert_eq!(u32_at(body, 4), 256);
+        assert_eq!((u16_at(body, 8), u16_at(body, 10)), (IF_NAME, 4));
+        assert_eq!(&body[12..16], b"eth0");
+        assert_eq!((u16_at(body, 16), u16_at(body, 18)), (IF_TSRESOL, 1));
+        assert_eq!(&body[20..24], &[TSRESOL_NSEC, 0, 0, 0]);
+        assert_eq!(&body[24..], &[0; 4]);
+
+        // The name of 5 bytes is padded to 8 bytes.
+        let (_, total_len, body) = blocks[4];
+        assert_eq!(total_len, 12 + 8 + 12 + 8 + 4);
+        assert_eq!(u16_at(body, 10), 5);
+        assert_eq!(&body[12..20], b"veth1\0\0\0");
+
+        let interface_ids: Vec<_> = [2, 3, 5].iter().map(|i| u32_at(blocks[*i].2, 0)).collect();
+        assert_eq!(interface_ids, [0, 0, 1]);
+    }
+
+    #[test]
+    fn enhanced_packet_block_padding() {
+        let payload = [0xAAu8; 8];
+        for len in 1..=8 {
+            let data = write(256, &[packet("eth0", &payload[..len], None)]);
+            let (block_type, total_len, body) = blocks(&data)[2];
+            assert_eq!(block_type, ENHANCED_PACKET_BLOCK);
+            assert_eq!(total_len, 12 + 20 + padded_len(len));
+
+            // Interface ID, timestamp (high and low), captured length and original length.
+            assert_eq!(u32_at(body, 0), 0);
+            assert_eq!((u32_at(body, 4), u32_at(body, 8)), (1, 2));
+            assert_eq!(u32_at(body, 12) as usize, len);
+            assert_eq!(u32_at(body, 16), 1500);
+            assert_eq!(&body[20..20 + len], &payload[..len]);
+            assert!(body[20 + len..].iter().all(|b| *b == 0));
+        }
+    }
+
+    #[test]
+    fn enhanced_packet_block_comment() {
+        let data = write(256, &[packet("eth0", &[1, 2, 3], Some("dropped"))]);
+        let (_, total_len, body) = blocks(&data)[2];
+        // The packet (3 bytes padded to 4), `opt_comment` (7 bytes padded to 8) and the end of
+        // the options.
+        assert_eq!(total_len, 12 + 20 + 4 + 12 + 4);
+        assert_eq!(&body[20..24], &[1, 2, 3, 0]);
+        assert_eq!((u16_at(body, 24), u16_at(body, 26)), (OPT_COMMENT, 7));
+        assert_eq!(&body[28..36], b"dropped\0");
+        assert_eq!(&body[36..], &[0; 4]);
+    }
+}
@@ -0,0 +1,253 @@
+mod pcapng;
+
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::fs::File;
+use std::io::BufWriter;
+use std::mem;
+
+use anyhow::Context;
+
+use aya::maps::{Array, AsyncPerfEventArray};
+use aya::programs::{Xdp, XdpFlags};
+use aya::util::online_cpus;
+use aya::Ebpf;
+
+use bytes::BytesMut;
+use clap::{Parser, ValueEnum};
+use log::{info, warn};
+use tokio::signal;
+use tokio::sync::mpsc;
+
+use {{ to_snake_case tutorial_name }}_common::{SampleMeta, SamplingConfig, MAX_SNAPLEN};
+
+use pcapng::{Packet, PcapngWriter};
+
+#[derive(Debug, Parser)]
+struct Opt {
+    /// Action to be performed on all the received packets.
+    #[clap(short, long, value_enum, default_value_t = XdpAction::Pass)]
+    action: XdpAction,
+
+    /// Path of the `pcapng` file the sampled packets are written to.
+    #[clap(short, long, default_value = "{{tutorial_name}}.pcapng")]
+    output: String,
+
+    /// Maximum number of bytes of a packet that are sampled.
+    #[clap(short, long, default_value_t = 256)]
+    snaplen: u32,
+
+    /// Sample one in every `SAMPLE_RATE` packets (on average).
+    #[clap(long, default_value_t = 1)]
+    sample_rate: u32,
+
+    #[clap(short, long, default_value = "{{tutorial_name}}")]
+    file: String,
+
+    #[clap(short, long, default_value = "lo")]
+    iface: String,
+
+    #[clap(long)]
+    release: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum XdpAction {
+    /// Pass the packets to the kernel network stack
+    Pass,
+
+    /// Drop the packets
+    Drop,
+}
+
+// Names of the XDP Actions. The index in this array is the value of the action.
+const XDP_ACTION_NAMES: [&str; 5] = ["aborted", "drop", "pass", "tx", "redirect"];
+
+// Number of samples read from a perf ring buffer at a time.
+const PERF_BUFFERS: usize = 16;
+
+fn sampling_config_from_opts(opts: &Opt) -> Result<SamplingConfig, anyhow::Error> {
+    if opts.snaplen == 0 || opts.snaplen > MAX_SNAPLEN {
+        return Err(anyhow::Error::msg(format!(
+            "Invalid snaplen: {}, snaplen should be between 1 and {MAX_SNAPLEN}.",
+            opts.snaplen
+        )));
+    }
+    if opts.sample_rate == 0 {
+        return Err(anyhow::Error::msg("Sample rate should be at least 1."));
+    }
+
+    let action = match opts.action {
+        XdpAction::Pass => 2,
+        XdpAction::Drop => 1,
+    };
+
+    Ok(SamplingConfig {
+        action,
+        sample_rate: opts.sample_rate,
+        snaplen: opts.snaplen,
+        _pad: 0,
+    })
+}
+
+// Returns the name of the interface with the given index.
+fn iface_name(ifindex: u32) -> String {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let name = unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) };
+    if name.is_null() {
+        return format!("ifindex({ifindex})");
+    }
+
+    unsafe { CStr::from_ptr(name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn clock_ns(clock: libc::clockid_t) -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(clock, &mut ts) };
+
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
+
+// The timestamps of the samples are from `CLOCK_MONOTONIC` (time since boot), the `pcapng` file
+// needs the time since the UNIX Epoch. The difference between the two clocks is added to the
+// timestamps of the samples.
+fn monotonic_to_realtime_offset() -> u64 {
+    clock_ns(libc::CLOCK_REALTIME) - clock_ns(libc::CLOCK_MONOTONIC)
+}
+
+// Writes a sample (the `SampleMeta` followed by the packet bytes) to the `pcapng` file.
+fn write_sample(
+    pcapng: &mut PcapngWriter<BufWriter<File>>,
+    ifaces: &mut HashMap<u32, String>,
+    time_offset: u64,
+    sample: &[u8],
+) -> anyhow::Result<()> {
+    let meta_len = mem::size_of::<SampleMeta>();
+    if sample.len() < meta_len {
+        return Err(anyhow::Error::msg("Sample shorter than the metadata."));
+    }
+    let meta = unsafe { (sample.as_ptr() as *const SampleMeta).read_unaligned() };
+
+    // The sample may be padded, only `cap_len` bytes are the packet data.
+    let data_end = (meta_len + meta.cap_len as usize).min(sample.len());
+    let iface = ifaces
+        .entry(meta.ifindex)
+        .or_insert_with(|| iface_name(meta.ifindex));
+    let action = XDP_ACTION_NAMES
+        .get(meta.action as usize)
+        .copied()
+        .unwrap_or("unknown");
+    let comment = format!("action: {action}");
+
+    pcapng.write_packet(&Packet {
+        iface,
+        timestamp_ns: meta.timestamp_ns + time_offset,
+        orig_len: meta.pkt_len,
+        data: &sample[meta_len..data_end],
+        comment: Some(&comment),
+    })?;
+
+    Ok(())
+}
+
+// This is a Userspace program that is responsible for 'installing' the XDP eBPF binary in the
+// kernel and attaching this binary to a particular network interface.
+//
+// The eBPF program sends the sampled packets to a `PerfEventArray`, which has a ring buffer for
+// every CPU. A task is spawned for reading every ring buffer, the samples are sent over a channel
+// to the main task, which writes them to the `pcapng` file.
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let opts = Opt::parse();
+    env_logger::init();
+
+    let config = sampling_config_from_opts(&opts)?;
+
+    let profile = if opts.release { "release" } else { "debug" };
+    let bpf_bin = format!("target/bpfel-unknown-none/{}/{}", profile, opts.file);
+    let bpf_bin = std::fs::read(&bpf_bin)?;
+    let mut bpf = Ebpf::load(&bpf_bin)?;
+
+    let mut sampling_config = Array::try_from(bpf.map_mut("SAMPLING_CONFIG").unwrap())?;
+    sampling_config.set(0, config, 0)?;
+    info!("Sampling configuration: {:?}", config);
+
+    let (tx, mut rx) = mpsc::channel::<BytesMut>(1024);
+    let mut samples = AsyncPerfEventArray::try_from(bpf.take_map("SAMPLES").unwrap())?;
+    let cpus = online_cpus().map_err(|(msg, e)| anyhow::Error::new(e).context(msg))?;
+    for cpu in cpus {
+        let mut buf = samples.open(cpu, None)?;
+        let tx = tx.clone();
+        let capacity = mem::size_of::<SampleMeta>() + config.snaplen as usize + 8;
+
+        tokio::spawn(async move {
+            let mut buffers = (0..PERF_BUFFERS)
+                .map(|_| BytesMut::with_capacity(capacity))
+                .collect::<Vec<_>>();
+
+            loop {
+                let events = match buf.read_events(&mut buffers).await {
+                    Ok(events) => events,
+                    Err(e) => {
+                        warn!("Failed to read the samples on CPU {}: {}", cpu, e);
+                        break;
+                    }
+                };
+                if events.lost > 0 {
+                    warn!("Lost {} samples on CPU {}", events.lost, cpu);
+                }
+                for buffer in buffers.iter_mut().take(events.read) {
+                    if tx.send(buffer.split()).await.is_err() {
+                        return;
+                    }
+                    buffer.reserve(capacity);
+                }
+            }
+        });
+    }
+
+    let program_name = "{{to_snake_case tutorial_name}}";
+    let xdp: &mut Xdp = bpf
+        .program_mut(program_name)
+        .with_context(|| format!("Unable to find the program '{program_name}'"))?
+        .try_into()?;
+    xdp.load()?;
+    let _linkid = xdp
+        .attach(&opts.iface, XdpFlags::default())
+        .context("Failed to attach the program to the interface using the `XdpFlags::default()`, try using `XdpFlags::SKB_MODE`")?;
+
+    let file = File::create(&opts.output)
+        .with_context(|| format!("Failed to create the file '{}'", opts.output))?;
+    let mut pcapng = PcapngWriter::new(BufWriter::new(file), opts.snaplen)?;
+    let mut ifaces = HashMap::new();
+    let time_offset = monotonic_to_realtime_offset();
+    let mut count = 0u64;
+
+    info!(
+        "XDP Program '{}' attached to '{}'! Writing the samples to '{}', now waiting for Ctrl-C",
+        program_name, &opts.iface, &opts.output
+    );
+
+    loop {
+        tokio::select! {
+            Some(sample) = rx.recv() => {
+                write_sample(&mut pcapng, &mut ifaces, time_offset, &sample)?;
+                count += 1;
+            }
+            _ = signal::ctrl_c() => {
+                info!("Exiting...");
+                break;
+            }
+        }
+    }
+
+    pcapng.flush()?;
+    info!("Wrote {} samples to '{}'", count, &opts.output);
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+use aya_ebpf::{
+    bindings::xdp_action,
+    helpers::{bpf_get_prandom_u32, bpf_ktime_get_ns},
+    macros::{map, xdp},
+    maps::{Array, PerfEventArray},
+    programs::XdpContext,
+};
+
+use {{ to_snake_case tutorial_name }}_common::{SampleMeta, SamplingConfig, MAX_SNAPLEN};
+
+// The configuration is set by the Userspace program before the program is attached.
+#[map]
+static SAMPLING_CONFIG: Array<SamplingConfig> = Array::<SamplingConfig>::with_max_entries(1, 0);
+
+// The sampled packets, one perf ring buffer per CPU is read by the Userspace program.
+#[map]
+static SAMPLES: PerfEventArray<SampleMeta> = PerfEventArray::<SampleMeta>::new(0);
+
+#[xdp]
+pub fn {{to_snake_case tutorial_name}}(ctx: XdpContext) -> u32 {
+    match try_{{to_snake_case tutorial_name}}(&ctx) {
+        Ok(ret) => ret,
+        Err(_) => xdp_action::XDP_ABORTED,
+    }
+}
+
+// Returns the configured action for all the packets, the sampled packets are sent to the
+// Userspace along with the metadata (`SampleMeta`).
+fn try_{{to_snake_case tutorial_name}}(ctx: &XdpContext) -> Result<u32, u32> {
+    let Some(config) = SAMPLING_CONFIG.get(0) else {
+        return Err(xdp_action::XDP_ABORTED);
+    };
+
+    if config.sample_rate > 1 && unsafe { bpf_get_prandom_u32() } % config.sample_rate != 0 {
+        return Ok(config.action);
+    }
+
+    let pkt_len = (ctx.data_end() - ctx.data()) as u32;
+    let cap_len = pkt_len.min(config.snaplen).min(MAX_SNAPLEN);
+    let meta = SampleMeta {
+        timestamp_ns: unsafe { bpf_ktime_get_ns() },
+        ifindex: unsafe { (*ctx.ctx).ingress_ifindex },
+        action: config.action,
+        pkt_len,
+        cap_len,
+    };
+
+    // The `flags` of the output are the number of bytes from the start of the packet, that are
+    // appended to the sample by the kernel after the `meta`. This avoids copying the packet data
+    // (of variable length) in the program.
+    SAMPLES.output(ctx, &meta, cap_len);
+
+    Ok(config.action)
+}
+
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    unsafe { core::hint::unreachable_unchecked() }
+}
@@ -437,20 +437,36 @@ print_alias()
 }
 
 #
-# This command can be used to populate maps for the assignment 3 of the
-# packet03-redirecting lesson. It takes two arguments: the source and the
-# destination environment names.
+# This command can be used to set up the redirects between two environments
+# for the 'redirect' mode of the packet03-redirecting tutorial. It takes two
+# arguments: the source and the destination environment names, and optionally
+# the name of the tutorial (default: packet03-redirecting). A runner is started
+# (using 'cargo xtask run' from the current directory, the root of the XDP
+# project) for each direction, both are stopped on Ctrl-C.
 #
 populate_redirect_map()
 {
     local src="$1"
     local dest="$2"
+    local tutorial="${3:-packet03-redirecting}"
+    local pids
+
+    [ -n "$src" ] && [ -n "$dest" ] || die "Usage: redirect <env1> <env2> [<tutorial>]"
+    command -v cargo >/dev/null || die "'cargo' not found, run from the root of the XDP project"
+
     local src_mac=$(ip netns exec $src cat /sys/class/net/veth0/address)
     local dest_mac=$(ip netns exec $dest cat /sys/class/net/veth0/address)
 
     # set bidirectional forwarding
-    ./xdp_prog_user -d $src -r $dest --src-mac $src_mac --dest-mac $dest_mac
-    ./xdp_prog_user -d $dest -r $src --src-mac $dest_mac --dest-mac $src_mac
+    cargo xtask run "$tutorial" -- --iface "$src" --mode redirect --redirect-iface "$dest" \
+          --src-mac "$src_mac" --dest-mac "$dest_mac" &
+    pids="$!"
+    cargo xtask run "$tutorial" -- --iface "$dest" --mode redirect --redirect-iface "$src" \
+          --src-mac "$dest_mac" --dest-mac "$src_mac" &
+    pids="$pids $!"
+
+    trap "kill -INT $pids 2>/dev/null" INT TERM
+    wait $pids
 }
 
 xdp_load()
@@ -496,7 +512,8 @@ usage()
     echo "unload                  Unload XDP program on outer interface"
     echo "tcpdump                 Run on outer interface (or inner with --inner)"
     echo "stats                   Run the XDP statistics program"
-    echo "redirect <env1> <env2>  Setup redirects for packet03 lessons"
+    echo "redirect <env1> <env2> [<tutorial>]"
+    echo "                        Run the packet03 redirect between the environments"
     echo ""
 
     if [ -z "$FULL" ] ; then